@@ -0,0 +1,138 @@
+//! Concurrent throughput of the Redis-backed stores, shared the way `AppState`
+//! shares them. Needs a Redis server at `REDIS_HOST_NAME`, 127.0.0.1 by default:
+//!
+//! ```bash
+//! cargo bench --bench redis_stores
+//! ```
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use secrecy::Secret;
+use tokio::{runtime::Runtime, task::JoinSet};
+
+use auth_service::{
+    domain::{
+        data_stores::{
+            BannedTokenStore, LoginAttemptId, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+        },
+        Email,
+    },
+    get_redis_connection,
+    services::data_stores::{RedisBannedTokenStore, RedisConnection, RedisTwoFACodeStore},
+    settings::RedisSettings,
+};
+
+/// Number of requests in flight at once.
+const CONCURRENCY: [usize; 3] = [1, 16, 128];
+
+const TOKEN: &str = "bench-token";
+const EMAIL: &str = "bench@example.com";
+
+fn connect(runtime: &Runtime) -> RedisConnection {
+    let settings = RedisSettings {
+        mode: "standalone".to_owned(),
+        host_name: std::env::var("REDIS_HOST_NAME").unwrap_or_else(|_| "127.0.0.1".to_owned()),
+        nodes: Vec::new(),
+        sentinel_service_name: String::new(),
+        tls: false,
+        username: None,
+        password: None,
+        key_namespace: "bench:".to_owned(),
+        connection_retries: 0,
+        max_retry_delay_secs: 1,
+        connection_timeout_secs: 2,
+        response_timeout_secs: 2,
+    };
+
+    runtime
+        .block_on(get_redis_connection(&settings))
+        .expect("Failed to connect to Redis")
+}
+
+/// Runs `concurrency` copies of `request` on separate tasks and waits for all of them.
+async fn run_concurrently<F, Fut>(concurrency: usize, request: F)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    for _ in 0..concurrency {
+        tasks.spawn(request());
+    }
+    while let Some(result) = tasks.join_next().await {
+        result.expect("Request panicked");
+    }
+}
+
+fn banned_token_store(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let store = RedisBannedTokenStore::new(connect(&runtime), Duration::from_secs(600));
+    runtime
+        .block_on(store.add_token(TOKEN.to_owned()))
+        .expect("Failed to ban token");
+    let store = Arc::new(store);
+
+    let mut group = c.benchmark_group("banned_token_store/contains_token");
+    for concurrency in CONCURRENCY {
+        group.throughput(Throughput::Elements(concurrency as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    run_concurrently(concurrency, || {
+                        let store = store.clone();
+                        async move {
+                            let is_banned = store.contains_token(TOKEN).await;
+                            assert!(is_banned.unwrap());
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn two_fa_code_store(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let email = Email::parse(EMAIL.to_owned()).unwrap();
+    let store = RedisTwoFACodeStore::new(connect(&runtime));
+    runtime
+        .block_on(store.add_code(
+            email.clone(),
+            &TwoFACodePurpose::Login,
+            LoginAttemptId::default(),
+            TwoFACode::parse(Secret::new("123456".to_owned())).unwrap(),
+        ))
+        .expect("Failed to add 2FA code");
+    let store = Arc::new(store);
+
+    let mut group = c.benchmark_group("two_fa_code_store/get_code");
+    for concurrency in CONCURRENCY {
+        group.throughput(Throughput::Elements(concurrency as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.to_async(&runtime).iter(|| {
+                    run_concurrently(concurrency, || {
+                        let store = store.clone();
+                        let email = email.clone();
+                        async move {
+                            assert!(store
+                                .get_code(&email, &TwoFACodePurpose::Login)
+                                .await
+                                .is_ok());
+                        }
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, banned_token_store, two_fa_code_store);
+criterion_main!(benches);
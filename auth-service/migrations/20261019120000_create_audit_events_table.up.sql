@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS audit_events(
+   id BIGSERIAL PRIMARY KEY,
+   event_type TEXT NOT NULL,
+   email TEXT,
+   ip_address TEXT,
+   user_agent TEXT,
+   request_id TEXT,
+   occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_email_occurred_at_idx
+   ON audit_events (email, occurred_at);
+
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx
+   ON audit_events (occurred_at);
+
+-- The audit log is append-only: rows can never be changed or removed.
+CREATE OR REPLACE FUNCTION reject_audit_event_modification() RETURNS TRIGGER AS $$
+BEGIN
+   RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+   BEFORE UPDATE OR DELETE ON audit_events
+   FOR EACH ROW EXECUTE FUNCTION reject_audit_event_modification();
+
+CREATE TRIGGER audit_events_no_truncate
+   BEFORE TRUNCATE ON audit_events
+   FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_event_modification();
@@ -0,0 +1,34 @@
+CREATE TABLE IF NOT EXISTS webhook_subscriptions(
+   id UUID NOT NULL PRIMARY KEY,
+   url TEXT NOT NULL,
+   secret TEXT NOT NULL,
+   event_types TEXT[] NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries(
+   id UUID NOT NULL PRIMARY KEY,
+   subscription_id UUID NOT NULL REFERENCES webhook_subscriptions(id) ON DELETE CASCADE,
+   event_id UUID NOT NULL,
+   event_type TEXT NOT NULL,
+   payload TEXT NOT NULL,
+   status TEXT NOT NULL DEFAULT 'pending',
+   attempts INTEGER NOT NULL DEFAULT 0,
+   next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due_idx
+   ON webhook_deliveries (next_attempt_at)
+   WHERE status = 'pending';
+
+CREATE TABLE IF NOT EXISTS webhook_delivery_attempts(
+   id BIGSERIAL PRIMARY KEY,
+   delivery_id UUID NOT NULL REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+   attempted_at TIMESTAMPTZ NOT NULL,
+   response_status INTEGER,
+   error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS webhook_delivery_attempts_delivery_id_idx
+   ON webhook_delivery_attempts (delivery_id);
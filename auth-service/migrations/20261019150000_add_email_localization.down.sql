@@ -0,0 +1,8 @@
+ALTER TABLE email_outbox
+   DROP COLUMN IF EXISTS html_body;
+
+ALTER TABLE email_outbox
+   RENAME COLUMN text_body TO content;
+
+ALTER TABLE users
+   DROP COLUMN IF EXISTS locale;
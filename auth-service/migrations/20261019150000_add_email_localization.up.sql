@@ -0,0 +1,8 @@
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS locale TEXT NOT NULL DEFAULT 'en';
+
+ALTER TABLE email_outbox
+   RENAME COLUMN content TO text_body;
+
+ALTER TABLE email_outbox
+   ADD COLUMN html_body TEXT NOT NULL DEFAULT '';
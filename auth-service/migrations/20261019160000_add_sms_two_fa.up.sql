@@ -0,0 +1,4 @@
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS phone_number TEXT,
+   ADD COLUMN IF NOT EXISTS phone_verified BOOLEAN NOT NULL DEFAULT FALSE,
+   ADD COLUMN IF NOT EXISTS two_fa_channel TEXT NOT NULL DEFAULT 'email';
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS password_history(
+   id BIGSERIAL PRIMARY KEY,
+   email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+   password_hash TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS password_history_email_id_idx
+   ON password_history (email, id);
+
+-- Every user's current password is the newest entry in their history.
+INSERT INTO password_history (email, password_hash)
+   SELECT email, password_hash FROM users;
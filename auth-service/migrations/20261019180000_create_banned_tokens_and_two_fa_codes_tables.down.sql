@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS two_fa_codes;
+DROP TABLE IF EXISTS banned_tokens;
@@ -0,0 +1,10 @@
+-- Tokens issued to a user before their epoch (Unix seconds) are banned. Like
+-- banned_tokens, only used when the stores are configured to live here.
+CREATE TABLE IF NOT EXISTS token_epochs(
+   email TEXT NOT NULL PRIMARY KEY,
+   epoch BIGINT NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS token_epochs_expires_at_idx
+   ON token_epochs (expires_at);
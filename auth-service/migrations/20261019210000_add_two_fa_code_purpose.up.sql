@@ -0,0 +1,9 @@
+-- A user can hold a login code and a phone verification code at once
+ALTER TABLE two_fa_codes
+   ADD COLUMN IF NOT EXISTS purpose TEXT NOT NULL DEFAULT 'login';
+
+ALTER TABLE two_fa_codes
+   DROP CONSTRAINT IF EXISTS two_fa_codes_pkey;
+
+ALTER TABLE two_fa_codes
+   ADD PRIMARY KEY (email, purpose);
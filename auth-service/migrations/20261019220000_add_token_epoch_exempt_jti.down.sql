@@ -0,0 +1,2 @@
+ALTER TABLE token_epochs
+   DROP COLUMN IF EXISTS exempt_jti;
@@ -0,0 +1,3 @@
+-- The token issued alongside the epoch, which its second would otherwise ban
+ALTER TABLE token_epochs
+   ADD COLUMN IF NOT EXISTS exempt_jti TEXT;
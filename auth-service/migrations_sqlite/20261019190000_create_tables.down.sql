@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS two_fa_codes;
+DROP TABLE IF EXISTS banned_tokens;
+DROP TABLE IF EXISTS password_history;
+DROP TABLE IF EXISTS users;
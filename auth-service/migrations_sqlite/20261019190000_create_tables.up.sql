@@ -0,0 +1,39 @@
+-- SQLite schema for single-node deployments. Only the user, banned token and
+-- 2FA code stores live in SQLite; timestamps are RFC 3339 text in UTC.
+CREATE TABLE IF NOT EXISTS users(
+   email TEXT NOT NULL PRIMARY KEY,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   locale TEXT NOT NULL DEFAULT 'en',
+   phone_number TEXT,
+   phone_verified BOOLEAN NOT NULL DEFAULT FALSE,
+   two_fa_channel TEXT NOT NULL DEFAULT 'email'
+);
+
+CREATE TABLE IF NOT EXISTS password_history(
+   id INTEGER PRIMARY KEY AUTOINCREMENT,
+   email TEXT NOT NULL REFERENCES users (email) ON DELETE CASCADE ON UPDATE CASCADE,
+   password_hash TEXT NOT NULL,
+   created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'))
+);
+
+CREATE INDEX IF NOT EXISTS password_history_email_id_idx
+   ON password_history (email, id);
+
+CREATE TABLE IF NOT EXISTS banned_tokens(
+   token TEXT NOT NULL PRIMARY KEY,
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS banned_tokens_expires_at_idx
+   ON banned_tokens (expires_at);
+
+CREATE TABLE IF NOT EXISTS two_fa_codes(
+   email TEXT NOT NULL PRIMARY KEY,
+   login_attempt_id TEXT NOT NULL,
+   code TEXT NOT NULL,
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx
+   ON two_fa_codes (expires_at);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS token_epochs(
+   email TEXT NOT NULL PRIMARY KEY,
+   epoch INTEGER NOT NULL,
+   expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS token_epochs_expires_at_idx
+   ON token_epochs (expires_at);
@@ -0,0 +1,17 @@
+CREATE TABLE two_fa_codes_old(
+   email TEXT NOT NULL PRIMARY KEY,
+   login_attempt_id TEXT NOT NULL,
+   code TEXT NOT NULL,
+   expires_at TEXT NOT NULL
+);
+
+INSERT INTO two_fa_codes_old (email, login_attempt_id, code, expires_at)
+   SELECT email, login_attempt_id, code, expires_at FROM two_fa_codes
+   WHERE purpose = 'login';
+
+DROP TABLE two_fa_codes;
+
+ALTER TABLE two_fa_codes_old RENAME TO two_fa_codes;
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx
+   ON two_fa_codes (expires_at);
@@ -0,0 +1,20 @@
+-- A user can hold a login code and a phone verification code at once. SQLite
+-- cannot change a primary key in place, so the table is rebuilt.
+CREATE TABLE two_fa_codes_new(
+   email TEXT NOT NULL,
+   purpose TEXT NOT NULL DEFAULT 'login',
+   login_attempt_id TEXT NOT NULL,
+   code TEXT NOT NULL,
+   expires_at TEXT NOT NULL,
+   PRIMARY KEY (email, purpose)
+);
+
+INSERT INTO two_fa_codes_new (email, login_attempt_id, code, expires_at)
+   SELECT email, login_attempt_id, code, expires_at FROM two_fa_codes;
+
+DROP TABLE two_fa_codes;
+
+ALTER TABLE two_fa_codes_new RENAME TO two_fa_codes;
+
+CREATE INDEX IF NOT EXISTS two_fa_codes_expires_at_idx
+   ON two_fa_codes (expires_at);
@@ -0,0 +1,2 @@
+ALTER TABLE token_epochs
+   DROP COLUMN exempt_jti;
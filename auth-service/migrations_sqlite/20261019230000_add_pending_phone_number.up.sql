@@ -0,0 +1,3 @@
+-- A new number waiting for its code, so that the verified one keeps working until then
+ALTER TABLE users
+   ADD COLUMN pending_phone_number TEXT;
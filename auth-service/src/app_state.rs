@@ -1,33 +1,298 @@
+use color_eyre::eyre::{eyre, Context, Result};
+use sqlx::{PgPool, SqlitePool};
 use std::sync::Arc;
-use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    domain::{
+        AuditLogStore, BannedTokenStore, BreachedPasswordChecker, EmailClient, EmailOutboxStore,
+        RateLimitStore, SmsClient, TwoFACodeStore, UserStore, WebhookStore,
+    },
+    get_redis_connection,
+    services::{
+        data_stores::{
+            HashmapEmailOutboxStore, HashmapRateLimitStore, HashmapTwoFACodeStore,
+            HashmapWebhookStore, HashsetBannedTokenStore, PostgresAuditLogStore,
+            PostgresBannedTokenStore, PostgresEmailOutboxStore, PostgresTwoFACodeStore,
+            PostgresUserStore, PostgresWebhookStore, RedisBannedTokenStore, RedisRateLimitStore,
+            RedisTwoFACodeStore, SqliteBannedTokenStore, SqliteTwoFACodeStore, SqliteUserStore,
+            VecAuditLogStore,
+        },
+        email_outbox_worker::FinishedEmailPruner,
+        expired_entry_sweeper::{ExpiredEntrySweeper, ExpiringStoreType},
+        file_email_client::DevMailbox,
+        postgres_revocation_broadcaster::PostgresRevocationBroadcaster,
+        redis_revocation_broadcaster::RedisRevocationBroadcaster,
+        revocation_cache::{CachedBannedTokenStore, RevocationBroadcasterType, RevocationCache},
+    },
+    settings::{RevocationCacheSettings, Settings},
+    utils::rate_limit::RateLimiter,
+};
 
-pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
-pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
-pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
+// These stores are shared by every request, so they handle their own locking
+// rather than sit behind one lock for the whole store.
+pub type UserStoreType = Arc<dyn UserStore + Send + Sync>;
+pub type BannedTokenStoreType = Arc<dyn BannedTokenStore + Send + Sync>;
+pub type TwoFACodeStoreType = Arc<dyn TwoFACodeStore + Send + Sync>;
+pub type RateLimitStoreType = Arc<dyn RateLimitStore + Send + Sync>;
+pub type AuditLogStoreType = Arc<dyn AuditLogStore + Send + Sync>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type SmsClientType = Arc<dyn SmsClient + Send + Sync>;
+pub type BreachedPasswordCheckerType = Arc<dyn BreachedPasswordChecker + Send + Sync>;
+pub type EmailOutboxStoreType = Arc<dyn EmailOutboxStore + Send + Sync>;
+pub type WebhookStoreType = Arc<dyn WebhookStore + Send + Sync>;
 
 #[derive(Clone)]
 pub struct AppState {
+    pub settings: Arc<Settings>,
     pub user_store: UserStoreType,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
-    pub email_client: EmailClientType,
+    pub email_outbox_store: EmailOutboxStoreType,
+    pub sms_client: SmsClientType,
+    /// Unset when breached password checks are disabled.
+    pub breached_password_checker: Option<BreachedPasswordCheckerType>,
+    pub rate_limiter: RateLimiter,
+    pub audit_log_store: AuditLogStoreType,
+    pub webhook_store: WebhookStoreType,
+    /// Set outside production to expose captured emails under `/dev/mailbox`.
+    pub dev_mailbox: Option<DevMailbox>,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        settings: Arc<Settings>,
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
-        email_client: EmailClientType,
+        email_outbox_store: EmailOutboxStoreType,
+        sms_client: SmsClientType,
+        breached_password_checker: Option<BreachedPasswordCheckerType>,
+        rate_limiter: RateLimiter,
+        audit_log_store: AuditLogStoreType,
+        webhook_store: WebhookStoreType,
+        dev_mailbox: Option<DevMailbox>,
     ) -> Self {
         Self {
+            settings,
             user_store,
             banned_token_store,
             two_fa_code_store,
-            email_client,
+            email_outbox_store,
+            sms_client,
+            breached_password_checker,
+            rate_limiter,
+            audit_log_store,
+            webhook_store,
+            dev_mailbox,
         }
     }
 }
+
+/// The database the users live in, picked by the scheme of `database.url`.
+pub enum Database {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+/// Every store the app uses, for the configured database and store backend.
+pub struct Stores {
+    pub user_store: UserStoreType,
+    pub audit_log_store: AuditLogStoreType,
+    pub webhook_store: WebhookStoreType,
+    pub email_outbox_store: EmailOutboxStoreType,
+    pub banned_token_store: BannedTokenStoreType,
+    pub two_fa_code_store: TwoFACodeStoreType,
+    pub rate_limit_store: RateLimitStoreType,
+    /// Deletes expired entries and old finished emails, once the caller runs it.
+    pub expired_entry_sweeper: ExpiredEntrySweeper,
+}
+
+/// Builds the stores for the database and `stores.backend`, so that the binary
+/// and the tests wire them up the same way.
+pub async fn build_stores(settings: &Settings, database: &Database) -> Result<Stores> {
+    let (user_store, audit_log_store, webhook_store, email_outbox_store) =
+        build_persistent_stores(settings, database)?;
+    let (banned_token_store, two_fa_code_store, rate_limit_store, mut expiring_stores) =
+        build_short_lived_stores(settings, database).await?;
+
+    expiring_stores.push(Arc::new(FinishedEmailPruner::new(
+        email_outbox_store.clone(),
+        settings.email_outbox.retention(),
+    )));
+
+    Ok(Stores {
+        user_store,
+        audit_log_store,
+        webhook_store,
+        email_outbox_store,
+        banned_token_store,
+        two_fa_code_store,
+        rate_limit_store,
+        expired_entry_sweeper: ExpiredEntrySweeper::new(
+            expiring_stores,
+            settings.stores.sweep_interval(),
+        ),
+    })
+}
+
+/// SQLite only holds users, so on a single node the audit log, webhooks and
+/// email outbox are kept in memory.
+fn build_persistent_stores(
+    settings: &Settings,
+    database: &Database,
+) -> Result<(
+    UserStoreType,
+    AuditLogStoreType,
+    WebhookStoreType,
+    EmailOutboxStoreType,
+)> {
+    let password_hasher = settings
+        .password_hashing
+        .hasher()
+        .wrap_err("failed to build password hasher")?;
+    let history_size = settings.password_policy.history_size;
+
+    Ok(match database {
+        Database::Postgres(pool) => (
+            Arc::new(PostgresUserStore::new(
+                pool.clone(),
+                password_hasher,
+                history_size,
+            )),
+            Arc::new(PostgresAuditLogStore::new(pool.clone())),
+            Arc::new(PostgresWebhookStore::new(pool.clone())),
+            Arc::new(PostgresEmailOutboxStore::new(pool.clone())),
+        ),
+        Database::Sqlite(pool) => {
+            tracing::warn!(
+                "SQLite only persists users: the audit log, webhooks and queued emails \
+                 are kept in memory and lost on restart"
+            );
+            (
+                Arc::new(SqliteUserStore::new(
+                    pool.clone(),
+                    password_hasher,
+                    history_size,
+                )),
+                Arc::new(VecAuditLogStore::default()),
+                Arc::new(HashmapWebhookStore::default()),
+                Arc::new(HashmapEmailOutboxStore::default()),
+            )
+        }
+    })
+}
+
+/// Keeps banned tokens, 2FA codes and rate limit buckets in Redis, or in the
+/// database and memory, or only in memory, for deployments without Redis. Also
+/// returns the stores that need sweeping, as Redis expires its own keys.
+async fn build_short_lived_stores(
+    settings: &Settings,
+    database: &Database,
+) -> Result<(
+    BannedTokenStoreType,
+    TwoFACodeStoreType,
+    RateLimitStoreType,
+    Vec<ExpiringStoreType>,
+)> {
+    let token_ttl = settings.auth.token_ttl();
+
+    let (banned_token_store, two_fa_code_store, rate_limit_store, expiring_stores, broadcaster): (
+        BannedTokenStoreType,
+        TwoFACodeStoreType,
+        RateLimitStoreType,
+        Vec<ExpiringStoreType>,
+        Option<RevocationBroadcasterType>,
+    ) = match (settings.stores.backend.as_str(), database) {
+        ("redis", _) => {
+            let redis_connection =
+                get_redis_connection(&settings.redis)
+                    .await
+                    .wrap_err_with(|| {
+                        format!("failed to connect to Redis at {}", settings.redis.host_name)
+                    })?;
+            (
+                Arc::new(RedisBannedTokenStore::new(
+                    redis_connection.clone(),
+                    token_ttl,
+                )),
+                Arc::new(RedisTwoFACodeStore::new(redis_connection.clone())),
+                Arc::new(RedisRateLimitStore::new(redis_connection.clone())),
+                vec![],
+                Some(Arc::new(RedisRevocationBroadcaster::new(
+                    redis_connection,
+                    settings.redis.clone(),
+                ))),
+            )
+        }
+        ("database", Database::Postgres(pool)) => {
+            let banned_token_store =
+                Arc::new(PostgresBannedTokenStore::new(pool.clone(), token_ttl));
+            let two_fa_code_store = Arc::new(PostgresTwoFACodeStore::new(pool.clone()));
+            let rate_limit_store = Arc::new(HashmapRateLimitStore::default());
+            (
+                banned_token_store.clone(),
+                two_fa_code_store.clone(),
+                rate_limit_store.clone(),
+                vec![banned_token_store, two_fa_code_store, rate_limit_store],
+                Some(Arc::new(PostgresRevocationBroadcaster::new(pool.clone()))),
+            )
+        }
+        // A SQLite database is only ever used by one instance
+        ("database", Database::Sqlite(pool)) => {
+            let banned_token_store = Arc::new(SqliteBannedTokenStore::new(pool.clone(), token_ttl));
+            let two_fa_code_store = Arc::new(SqliteTwoFACodeStore::new(pool.clone()));
+            let rate_limit_store = Arc::new(HashmapRateLimitStore::default());
+            (
+                banned_token_store.clone(),
+                two_fa_code_store.clone(),
+                rate_limit_store.clone(),
+                vec![banned_token_store, two_fa_code_store, rate_limit_store],
+                None,
+            )
+        }
+        ("memory", _) => {
+            let banned_token_store = Arc::new(HashsetBannedTokenStore::new(token_ttl));
+            let two_fa_code_store = Arc::new(HashmapTwoFACodeStore::default());
+            let rate_limit_store = Arc::new(HashmapRateLimitStore::default());
+            (
+                banned_token_store.clone(),
+                two_fa_code_store.clone(),
+                rate_limit_store.clone(),
+                vec![banned_token_store, two_fa_code_store, rate_limit_store],
+                None,
+            )
+        }
+        (backend, _) => return Err(eyre!("unsupported store backend: {}", backend)),
+    };
+
+    Ok((
+        build_revocation_cache(&settings.revocation_cache, banned_token_store, broadcaster),
+        two_fa_code_store,
+        rate_limit_store,
+        expiring_stores,
+    ))
+}
+
+/// Fronts the banned token store with a cache, which listens for the
+/// revocations other instances broadcast.
+fn build_revocation_cache(
+    settings: &RevocationCacheSettings,
+    banned_token_store: BannedTokenStoreType,
+    broadcaster: Option<RevocationBroadcasterType>,
+) -> BannedTokenStoreType {
+    if !settings.enabled {
+        return banned_token_store;
+    }
+
+    let cache = Arc::new(RevocationCache::new(settings.capacity, settings.ttl()));
+    if let Some(broadcaster) = &broadcaster {
+        tokio::spawn(broadcaster.clone().listen(cache.clone()));
+    }
+
+    Arc::new(CachedBannedTokenStore::new(
+        banned_token_store,
+        cache,
+        broadcaster,
+    ))
+}
@@ -0,0 +1,33 @@
+//! Builds the index used by the `offline` breached password mode from a
+//! download of the Pwned Passwords SHA-1 corpus, e.g. one made with
+//! https://github.com/HaveIBeenPwned/PwnedPasswordsDownloader.
+//!
+//! Usage: build_hibp_index <download file or directory> <index path>
+
+use std::{path::PathBuf, time::Instant};
+
+use auth_service::services::hibp_offline_checker::HibpIndex;
+use color_eyre::eyre::Result;
+
+fn main() -> Result<()> {
+    color_eyre::install()?;
+
+    let mut args = std::env::args_os().skip(1);
+    let (Some(source), Some(destination), None) = (args.next(), args.next(), args.next()) else {
+        eprintln!("usage: build_hibp_index <download file or directory> <index path>");
+        std::process::exit(2);
+    };
+    let (source, destination) = (PathBuf::from(source), PathBuf::from(destination));
+
+    let started = Instant::now();
+    let count = HibpIndex::build(&source, &destination)?;
+
+    println!(
+        "Indexed {} hashes into {} in {:.1?}",
+        count,
+        destination.display(),
+        started.elapsed()
+    );
+
+    Ok(())
+}
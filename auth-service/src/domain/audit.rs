@@ -0,0 +1,138 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+
+use super::Email;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    Signup,
+    LoginSucceeded,
+    LoginFailed,
+    TwoFACodeSent,
+    TwoFAVerified,
+    TwoFAFailed,
+    Logout,
+    TokenRevoked,
+    PasswordChanged,
+    PhoneNumberVerified,
+}
+
+impl AuditEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::LoginSucceeded => "login_succeeded",
+            Self::LoginFailed => "login_failed",
+            Self::TwoFACodeSent => "two_fa_code_sent",
+            Self::TwoFAVerified => "two_fa_verified",
+            Self::TwoFAFailed => "two_fa_failed",
+            Self::Logout => "logout",
+            Self::TokenRevoked => "token_revoked",
+            Self::PasswordChanged => "password_changed",
+            Self::PhoneNumberVerified => "phone_number_verified",
+        }
+    }
+}
+
+impl FromStr for AuditEventType {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "signup" => Ok(Self::Signup),
+            "login_succeeded" => Ok(Self::LoginSucceeded),
+            "login_failed" => Ok(Self::LoginFailed),
+            "two_fa_code_sent" => Ok(Self::TwoFACodeSent),
+            "two_fa_verified" => Ok(Self::TwoFAVerified),
+            "two_fa_failed" => Ok(Self::TwoFAFailed),
+            "logout" => Ok(Self::Logout),
+            "token_revoked" => Ok(Self::TokenRevoked),
+            "password_changed" => Ok(Self::PasswordChanged),
+            "phone_number_verified" => Ok(Self::PhoneNumberVerified),
+            _ => Err(eyre!("Unknown audit event type: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for AuditEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub event_type: AuditEventType,
+    pub email: Option<Email>,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: AuditEventType, email: Option<Email>) -> Self {
+        Self {
+            event_type,
+            email,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Filters for reading back the audit log. Time bounds are `[from, to)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditLogQuery {
+    pub email: Option<Email>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: u32,
+}
+
+impl AuditLogQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.email
+            .as_ref()
+            .is_none_or(|email| event.email.as_ref() == Some(email))
+            && self.from.is_none_or(|from| event.occurred_at >= from)
+            && self.to.is_none_or(|to| event.occurred_at < to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_EVENT_TYPES: [AuditEventType; 10] = [
+        AuditEventType::Signup,
+        AuditEventType::LoginSucceeded,
+        AuditEventType::LoginFailed,
+        AuditEventType::TwoFACodeSent,
+        AuditEventType::TwoFAVerified,
+        AuditEventType::TwoFAFailed,
+        AuditEventType::Logout,
+        AuditEventType::TokenRevoked,
+        AuditEventType::PasswordChanged,
+        AuditEventType::PhoneNumberVerified,
+    ];
+
+    #[test]
+    fn event_types_round_trip_through_strings() {
+        for event_type in ALL_EVENT_TYPES {
+            assert_eq!(
+                event_type.as_str().parse::<AuditEventType>().unwrap(),
+                event_type
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_event_type_is_rejected() {
+        assert!("password_reset".parse::<AuditEventType>().is_err());
+    }
+}
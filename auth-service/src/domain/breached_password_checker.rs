@@ -0,0 +1,27 @@
+use color_eyre::eyre::Result;
+use secrecy::{ExposeSecret, Secret};
+use sha1::{Digest, Sha1};
+
+/// Looks passwords up in the Have I Been Pwned breach corpus.
+#[async_trait::async_trait]
+pub trait BreachedPasswordChecker {
+    async fn is_breached(&self, password: &Secret<String>) -> Result<bool>;
+}
+
+/// The uppercase hex SHA-1 of a password, the form the corpus is published in.
+pub fn password_sha1_hex(password: &Secret<String>) -> String {
+    hex::encode_upper(Sha1::digest(password.expose_secret().as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_is_uppercase_hex() {
+        assert_eq!(
+            password_sha1_hex(&Secret::new("password".to_owned())),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+}
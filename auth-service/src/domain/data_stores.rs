@@ -1,16 +1,52 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::{eyre, Context, Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
 use thiserror::Error;
 
-use super::{Email, Password, User};
+use super::{
+    AuditEvent, AuditLogQuery, Email, EmailQueueDepth, OutboxEmail, Password, PhoneNumber,
+    TwoFAChannel, User, WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryLogEntry,
+    WebhookEvent, WebhookSubscription,
+};
 
 #[async_trait::async_trait]
 pub trait UserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    /// Fails with [`UserStoreError::UserAlreadyExists`] if the email is taken.
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
+    /// Fails with [`UserStoreError::UserNotFound`] for an unknown email, and
+    /// [`UserStoreError::InvalidCredentials`] for a wrong password.
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    /// Fails with [`UserStoreError::PasswordReused`] if the password is one
+    /// of the user's recent passwords.
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    /// Records a new number to verify, replacing any earlier pending one. The
+    /// current number keeps receiving 2FA codes meanwhile.
+    async fn set_pending_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError>;
+    /// Makes the pending number the user's verified one, as long as it is still
+    /// the number awaiting verification.
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+    ) -> Result<(), UserStoreError>;
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -21,6 +57,10 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Phone number does not match")]
+    PhoneNumberMismatch,
+    #[error("Password was used recently")]
+    PasswordReused,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -32,6 +72,8 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::PhoneNumberMismatch, Self::PhoneNumberMismatch)
+                | (Self::PasswordReused, Self::PasswordReused)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -39,8 +81,36 @@ impl PartialEq for UserStoreError {
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError>;
+    /// Banning a token again is not an error.
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError>;
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError>;
+    /// Bans every token the epoch covers, replacing any earlier epoch. Takes
+    /// one entry however many sessions the user has.
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError>;
+    /// The user's epoch, while tokens issued before it could still be unexpired.
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError>;
+}
+
+/// Bans every token issued to a user before `epoch`, in Unix milliseconds,
+/// except the one named by `exempt_jti`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenEpoch {
+    pub epoch: i64,
+    /// The token issued alongside the epoch, kept valid even if the clock steps back.
+    pub exempt_jti: Option<String>,
+}
+
+impl TokenEpoch {
+    pub fn bans(&self, issued_at_ms: i64, jti: &str) -> bool {
+        issued_at_ms < self.epoch && self.exempt_jti.as_deref() != Some(jti)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -49,19 +119,65 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
+/// How long a 2FA code stays valid, whichever store holds it.
+pub const TWO_FA_CODE_TTL: Duration = Duration::from_secs(600);
+
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
+    /// Replaces any code the user already has for the same purpose.
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
+        purpose: &TwoFACodePurpose,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    /// Removing a code that is missing or expired is not an error.
+    async fn remove_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+    ) -> Result<(), TwoFACodeStoreError>;
+    /// Fails with [`TwoFACodeStoreError::LoginAttemptIdNotFound`] unless the
+    /// user has an unexpired code for the purpose.
     async fn get_code(
         &self,
         email: &Email,
+        purpose: &TwoFACodePurpose,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    /// Removes the user's code for the purpose if it is unexpired and matches
+    /// both `login_attempt_id` and `code`, and returns whether it did. Checks
+    /// and removes in one step, so a code can only be taken once however many
+    /// requests race for it.
+    async fn take_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<bool, TwoFACodeStoreError>;
+}
+
+/// What a 2FA code proves. A user holds at most one code per purpose, so
+/// starting phone enrollment never replaces a pending login code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TwoFACodePurpose {
+    Login,
+    /// Possession of the number the code was texted to. A code sent to one
+    /// number cannot verify another.
+    PhoneVerification(PhoneNumber),
+}
+
+impl TwoFACodePurpose {
+    /// Identifies the purpose in storage keys.
+    pub fn as_key(&self) -> String {
+        match self {
+            Self::Login => "login".to_owned(),
+            Self::PhoneVerification(phone_number) => {
+                format!("phone_verification:{}", phone_number.as_ref())
+            }
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -137,6 +253,142 @@ impl Default for TwoFACode {
 // TODO: how should this be safely exposed? As ref is exposing the secret, but that isn't obvious to the caller
 impl AsRef<str> for TwoFACode {
     fn as_ref(&self) -> &str {
-        &self.0.expose_secret()
+        self.0.expose_secret()
     }
 }
+
+/// Implemented by stores whose backend does not drop expired entries by
+/// itself, unlike Redis. Expired entries are already ignored on reads.
+#[async_trait::async_trait]
+pub trait ExpiringStore {
+    /// Deletes every expired entry and returns how many there were.
+    async fn delete_expired(&self) -> Result<u64>;
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore {
+    async fn consume_token(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, RateLimitStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// Token bucket parameters: a bucket holds at most `capacity` tokens and
+/// regains `refill_per_second` tokens every second. Each request takes one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+impl RateLimitPolicy {
+    pub fn new(capacity: u32, refill_per_second: f64) -> Self {
+        Self {
+            capacity,
+            refill_per_second,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed { remaining: u32 },
+    Limited { retry_after: Duration },
+}
+
+#[async_trait::async_trait]
+pub trait AuditLogStore {
+    async fn append_event(&self, event: AuditEvent) -> Result<(), AuditLogStoreError>;
+    async fn query_events(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait WebhookStore {
+    async fn add_subscription(
+        &self,
+        subscription: WebhookSubscription,
+    ) -> Result<(), WebhookStoreError>;
+    async fn get_subscriptions(&self) -> Result<Vec<WebhookSubscription>, WebhookStoreError>;
+    /// Removes a subscription and its pending deliveries. Removing an unknown id is not an error.
+    async fn remove_subscription(&self, id: &uuid::Uuid) -> Result<(), WebhookStoreError>;
+    /// Queues a delivery of `event` for every subscription interested in its type.
+    async fn enqueue_event(&self, event: &WebhookEvent) -> Result<(), WebhookStoreError>;
+    /// Leases up to `limit` pending deliveries that are due, so that no other
+    /// dispatcher picks them up until `lease_until`.
+    async fn claim_due_deliveries(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError>;
+    /// Logs an attempt. Failed attempts are retried at `retry_at`, or the delivery
+    /// is marked as failed if there is none.
+    async fn record_attempt(
+        &self,
+        attempt: WebhookDeliveryAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookStoreError>;
+    async fn get_delivery_log(
+        &self,
+        subscription_id: &uuid::Uuid,
+    ) -> Result<Vec<WebhookDeliveryLogEntry>, WebhookStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookStoreError {
+    #[error("Delivery not found")]
+    DeliveryNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait EmailOutboxStore {
+    async fn enqueue_email(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError>;
+    /// Leases up to `limit` pending emails that are due, so that no other
+    /// worker picks them up until `lease_until`.
+    async fn claim_due_emails(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError>;
+    async fn mark_sent(&self, id: &uuid::Uuid) -> Result<(), EmailOutboxStoreError>;
+    /// Records a failed attempt. The email is retried at `retry_at`, or moved to
+    /// the dead letter queue if there is none.
+    async fn mark_failed(
+        &self,
+        id: &uuid::Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError>;
+    async fn queue_depth(&self) -> Result<EmailQueueDepth, EmailOutboxStoreError>;
+    /// Deletes sent and dead-lettered emails enqueued before `created_before`
+    /// and returns how many there were.
+    async fn delete_finished(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, EmailOutboxStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailOutboxStoreError {
+    #[error("Email not found")]
+    EmailNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
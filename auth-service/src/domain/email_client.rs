@@ -1,12 +1,16 @@
 use color_eyre::eyre::{Result};
 use super::Email;
 
+/// A rendered email, with HTML and plain text alternatives of the same content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub recipient: Email,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
 #[async_trait::async_trait]
 pub trait EmailClient {
-    async fn send_email(
-        &self,
-        recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<()>;
+    async fn send_email(&self, message: &EmailMessage) -> Result<()>;
 }
@@ -0,0 +1,91 @@
+use std::{fmt, str::FromStr};
+
+use color_eyre::eyre::{eyre, Report};
+use uuid::Uuid;
+
+use super::EmailMessage;
+
+/// An email waiting in the outbox to be handed to the `EmailClient`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEmail {
+    pub id: Uuid,
+    pub message: EmailMessage,
+    pub attempts: u32,
+}
+
+impl OutboxEmail {
+    pub fn new(message: EmailMessage) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            message,
+            attempts: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutboxEmailStatus {
+    Pending,
+    Sent,
+    DeadLettered,
+}
+
+impl OutboxEmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Sent => "sent",
+            Self::DeadLettered => "dead_lettered",
+        }
+    }
+}
+
+impl FromStr for OutboxEmailStatus {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "sent" => Ok(Self::Sent),
+            "dead_lettered" => Ok(Self::DeadLettered),
+            _ => Err(eyre!("Unknown outbox email status: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for OutboxEmailStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Number of emails still waiting to be sent and given up on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmailQueueDepth {
+    pub pending: u64,
+    pub dead_lettered: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_round_trip_through_strings() {
+        for status in [
+            OutboxEmailStatus::Pending,
+            OutboxEmailStatus::Sent,
+            OutboxEmailStatus::DeadLettered,
+        ] {
+            assert_eq!(
+                status.as_str().parse::<OutboxEmailStatus>().unwrap(),
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert!("bounced".parse::<OutboxEmailStatus>().is_err());
+    }
+}
@@ -1,18 +1,26 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::PasswordPolicyViolation;
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
     UserAlreadyExists,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Password does not meet the policy")]
+    WeakPassword(Vec<PasswordPolicyViolation>),
     #[error("Incorrect credentials")]
     IncorrectCredentials,
     #[error("Missing token")]
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Invalid phone number")]
+    InvalidPhoneNumber,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
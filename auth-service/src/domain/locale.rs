@@ -0,0 +1,92 @@
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report};
+
+/// A language that user-facing emails are available in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    Es,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Es => "es",
+        }
+    }
+
+    /// Matches a language tag such as `es-MX` by its primary language, so that
+    /// regional variants fall back to the base language.
+    pub fn from_language_tag(tag: &str) -> Option<Self> {
+        let language = tag.trim().split(['-', '_']).next()?;
+        language.to_ascii_lowercase().parse().ok()
+    }
+
+    /// Picks the most preferred supported locale from an `Accept-Language` header.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut preferences: Vec<(&str, f32)> = header
+            .split(',')
+            .filter_map(|entry| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+
+        // Stable, so equally weighted tags keep the client's order
+        preferences.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        preferences
+            .into_iter()
+            .find_map(|(tag, _)| Self::from_language_tag(tag))
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "es" => Ok(Self::Es),
+            _ => Err(eyre!("Unsupported locale: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regional_tags_fall_back_to_language() {
+        assert_eq!(Locale::from_language_tag("es-MX"), Some(Locale::Es));
+        assert_eq!(Locale::from_language_tag("EN_gb"), Some(Locale::En));
+        assert_eq!(Locale::from_language_tag("fr-FR"), None);
+    }
+
+    #[test]
+    fn accept_language_respects_quality() {
+        assert_eq!(
+            Locale::from_accept_language("en;q=0.5, es-ES;q=0.8"),
+            Some(Locale::Es)
+        );
+        assert_eq!(
+            Locale::from_accept_language("fr-CH, fr;q=0.9, en;q=0.8, es;q=0.7"),
+            Some(Locale::En)
+        );
+    }
+
+    #[test]
+    fn accept_language_without_supported_locale() {
+        assert_eq!(Locale::from_accept_language("fr, de;q=0.5, es;q=0"), None);
+        assert_eq!(Locale::from_accept_language(""), None);
+    }
+}
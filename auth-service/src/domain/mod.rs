@@ -1,13 +1,31 @@
+pub mod audit;
+pub mod breached_password_checker;
 pub mod data_stores;
 pub mod email;
 pub mod email_client;
+pub mod email_outbox;
 pub mod error;
+pub mod locale;
 pub mod password;
+pub mod password_policy;
+pub mod password_strength;
+pub mod phone_number;
+pub mod sms_client;
 pub mod user;
+pub mod webhook;
 
+pub use audit::*;
+pub use breached_password_checker::*;
 pub use data_stores::*;
 pub use email::*;
 pub use email_client::*;
+pub use email_outbox::*;
 pub use error::*;
+pub use locale::*;
 pub use password::*;
+pub use password_policy::*;
+pub use password_strength::*;
+pub use phone_number::*;
+pub use sms_client::*;
 pub use user::*;
+pub use webhook::*;
@@ -22,8 +22,10 @@ impl Password {
     }
 }
 
+/// Only rejects empty input; length and the other rules belong to the
+/// configured [`super::PasswordPolicy`], which is checked for new passwords.
 fn validate_password(s: &Secret<String>) -> bool { // Updated!
-    s.expose_secret().len() >= 8
+    !s.expose_secret().is_empty()
 }
 
 impl AsRef<Secret<String>> for Password { // Updated!
@@ -46,9 +48,9 @@ mod tests {
         assert!(Password::parse(password).is_err());
     }
     #[test]
-    fn string_less_than_8_characters_is_rejected() {
-        let password = Secret::new("1234567".to_string()); // Updated!
-        assert!(Password::parse(password).is_err());
+    fn short_string_is_accepted() {
+        let password = Secret::new("1234567".to_string());
+        assert!(Password::parse(password).is_ok());
     }
 
     #[derive(Debug, Clone)]
@@ -0,0 +1,234 @@
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use super::{estimate_strength, Email};
+
+/// Email local parts shorter than this are too common to be worth rejecting.
+const MIN_EMAIL_LOCAL_PART_LENGTH: usize = 3;
+
+/// Rules new passwords must follow. Stored passwords are not checked again,
+/// so tightening the policy only affects signups and password changes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Rejects passwords containing the part of the email before the `@`.
+    pub disallow_email: bool,
+    /// From 0, which accepts anything, to 4, see [`estimate_strength`].
+    pub min_strength_score: u8,
+    /// How many recent passwords, including the current one, cannot be reused.
+    /// Enforced by the user store, which keeps their hashes.
+    pub history_size: usize,
+}
+
+/// One rule a password broke, as reported to the client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "rule",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum PasswordPolicyViolation {
+    TooShort {
+        min_length: usize,
+    },
+    TooLong {
+        max_length: usize,
+    },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsEmail,
+    TooWeak {
+        score: u8,
+        min_score: u8,
+    },
+    /// Found in a known data breach, see [`super::BreachedPasswordChecker`].
+    Breached,
+    /// One of the user's recent passwords.
+    Reused,
+}
+
+impl PasswordPolicy {
+    /// Returns every rule the password breaks, not just the first.
+    pub fn check(
+        &self,
+        password: &Secret<String>,
+        email: &Email,
+    ) -> Result<(), Vec<PasswordPolicyViolation>> {
+        let password = password.expose_secret();
+        let length = password.chars().count();
+        let mut violations = Vec::new();
+
+        if length < self.min_length {
+            violations.push(PasswordPolicyViolation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+        if length > self.max_length {
+            violations.push(PasswordPolicyViolation::TooLong {
+                max_length: self.max_length,
+            });
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PasswordPolicyViolation::MissingLowercase);
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PasswordPolicyViolation::MissingUppercase);
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordPolicyViolation::MissingDigit);
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violations.push(PasswordPolicyViolation::MissingSymbol);
+        }
+
+        let local_part = email_local_part(email);
+        if self.disallow_email
+            && local_part.chars().count() >= MIN_EMAIL_LOCAL_PART_LENGTH
+            && password.to_lowercase().contains(&local_part)
+        {
+            violations.push(PasswordPolicyViolation::ContainsEmail);
+        }
+
+        // Very long passwords are already rejected, and would be slow to score
+        if self.min_strength_score > 0 && length <= self.max_length {
+            let score = estimate_strength(password, &[&local_part]).score;
+            if score < self.min_strength_score {
+                violations.push(PasswordPolicyViolation::TooWeak {
+                    score,
+                    min_score: self.min_strength_score,
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn email_local_part(email: &Email) -> String {
+    let email = email.as_ref();
+    let local_part = email.rsplit_once('@').map_or(email, |(local, _)| local);
+    local_part.to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 8,
+            max_length: 64,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            disallow_email: true,
+            min_strength_score: 0,
+            history_size: 0,
+        }
+    }
+
+    fn check(policy: &PasswordPolicy, password: &str) -> Result<(), Vec<PasswordPolicyViolation>> {
+        let email = Email::parse("Marguerite@example.com".to_owned()).unwrap();
+        policy.check(&Secret::new(password.to_owned()), &email)
+    }
+
+    #[test]
+    fn length_is_counted_in_characters() {
+        let policy = PasswordPolicy {
+            max_length: 10,
+            ..policy()
+        };
+
+        assert_eq!(
+            check(&policy, "short"),
+            Err(vec![PasswordPolicyViolation::TooShort { min_length: 8 }])
+        );
+        assert_eq!(
+            check(&policy, "much too long"),
+            Err(vec![PasswordPolicyViolation::TooLong { max_length: 10 }])
+        );
+        // Eight characters, but sixteen bytes
+        assert_eq!(check(&policy, "éééééééé"), Ok(()));
+    }
+
+    #[test]
+    fn every_broken_class_rule_is_reported() {
+        let policy = PasswordPolicy {
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..policy()
+        };
+
+        assert_eq!(
+            check(&policy, "abcdefgh"),
+            Err(vec![
+                PasswordPolicyViolation::MissingUppercase,
+                PasswordPolicyViolation::MissingDigit,
+                PasswordPolicyViolation::MissingSymbol,
+            ])
+        );
+        assert_eq!(check(&policy, "aB3$efgh"), Ok(()));
+    }
+
+    #[test]
+    fn email_local_part_is_rejected_ignoring_case() {
+        assert_eq!(
+            check(&policy(), "xMARGUERITE!"),
+            Err(vec![PasswordPolicyViolation::ContainsEmail])
+        );
+
+        let allowed = PasswordPolicy {
+            disallow_email: false,
+            ..policy()
+        };
+        assert_eq!(check(&allowed, "xMARGUERITE!"), Ok(()));
+    }
+
+    #[test]
+    fn weak_passwords_are_rejected_below_the_threshold() {
+        let policy = PasswordPolicy {
+            min_strength_score: 3,
+            ..policy()
+        };
+
+        assert_eq!(
+            check(&policy, "password123"),
+            Err(vec![PasswordPolicyViolation::TooWeak {
+                score: 1,
+                min_score: 3
+            }])
+        );
+        assert_eq!(check(&policy, "x7#Kq9!mZ2vP"), Ok(()));
+    }
+
+    #[test]
+    fn violations_serialize_with_a_rule_tag() {
+        let json = serde_json::to_value(vec![
+            PasswordPolicyViolation::TooShort { min_length: 12 },
+            PasswordPolicyViolation::MissingDigit,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([
+                { "rule": "too_short", "minLength": 12 },
+                { "rule": "missing_digit" },
+            ])
+        );
+    }
+}
@@ -0,0 +1,198 @@
+/// Most common passwords and password stems, most common first. A password
+/// made of one of these plus a few digits or symbols is guessed early on.
+#[rustfmt::skip]
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "123456", "12345678", "qwerty", "123456789", "12345", "1234", "111111", "1234567",
+    "dragon", "123123", "baseball", "abc123", "football", "monkey", "letmein", "696969", "shadow",
+    "master", "666666", "qwertyuiop", "123321", "mustang", "1234567890", "michael", "654321",
+    "superman", "1qaz2wsx", "7777777", "121212", "000000", "qazwsx", "123qwe", "killer", "trustno1",
+    "jordan", "jennifer", "zxcvbnm", "asdfgh", "hunter", "buster", "soccer", "harley", "batman",
+    "andrew", "tigger", "sunshine", "iloveyou", "fuckme", "charlie", "robert", "thomas", "hockey",
+    "ranger", "daniel", "starwars", "klaster", "112233", "george", "computer", "michelle",
+    "jessica", "pepper", "1111", "zxcvbn", "555555", "11111111", "131313", "freedom", "777777",
+    "pass", "maggie", "159753", "aaaaaa", "ginger", "princess", "joshua", "cheese", "amanda",
+    "summer", "love", "ashley", "nicole", "chelsea", "biteme", "matthew", "access", "yankees",
+    "987654321", "dallas", "austin", "thunder", "taylor", "matrix", "welcome", "admin", "login",
+    "passw0rd", "secret", "changeme", "default", "guest", "test", "hello",
+];
+
+/// Keyboard rows and alphabets that people walk along, e.g. `qwerty` or `4567`.
+const SEQUENCES: &[&str] = &[
+    "abcdefghijklmnopqrstuvwxyz",
+    "01234567890",
+    "qwertyuiop",
+    "asdfghjkl",
+    "zxcvbnm",
+];
+
+/// Bands of guesses needed to crack a password, as used by zxcvbn.
+const SCORE_THRESHOLDS_LOG10: [f64; 4] = [3.0, 6.0, 8.0, 10.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PasswordStrength {
+    /// From 0 (guessed almost immediately) to 4 (very unlikely to be guessed).
+    pub score: u8,
+    pub guesses_log10: f64,
+}
+
+/// Estimates how many guesses an attacker needs, in the spirit of zxcvbn:
+/// dictionary words with a few extra characters, repeats and keyboard or
+/// alphabet sequences count for much less than their length suggests.
+/// `user_inputs` are words such as the email address that an attacker targeting
+/// this user would try first.
+pub fn estimate_strength(password: &str, user_inputs: &[&str]) -> PasswordStrength {
+    let guesses_log10 = estimate_guesses_log10(password, user_inputs);
+    let score = SCORE_THRESHOLDS_LOG10
+        .iter()
+        .filter(|threshold| guesses_log10 >= **threshold)
+        .count() as u8;
+
+    PasswordStrength {
+        score,
+        guesses_log10,
+    }
+}
+
+fn estimate_guesses_log10(password: &str, user_inputs: &[&str]) -> f64 {
+    let brute_force = brute_force_guesses_log10(password);
+
+    dictionary_guesses_log10(password, user_inputs)
+        .map_or(brute_force, |dictionary| dictionary.min(brute_force))
+}
+
+/// Guesses for a known word, optionally wrapped in a short run of digits or symbols.
+fn dictionary_guesses_log10(password: &str, user_inputs: &[&str]) -> Option<f64> {
+    let password = password.to_lowercase();
+    let stem = match password.trim_matches(|c: char| !c.is_alphabetic()) {
+        "" => password.as_str(),
+        stem => stem,
+    };
+    let extra = password.chars().count() - stem.chars().count();
+    let candidates = [stem.to_owned(), unleet(stem)];
+
+    let rank = user_inputs
+        .iter()
+        .map(|input| input.to_lowercase())
+        .any(|input| !input.is_empty() && candidates.contains(&input))
+        .then_some(1)
+        .or_else(|| {
+            COMMON_PASSWORDS
+                .iter()
+                .position(|common| candidates.iter().any(|candidate| candidate == common))
+                .map(|position| position + 1)
+        })?;
+
+    // Each extra character is one of about 40 likely digits and symbols
+    Some((rank as f64).log10() + extra as f64 * 40f64.log10())
+}
+
+fn brute_force_guesses_log10(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
+    let cardinality = cardinality(&chars) as f64;
+
+    // Characters that repeat or continue a sequence add almost nothing
+    let mut effective_length = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        let predictable = i > 0 && (chars[i - 1] == *c || continues_sequence(chars[i - 1], *c));
+        effective_length += if predictable { 0.2 } else { 1.0 };
+    }
+
+    effective_length * cardinality.log10()
+}
+
+fn cardinality(chars: &[char]) -> u32 {
+    let mut cardinality = 0;
+    if chars.iter().any(char::is_ascii_lowercase) {
+        cardinality += 26;
+    }
+    if chars.iter().any(char::is_ascii_uppercase) {
+        cardinality += 26;
+    }
+    if chars.iter().any(char::is_ascii_digit) {
+        cardinality += 10;
+    }
+    if chars.iter().any(char::is_ascii_punctuation) || chars.contains(&' ') {
+        cardinality += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        cardinality += 100;
+    }
+    cardinality.max(1)
+}
+
+fn continues_sequence(previous: char, current: char) -> bool {
+    let previous = previous.to_ascii_lowercase();
+    let current = current.to_ascii_lowercase();
+
+    SEQUENCES.iter().any(|sequence| {
+        let mut pairs = sequence.chars().zip(sequence.chars().skip(1));
+        pairs.any(|(a, b)| (a, b) == (previous, current) || (b, a) == (previous, current))
+    })
+}
+
+/// Undoes common substitutions such as `p@ssw0rd`.
+fn unleet(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '@' | '4' => 'a',
+            '3' => 'e',
+            '1' | '!' => 'i',
+            '0' => 'o',
+            '$' | '5' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(password: &str) -> u8 {
+        estimate_strength(password, &[]).score
+    }
+
+    #[test]
+    fn common_passwords_score_lowest() {
+        for password in ["password", "123456", "qwerty", "letmein", "P@ssw0rd"] {
+            assert_eq!(score(password), 0, "{}", password);
+        }
+    }
+
+    #[test]
+    fn common_passwords_with_a_suffix_are_weak() {
+        for password in ["password123", "Password1!", "dragon2024"] {
+            assert!(score(password) <= 2, "{}", password);
+        }
+    }
+
+    #[test]
+    fn repeats_and_sequences_are_weak() {
+        for password in [
+            "aaaaaaaaaaaa",
+            "abcdefghijkl",
+            "1234567890123",
+            "qwertyuiopas",
+        ] {
+            assert!(score(password) <= 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn user_inputs_are_treated_as_known_words() {
+        assert!(score("marguerite42") >= 3);
+        assert!(estimate_strength("marguerite42", &["marguerite"]).score <= 1);
+    }
+
+    #[test]
+    fn long_random_passwords_score_highest() {
+        for password in [
+            "correct horse battery staple",
+            "x7#Kq9!mZ2vP",
+            "tR8vN3qLw5Yz",
+        ] {
+            assert_eq!(score(password), 4, "{}", password);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+/// A phone number in E.164 format, such as `+14155550123`.
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct PhoneNumber(String);
+
+/// E.164 allows at most 15 digits, including the country code.
+const MAX_DIGITS: usize = 15;
+
+impl PhoneNumber {
+    /// Accepts common formatting such as `+1 (415) 555-0123` and stores the bare E.164 form.
+    pub fn parse(s: String) -> Result<PhoneNumber, String> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+            .collect();
+
+        let is_valid = normalized.strip_prefix('+').is_some_and(|digits| {
+            (2..=MAX_DIGITS).contains(&digits.len())
+                && digits.chars().all(|c| c.is_ascii_digit())
+                && !digits.starts_with('0')
+        });
+
+        if is_valid {
+            Ok(Self(normalized))
+        } else {
+            Err(format!("{} is not a valid E.164 phone number.", s))
+        }
+    }
+}
+
+impl AsRef<str> for PhoneNumber {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhoneNumber;
+
+    #[test]
+    fn e164_number_is_accepted() {
+        let phone_number = PhoneNumber::parse("+14155550123".to_owned()).unwrap();
+        assert_eq!(phone_number.as_ref(), "+14155550123");
+    }
+
+    #[test]
+    fn formatting_is_stripped() {
+        let phone_number = PhoneNumber::parse("+1 (415) 555-0123".to_owned()).unwrap();
+        assert_eq!(phone_number.as_ref(), "+14155550123");
+    }
+
+    #[test]
+    fn number_without_country_code_is_rejected() {
+        assert!(PhoneNumber::parse("4155550123".to_owned()).is_err());
+    }
+
+    #[test]
+    fn country_code_starting_with_zero_is_rejected() {
+        assert!(PhoneNumber::parse("+04155550123".to_owned()).is_err());
+    }
+
+    #[test]
+    fn number_longer_than_15_digits_is_rejected() {
+        assert!(PhoneNumber::parse("+1234567890123456".to_owned()).is_err());
+    }
+
+    #[test]
+    fn non_digits_are_rejected() {
+        assert!(PhoneNumber::parse("+1415555O123".to_owned()).is_err());
+        assert!(PhoneNumber::parse("+".to_owned()).is_err());
+        assert!(PhoneNumber::parse("".to_owned()).is_err());
+    }
+}
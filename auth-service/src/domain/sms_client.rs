@@ -0,0 +1,8 @@
+use color_eyre::eyre::Result;
+
+use super::PhoneNumber;
+
+#[async_trait::async_trait]
+pub trait SmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()>;
+}
@@ -1,10 +1,23 @@
-use super::{Email, Password};
+use std::str::FromStr;
+
+use color_eyre::eyre::{eyre, Report};
+
+use super::{Email, Locale, Password, PhoneNumber};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    /// Language of the emails sent to the user.
+    pub locale: Locale,
+    pub phone_number: Option<PhoneNumber>,
+    /// Whether the user proved they own `phone_number`.
+    pub phone_verified: bool,
+    /// A new number waiting for its verification code, which replaces
+    /// `phone_number` once verified.
+    pub pending_phone_number: Option<PhoneNumber>,
+    pub two_fa_channel: TwoFAChannel,
 }
 
 impl User {
@@ -13,6 +26,95 @@ impl User {
             email,
             password,
             requires_2fa,
+            locale: Locale::default(),
+            phone_number: None,
+            phone_verified: false,
+            pending_phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
+        }
+    }
+
+    /// The number to text 2FA codes to, if the user prefers SMS and has verified one.
+    pub fn sms_phone_number(&self) -> Option<&PhoneNumber> {
+        match self.two_fa_channel {
+            TwoFAChannel::Sms if self.phone_verified => self.phone_number.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// How a user wants to receive their 2FA codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Email => "email",
+            Self::Sms => "sms",
+        }
+    }
+}
+
+impl FromStr for TwoFAChannel {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "email" => Ok(Self::Email),
+            "sms" => Ok(Self::Sms),
+            _ => Err(eyre!("Unknown 2FA channel: {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn user() -> User {
+        User::new(
+            Email::parse("test@example.com".to_owned()).unwrap(),
+            Password::parse(Secret::new("password".to_owned())).unwrap(),
+            true,
+        )
+    }
+
+    #[test]
+    fn sms_requires_a_verified_phone_number() {
+        let phone_number = PhoneNumber::parse("+14155550123".to_owned()).unwrap();
+
+        let unverified = User {
+            phone_number: Some(phone_number.clone()),
+            two_fa_channel: TwoFAChannel::Sms,
+            ..user()
+        };
+        assert_eq!(unverified.sms_phone_number(), None);
+
+        let verified = User {
+            phone_verified: true,
+            ..unverified
+        };
+        assert_eq!(verified.sms_phone_number(), Some(&phone_number));
+
+        let prefers_email = User {
+            two_fa_channel: TwoFAChannel::Email,
+            ..verified
+        };
+        assert_eq!(prefers_email.sms_phone_number(), None);
+    }
+
+    #[test]
+    fn channels_round_trip_through_strings() {
+        for channel in [TwoFAChannel::Email, TwoFAChannel::Sms] {
+            assert_eq!(channel.as_str().parse::<TwoFAChannel>().unwrap(), channel);
         }
+        assert!("carrier-pigeon".parse::<TwoFAChannel>().is_err());
     }
 }
@@ -0,0 +1,227 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Report};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::Email;
+
+/// The events that are delivered to subscribers. Only add a type once something
+/// emits it, so that subscribing to an event that never comes is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    /// Emitted when an account is created.
+    #[serde(rename = "user.signed_up")]
+    UserSignedUp,
+    /// Emitted on every successful login, once any 2FA code is verified.
+    #[serde(rename = "user.logged_in")]
+    UserLoggedIn,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UserSignedUp => "user.signed_up",
+            Self::UserLoggedIn => "user.logged_in",
+        }
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user.signed_up" => Ok(Self::UserSignedUp),
+            "user.logged_in" => Ok(Self::UserLoggedIn),
+            _ => Err(eyre!("Unknown webhook event type: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    pub event_type: WebhookEventType,
+    pub email: Email,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: WebhookEventType, email: Email) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            event_type,
+            email,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    /// The JSON body POSTed to subscribers.
+    pub fn payload(&self) -> String {
+        serde_json::json!({
+            "id": self.id,
+            "type": self.event_type,
+            "occurredAt": self.occurred_at,
+            "data": {
+                "email": self.email.as_ref(),
+            },
+        })
+        .to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub url: String,
+    pub secret: Secret<String>,
+    pub event_types: Vec<WebhookEventType>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl WebhookSubscription {
+    /// Creates a subscription with a freshly generated signing secret.
+    pub fn new(url: String, event_types: Vec<WebhookEventType>) -> Self {
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SECRET_LENGTH)
+            .map(char::from)
+            .collect();
+
+        Self {
+            id: Uuid::new_v4(),
+            url,
+            secret: Secret::new(secret),
+            event_types,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn is_subscribed_to(&self, event_type: WebhookEventType) -> bool {
+        self.event_types.contains(&event_type)
+    }
+}
+
+const SECRET_LENGTH: usize = 32;
+
+/// A delivery that is due, together with where and how to send it.
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub subscription_id: Uuid,
+    pub url: String,
+    pub secret: Secret<String>,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub payload: String,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "failed" => Ok(Self::Failed),
+            _ => Err(eyre!("Unknown webhook delivery status: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDeliveryAttempt {
+    pub delivery_id: Uuid,
+    pub attempted_at: DateTime<Utc>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl WebhookDeliveryAttempt {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// One row of a subscription's delivery log: an attempt and the state of its delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDeliveryLogEntry {
+    pub delivery_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub status: WebhookDeliveryStatus,
+    pub attempt: WebhookDeliveryAttempt,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_types_round_trip_through_strings() {
+        for event_type in [
+            WebhookEventType::UserSignedUp,
+            WebhookEventType::UserLoggedIn,
+        ] {
+            assert_eq!(
+                event_type.as_str().parse::<WebhookEventType>().unwrap(),
+                event_type
+            );
+            assert_eq!(
+                serde_json::to_value(event_type).unwrap(),
+                serde_json::json!(event_type.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn payload_contains_event_details() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let event = WebhookEvent::new(WebhookEventType::UserSignedUp, email);
+
+        let payload: serde_json::Value = serde_json::from_str(&event.payload()).unwrap();
+
+        assert_eq!(payload["id"], event.id.to_string());
+        assert_eq!(payload["type"], "user.signed_up");
+        assert_eq!(payload["data"]["email"], "test@example.com");
+    }
+
+    #[test]
+    fn new_subscriptions_get_distinct_secrets() {
+        use secrecy::ExposeSecret;
+
+        let a = WebhookSubscription::new("http://a".to_owned(), vec![]);
+        let b = WebhookSubscription::new("http://b".to_owned(), vec![]);
+
+        assert_eq!(a.secret.expose_secret().len(), SECRET_LENGTH);
+        assert_ne!(a.secret.expose_secret(), b.secret.expose_secret());
+    }
+}
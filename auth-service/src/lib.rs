@@ -1,19 +1,36 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
+    http::StatusCode,
+    middleware::AddExtension,
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post, MethodRouter},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
-use redis::{Client, RedisResult};
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use domain::{AuthAPIError, PasswordPolicyViolation};
+use redis::RedisResult;
+use routes::{
+    add_phone_number, change_password, create_webhook, delete_webhook, get_audit_events,
+    get_dev_mailbox, get_dev_mailbox_email, get_webhook_deliveries, get_webhooks, login, logout,
+    set_two_fa_channel, signup, verify_2fa, verify_phone_number, verify_token,
+};
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use services::data_stores::RedisConnection;
+use settings::{ApplicationSettings, RedisSettings};
+use sqlx::{
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    PgPool, SqlitePool,
+};
+use tower::Layer;
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
 use tracing::info;
 
 use crate::utils::tracing::{make_span_with_request_id, on_request, on_response};
@@ -22,44 +39,87 @@ pub mod app_state;
 pub mod domain;
 pub mod routes;
 pub mod services;
+pub mod settings;
 pub mod utils;
 
+/// Routes that skip the rate limit. `/verify-token` is called by app-service,
+/// from one IP, on every page load, and checks no credentials.
+const RATE_LIMIT_EXEMPT_ROUTES: &[&str] = &["/verify-token"];
+
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
 impl Application {
-    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "http://[YOUR_DROPLET_IP]:8000".parse()?,
+    pub async fn build(
+        app_state: AppState,
+        settings: &ApplicationSettings,
+    ) -> Result<Self, Box<dyn Error>> {
+        let rate_limit = app_state.rate_limiter.layer();
+
+        let routes: Vec<(&str, MethodRouter<AppState>)> = vec![
+            ("/signup", post(signup)),
+            ("/login", post(login)),
+            ("/verify-2fa", post(verify_2fa)),
+            ("/logout", post(logout)),
+            ("/change-password", post(change_password)),
+            ("/verify-token", post(verify_token)),
+            ("/phone-number", post(add_phone_number)),
+            ("/verify-phone-number", post(verify_phone_number)),
+            ("/2fa-channel", post(set_two_fa_channel)),
+            ("/admin/audit-events", get(get_audit_events)),
+            ("/admin/webhooks", get(get_webhooks).post(create_webhook)),
+            ("/admin/webhooks/:id", delete(delete_webhook)),
+            (
+                "/admin/webhooks/:id/deliveries",
+                get(get_webhook_deliveries),
+            ),
+            ("/dev/mailbox", get(get_dev_mailbox)),
+            ("/dev/mailbox/:id", get(get_dev_mailbox_email)),
         ];
 
-        let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
-            .allow_credentials(true)
-            .allow_origin(allowed_origins);
-
-        let router = Router::new()
-            .nest_service("/", ServeDir::new("assets"))
-            .route("/signup", post(signup))
-            .route("/login", post(login))
-            .route("/verify-2fa", post(verify_2fa))
-            .route("/logout", post(logout))
-            .route("/verify-token", post(verify_token))
+        // CORS is applied per route, rather than around the whole router, so
+        // that routes can override the policy and still answer preflight
+        // requests. It wraps the rate limit so that 429s carry CORS headers.
+        let mut router = Router::new();
+        for (path, method_router) in routes {
+            let cors = settings.cors.policy_for(path).layer()?;
+            let method_router = if RATE_LIMIT_EXEMPT_ROUTES.contains(&path) {
+                method_router
+            } else {
+                method_router.route_layer(rate_limit.clone())
+            };
+            router = router.route(path, method_router.layer(cors));
+        }
+
+        let assets = settings
+            .cors
+            .default_policy()
+            .layer()?
+            .layer(ServeDir::new("assets"));
+
+        let router = router
+            .nest_service("/", assets)
             .with_state(app_state)
-            .layer(cors)
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
-        let listener = tokio::net::TcpListener::bind(address).await?;
+        let listener = tokio::net::TcpListener::bind(&settings.address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -73,13 +133,19 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// The rules a rejected password broke.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<PasswordPolicyViolation>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
 
-        let (status, error_message) = match self {
+        let (status, error_message) = match &self {
+            AuthAPIError::WeakPassword(_) => {
+                (StatusCode::BAD_REQUEST, "Password does not meet the policy")
+            }
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
             AuthAPIError::IncorrectCredentials => {
@@ -87,12 +153,21 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::InvalidPhoneNumber => (StatusCode::BAD_REQUEST, "Invalid phone number"),
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
         };
+        let violations = match self {
+            AuthAPIError::WeakPassword(violations) => violations,
+            _ => Vec::new(),
+        };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            violations,
         });
         (status, body).into_response()
     }
@@ -102,9 +177,27 @@ pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new().max_connections(5).connect(url).await
 }
 
-pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
-    let redis_url = format!("redis://{}/", redis_hostname);
-    redis::Client::open(redis_url)
+/// Opens the database file, creating it if needed. WAL lets readers carry on
+/// while a write is in progress, and writers wait for each other instead of
+/// failing with `SQLITE_BUSY`.
+pub async fn get_sqlite_pool(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let options = url
+        .parse::<SqliteConnectOptions>()?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(std::time::Duration::from_secs(5))
+        .foreign_keys(true);
+
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
+/// Connects to the configured Redis deployment, retrying with backoff while it
+/// is unavailable. The connection reconnects in the background when it drops.
+pub async fn get_redis_connection(settings: &RedisSettings) -> RedisResult<RedisConnection> {
+    RedisConnection::connect(settings).await
 }
 
 fn log_error_chain(e: &(dyn Error + 'static)) {
@@ -119,4 +212,4 @@ fn log_error_chain(e: &(dyn Error + 'static)) {
     }
     report = format!("{}\n{}", report, separator);
     tracing::error!("{}", report);
-}
\ No newline at end of file
+}
@@ -1,17 +1,31 @@
+use metrics_exporter_prometheus::PrometheusBuilder;
 use reqwest::Client;
-use sqlx::PgPool;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use secrecy::ExposeSecret;
+use std::{sync::Arc, time::Duration};
 
 use auth_service::{
-    app_state::AppState,
-    get_postgres_pool, get_redis_client,
+    app_state::{
+        build_stores, AppState, Database, EmailClientType, EmailOutboxStoreType, SmsClientType,
+        WebhookStoreType,
+    },
+    domain::{Email, PhoneNumber},
+    get_postgres_pool, get_sqlite_pool,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        email_outbox_worker::{EmailOutboxWorker, EmailOutboxWorkerConfig},
+        failover_email_client::FailoverEmailClient,
+        file_email_client::{DevMailbox, FileEmailClient},
+        mailgun_email_client::MailgunEmailClient,
+        mock_sms_client::MockSmsClient,
         postmark_email_client::PostmarkEmailClient,
+        sendgrid_email_client::SendGridEmailClient,
+        ses_email_client::{AwsCredentials, SesEmailClient},
+        smtp_email_client::{SmtpCredentials, SmtpEmailClient, SmtpEmailClientConfig, SmtpTlsMode},
+        twilio_sms_client::TwilioSmsClient,
+        webhook_dispatcher::{WebhookDispatcher, WebhookDispatcherConfig},
     },
+    settings::{EmailOutboxSettings, EmailSettings, Settings, WebhookSettings},
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        rate_limit::{RateLimitConfig, RateLimiter},
         tracing::init_tracing,
     },
     Application,
@@ -22,61 +36,270 @@ async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
 
-    let pg_pool = configure_postgresql().await;
-    let redis_connection = Arc::new(RwLock::new(configure_redis()));
+    let settings = match Settings::load() {
+        Ok(settings) => Arc::new(settings),
+        Err(e) => {
+            eprintln!("{:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    configure_metrics(&settings.application.metrics_address);
+
+    let database = configure_database(&settings).await;
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-        redis_connection.clone(),
-    )));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    let stores = match build_stores(&settings, &database).await {
+        Ok(stores) => stores,
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to set up the stores");
+            std::process::exit(1);
+        }
+    };
 
-    let email_client = Arc::new(configure_postmark_email_client());
+    let email_client = configure_email_client(&settings.email);
+    let sms_client = configure_sms_client(&settings);
+
+    let breached_password_checker = settings
+        .breached_passwords
+        .checker()
+        .expect("Failed to build breached password checker");
+
+    let rate_limiter = RateLimiter::new(stores.rate_limit_store, configure_rate_limit(&settings));
+
+    tokio::spawn(
+        configure_webhook_dispatcher(stores.webhook_store.clone(), &settings.webhooks).run(),
+    );
+    tokio::spawn(
+        configure_email_outbox_worker(
+            stores.email_outbox_store.clone(),
+            email_client,
+            &settings.email_outbox,
+        )
+        .run(),
+    );
+    tokio::spawn(stores.expired_entry_sweeper.run());
 
     let app_state = AppState::new(
-        user_store,
-        banned_token_store,
-        two_fa_code_store,
-        email_client,
+        settings.clone(),
+        stores.user_store,
+        stores.banned_token_store,
+        stores.two_fa_code_store,
+        stores.email_outbox_store,
+        sms_client,
+        breached_password_checker,
+        rate_limiter,
+        stores.audit_log_store,
+        stores.webhook_store,
+        configure_dev_mailbox(&settings),
     );
 
-    let app = Application::build(app_state, prod::APP_ADDRESS)
+    let app = Application::build(app_state, &settings.application)
         .await
         .expect("Failed to build app");
 
     app.run().await.expect("Failed to run app");
 }
 
-async fn configure_postgresql() -> PgPool {
-    let pg_pool = get_postgres_pool(&DATABASE_URL)
-        .await
-        .expect("Failed to create Postgres connection pool!");
+async fn configure_database(settings: &Settings) -> Database {
+    let url = settings.database.url.expose_secret();
 
-    sqlx::migrate!()
-        .run(&pg_pool)
-        .await
-        .expect("Failed to run migrations");
+    if settings.database.is_sqlite() {
+        let pool = get_sqlite_pool(url)
+            .await
+            .expect("Failed to open SQLite database!");
+
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        Database::Sqlite(pool)
+    } else {
+        let pool = get_postgres_pool(url)
+            .await
+            .expect("Failed to create Postgres connection pool!");
+
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        Database::Postgres(pool)
+    }
+}
+
+fn configure_metrics(address: &str) {
+    let address: std::net::SocketAddr = address.parse().expect("Failed to parse metrics address");
+
+    PrometheusBuilder::new()
+        .with_http_listener(address)
+        .install()
+        .expect("Failed to install Prometheus exporter");
+}
+
+fn configure_email_client(settings: &EmailSettings) -> EmailClientType {
+    let primary = configure_email_provider(&settings.provider, settings);
+
+    match settings.fallback_provider.as_deref() {
+        Some(fallback) => Arc::new(FailoverEmailClient::new(
+            primary,
+            configure_email_provider(fallback, settings),
+        )),
+        None => primary,
+    }
+}
+
+fn configure_email_provider(provider: &str, settings: &EmailSettings) -> EmailClientType {
+    match provider {
+        "postmark" => Arc::new(PostmarkEmailClient::new(
+            settings.postmark.base_url.to_owned(),
+            email_sender(settings),
+            settings.postmark.auth_token.clone(),
+            email_http_client(settings),
+        )),
+        "sendgrid" => Arc::new(SendGridEmailClient::new(
+            settings.sendgrid.base_url.to_owned(),
+            email_sender(settings),
+            settings.sendgrid.api_key.clone(),
+            email_http_client(settings),
+        )),
+        "mailgun" => Arc::new(MailgunEmailClient::new(
+            settings.mailgun.base_url.to_owned(),
+            settings.mailgun.domain.to_owned(),
+            email_sender(settings),
+            settings.mailgun.api_key.clone(),
+            email_http_client(settings),
+        )),
+        "ses" => Arc::new(SesEmailClient::new(
+            format!("https://email.{}.amazonaws.com", settings.ses.region),
+            settings.ses.region.to_owned(),
+            email_sender(settings),
+            AwsCredentials {
+                access_key_id: settings.ses.access_key_id.to_owned(),
+                secret_access_key: settings.ses.secret_access_key.clone(),
+                session_token: settings.ses.session_token.clone(),
+            },
+            email_http_client(settings),
+        )),
+        "smtp" => Arc::new(configure_smtp_email_client(settings)),
+        "file" => Arc::new(
+            FileEmailClient::new(settings.mailbox_dir.clone(), email_sender(settings))
+                .expect("Failed to create mailbox directory"),
+        ),
+        provider => panic!("Unsupported email provider: {}", provider),
+    }
+}
 
-    pg_pool
+fn email_sender(settings: &EmailSettings) -> Email {
+    Email::parse(settings.sender.to_owned()).unwrap()
 }
 
-fn configure_redis() -> redis::Connection {
-    get_redis_client(REDIS_HOST_NAME.to_owned())
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
+fn email_http_client(settings: &EmailSettings) -> Client {
+    Client::builder()
+        .timeout(settings.timeout())
+        .build()
+        .expect("Failed to build HTTP client")
 }
 
-fn configure_postmark_email_client() -> PostmarkEmailClient {
+fn configure_sms_client(settings: &Settings) -> SmsClientType {
+    let sms = &settings.sms;
+
+    match sms.provider.as_str() {
+        "twilio" => Arc::new(TwilioSmsClient::new(
+            sms.twilio.base_url.to_owned(),
+            sms.twilio.account_sid.to_owned(),
+            sms.twilio.auth_token.clone(),
+            PhoneNumber::parse(sms.twilio.from_number.to_owned())
+                .expect("TWILIO_FROM_NUMBER must be an E.164 phone number."),
+            Client::builder()
+                .timeout(sms.timeout())
+                .build()
+                .expect("Failed to build HTTP client"),
+        )),
+        "mock" => {
+            if settings.is_production() {
+                tracing::warn!("using the mock SMS provider, text messages will only be logged");
+            }
+            Arc::new(MockSmsClient)
+        }
+        provider => panic!("Unsupported SMS provider: {}", provider),
+    }
+}
+
+/// Only lets captured emails be browsed when they are written to files outside production.
+fn configure_dev_mailbox(settings: &Settings) -> Option<DevMailbox> {
+    let enabled = settings.email.provider == "file" && !settings.is_production();
+
+    enabled.then(|| DevMailbox::new(settings.email.mailbox_dir.clone()))
+}
+
+fn configure_smtp_email_client(settings: &EmailSettings) -> SmtpEmailClient {
+    let smtp = &settings.smtp;
+    let tls: SmtpTlsMode = smtp.tls.parse().expect("Failed to parse SMTP_TLS");
+    let credentials = smtp.username.clone().map(|username| SmtpCredentials {
+        username,
+        password: smtp.password.clone(),
+    });
+
+    SmtpEmailClient::new(
+        SmtpEmailClientConfig {
+            host: smtp.host.to_owned(),
+            port: smtp.port.unwrap_or(tls.default_port()),
+            tls,
+            credentials,
+            timeout: settings.timeout(),
+            max_connections: smtp.max_connections,
+            idle_timeout: Duration::from_secs(smtp.idle_timeout_secs),
+        },
+        email_sender(settings),
+    )
+    .expect("Failed to build SMTP email client")
+}
+
+fn configure_rate_limit(settings: &Settings) -> RateLimitConfig {
+    let rate_limit = &settings.rate_limit;
+
+    RateLimitConfig {
+        ip_policy: rate_limit.ip_policy(),
+        email_policy: Some(rate_limit.email_policy()),
+        trusted_proxies: settings.application.trusted_proxies.clone(),
+    }
+}
+
+fn configure_webhook_dispatcher(
+    store: WebhookStoreType,
+    settings: &WebhookSettings,
+) -> WebhookDispatcher {
     let http_client = Client::builder()
-        .timeout(prod::email_client::TIMEOUT)
+        .timeout(settings.timeout())
         .build()
         .expect("Failed to build HTTP client");
 
-    PostmarkEmailClient::new(
-        prod::email_client::BASE_URL.to_owned(),
-        auth_service::domain::Email::parse(prod::email_client::SENDER.to_owned()).unwrap(),
-        POSTMARK_AUTH_TOKEN.to_owned(),
+    WebhookDispatcher::new(
+        store,
         http_client,
+        WebhookDispatcherConfig {
+            poll_interval: settings.poll_interval(),
+            batch_size: settings.batch_size,
+            lease: settings.lease(),
+            backoff: settings.backoff(),
+        },
+    )
+}
+
+fn configure_email_outbox_worker(
+    store: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    settings: &EmailOutboxSettings,
+) -> EmailOutboxWorker {
+    EmailOutboxWorker::new(
+        store,
+        email_client,
+        EmailOutboxWorkerConfig {
+            poll_interval: settings.poll_interval(),
+            batch_size: settings.batch_size,
+            lease: settings.lease(),
+            backoff: settings.backoff(),
+        },
     )
 }
@@ -0,0 +1,26 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use secrecy::ExposeSecret;
+
+use crate::{app_state::AppState, domain::AuthAPIError};
+
+/// Checks the `Authorization: Bearer` header against the configured admin API token.
+pub(crate) fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), AuthAPIError> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(AuthAPIError::MissingToken)?;
+
+    match &state.settings.admin.api_token {
+        Some(expected) if constant_time_eq(token, expected.expose_secret()) => Ok(()),
+        _ => Err(AuthAPIError::InvalidToken),
+    }
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
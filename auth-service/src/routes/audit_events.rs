@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::admin::authorize_admin;
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditLogQuery, AuthAPIError, Email},
+};
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+#[tracing::instrument(skip_all)]
+pub async fn get_audit_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(request): Query<AuditEventsRequest>,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError> {
+    authorize_admin(&state, &headers)?;
+
+    let email = request
+        .email
+        .map(Email::parse)
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let query = AuditLogQuery {
+        email,
+        from: request.from,
+        to: request.to,
+        limit: request.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+    };
+
+    let events = state
+        .audit_log_store
+        .query_events(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse {
+        events: events.into_iter().map(AuditEventResponse::from).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventsRequest {
+    pub email: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditEventResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventResponse {
+    pub event_type: String,
+    pub email: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            event_type: event.event_type.to_string(),
+            email: event.email.map(|email| email.as_ref().to_owned()),
+            ip_address: event.ip_address.map(|ip| ip.to_string()),
+            user_agent: event.user_agent,
+            request_id: event.request_id,
+            occurred_at: event.occurred_at,
+        }
+    }
+}
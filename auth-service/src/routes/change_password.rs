@@ -0,0 +1,105 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEventType, AuthAPIError, Password, PasswordPolicyViolation, UserStoreError},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate, ban_all_tokens},
+        password::check_new_password,
+        request_context::RequestContext,
+    },
+};
+
+/// Replaces the signed-in user's password after checking the current one, and
+/// signs out every session but this one, which gets a fresh token. The email
+/// rate limit applies to the signed-in account, as the current password can be
+/// guessed here, and a new password is only checked once the current one is right.
+#[tracing::instrument(name = "Change password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), &state.settings.auth).await?;
+
+    if let Err(response) = state
+        .rate_limiter
+        .check_account("/change-password", &email)
+        .await
+    {
+        return Ok(response);
+    }
+
+    let current_password =
+        Password::parse(request.current_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_store = &state.user_store;
+
+    match user_store.validate_user(&email, &current_password).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound | UserStoreError::InvalidCredentials) => {
+            return Err(AuthAPIError::IncorrectCredentials)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    check_new_password(&state, &request.new_password, &email).await?;
+    let new_password =
+        Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    match user_store.update_password(&email, new_password).await {
+        Ok(()) => {}
+        Err(UserStoreError::PasswordReused) => {
+            return Err(AuthAPIError::WeakPassword(vec![
+                PasswordPolicyViolation::Reused,
+            ]))
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let auth_cookie = ban_all_tokens(
+        &email,
+        state.banned_token_store.clone(),
+        &state.settings.auth,
+    )
+    .await
+    .map_err(AuthAPIError::UnexpectedError)?;
+    record_audit_event(&state, &context, AuditEventType::TokenRevoked, Some(&email)).await;
+
+    record_audit_event(
+        &state,
+        &context,
+        AuditEventType::PasswordChanged,
+        Some(&email),
+    )
+    .await;
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password changed".to_owned(),
+    });
+
+    Ok((StatusCode::OK, jar.add(auth_cookie), response).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "currentPassword")]
+    pub current_password: Secret<String>,
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
@@ -0,0 +1,52 @@
+use askama::Template;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+
+use crate::{
+    app_state::AppState, domain::AuthAPIError, services::file_email_client::CapturedEmail,
+};
+
+#[derive(Template)]
+#[template(path = "dev/mailbox.html")]
+struct MailboxTemplate {
+    emails: Vec<CapturedEmail>,
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_dev_mailbox(State(state): State<AppState>) -> Result<Response, AuthAPIError> {
+    let Some(mailbox) = &state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let emails = mailbox
+        .list()
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let page = MailboxTemplate { emails }
+        .render()
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Html(page).into_response())
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_dev_mailbox_email(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AuthAPIError> {
+    let Some(mailbox) = &state.dev_mailbox else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    match mailbox
+        .get(&id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?
+    {
+        Some(email) => Ok(Html(email.html_body).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
@@ -5,13 +5,21 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEventType, AuthAPIError, Email, LoginAttemptId, OutboxEmail, Password, PhoneNumber,
+        TwoFACode, TwoFACodePurpose, User, UserStoreError, WebhookEventType,
+    },
+    services::{email_templates::EmailTemplate, sms_templates::SmsTemplate},
+    utils::{
+        audit::record_audit_event, auth::generate_auth_cookie, request_context::RequestContext,
+        webhooks::emit_webhook_event,
+    },
 };
 
 #[tracing::instrument(skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -25,10 +33,15 @@ pub async fn login(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let user_store = &state.user_store.read().await;
+    let user_store = &state.user_store;
 
-    if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    match user_store.validate_user(&email, &password).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserNotFound | UserStoreError::InvalidCredentials) => {
+            record_audit_event(&state, &context, AuditEventType::LoginFailed, Some(&email)).await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     }
 
     let user = match user_store.get_user(&email).await {
@@ -37,41 +50,49 @@ pub async fn login(
     };
 
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        true => handle_2fa(&user, &state, &context, jar).await,
+        false => handle_no_2fa(&user.email, &state, &context, jar).await,
     }
 }
 
 #[tracing::instrument(skip_all)]
 async fn handle_2fa(
-    email: &Email,
+    user: &User,
     state: &AppState,
+    context: &RequestContext,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
+    let email = &user.email;
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
     if let Err(e) = state
         .two_fa_code_store
-        .write()
-        .await
-        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
+        .add_code(
+            email.clone(),
+            &TwoFACodePurpose::Login,
+            login_attempt_id.clone(),
+            two_fa_code.clone(),
+        )
         .await
     {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "2FA Code", two_fa_code.as_ref())
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e)));
+    let sent = match user.sms_phone_number() {
+        Some(phone_number) => send_code_by_sms(user, phone_number, &two_fa_code, state).await,
+        None => send_code_by_email(user, &two_fa_code, state).await,
+    };
+
+    if let Err(e) = sent {
+        return (jar, Err(e));
     }
 
+    record_audit_event(state, context, AuditEventType::TwoFACodeSent, Some(email)).await;
+
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().to_owned(),
@@ -80,21 +101,67 @@ async fn handle_2fa(
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
+#[tracing::instrument(skip_all)]
+async fn send_code_by_sms(
+    user: &User,
+    phone_number: &PhoneNumber,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let body = SmsTemplate::TwoFACode {
+        code: two_fa_code.as_ref().to_owned(),
+    }
+    .render(user.locale);
+
+    // Texts are sent inline, so a gateway failure fails the login and can be retried
+    state
+        .sms_client
+        .send_sms(phone_number, &body)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+#[tracing::instrument(skip_all)]
+async fn send_code_by_email(
+    user: &User,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    // Queue the code rather than sending it inline, so a slow or unavailable
+    // email provider does not fail the login
+    let message = EmailTemplate::TwoFACode {
+        code: two_fa_code.as_ref().to_owned(),
+    }
+    .render(&user.email, user.locale)
+    .map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .email_outbox_store
+        .enqueue_email(OutboxEmail::new(message))
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
+
 #[tracing::instrument(skip_all)]
 async fn handle_no_2fa(
     email: &Email,
+    state: &AppState,
+    context: &RequestContext,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(email) {
+    let auth_cookie = match generate_auth_cookie(email, &state.settings.auth) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
     let updated_jar = jar.add(auth_cookie);
 
+    record_audit_event(state, context, AuditEventType::LoginSucceeded, Some(email)).await;
+    emit_webhook_event(state, WebhookEventType::UserLoggedIn, email).await;
+
     (
         updated_jar,
         Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))),
@@ -3,13 +3,17 @@ use axum_extra::extract::{cookie, CookieJar};
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuditEventType, AuthAPIError, Email},
+    utils::{
+        audit::record_audit_event, auth::validate_token, constants::JWT_COOKIE_NAME,
+        request_context::RequestContext,
+    },
 };
 
 #[tracing::instrument(skip_all)]
 pub async fn logout(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let cookie = match jar.get(JWT_COOKIE_NAME) {
@@ -19,22 +23,32 @@ pub async fn logout(
 
     // Validate token
     let token = cookie.value().to_owned();
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let claims = match validate_token(
+        &token,
+        state.banned_token_store.clone(),
+        &state.settings.auth,
+    )
+    .await
+    {
         Ok(claims) => claims,
         Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
     };
 
-    // Add token to banned list
-    if let Err(e) = state
-        .banned_token_store
-        .write()
-        .await
-        .add_token(token.to_owned())
-        .await
-    {
+    // Ban this session's token, leaving the user's other sessions signed in
+    if let Err(e) = state.banned_token_store.add_token(claims.jti).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
+    let email = Email::parse(claims.sub).ok();
+    record_audit_event(
+        &state,
+        &context,
+        AuditEventType::TokenRevoked,
+        email.as_ref(),
+    )
+    .await;
+    record_audit_event(&state, &context, AuditEventType::Logout, email.as_ref()).await;
+
     // Remove jwt cookie
     let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
 
@@ -1,11 +1,24 @@
+mod admin;
+mod audit_events;
+mod change_password;
+mod dev_mailbox;
 mod login;
 mod logout;
+mod phone_number;
 mod signup;
+mod two_fa_channel;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
 
+pub use audit_events::*;
+pub use change_password::*;
+pub use dev_mailbox::*;
 pub use login::*;
 pub use logout::*;
+pub use phone_number::*;
 pub use signup::*;
+pub use two_fa_channel::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
+pub use webhooks::*;
@@ -0,0 +1,163 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditEventType, AuthAPIError, LoginAttemptId, PhoneNumber, TwoFAChannel, TwoFACode,
+        TwoFACodePurpose, UserStoreError,
+    },
+    services::sms_templates::SmsTemplate,
+    utils::{audit::record_audit_event, auth::authenticate, request_context::RequestContext},
+};
+
+/// Starts SMS enrollment by texting a code to the new number, which stays pending
+/// until verified so that a verified number keeps working meanwhile. Each text
+/// costs money, so the email rate limit applies to the signed-in account.
+#[tracing::instrument(name = "Add phone number", skip_all)]
+pub async fn add_phone_number(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<AddPhoneNumberRequest>,
+) -> Result<Response, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), &state.settings.auth).await?;
+    let phone_number =
+        PhoneNumber::parse(request.phone_number).map_err(|_| AuthAPIError::InvalidPhoneNumber)?;
+
+    if let Err(response) = state
+        .rate_limiter
+        .check_account("/phone-number", &email)
+        .await
+    {
+        return Ok(response);
+    }
+
+    let user_store = &state.user_store;
+
+    user_store
+        .set_pending_phone_number(&email, phone_number.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let user = user_store
+        .get_user(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let verification_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    state
+        .two_fa_code_store
+        .add_code(
+            email,
+            &TwoFACodePurpose::PhoneVerification(phone_number.clone()),
+            verification_id.clone(),
+            code.clone(),
+        )
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let body = SmsTemplate::PhoneVerification {
+        code: code.as_ref().to_owned(),
+    }
+    .render(user.locale);
+
+    state
+        .sms_client
+        .send_sms(&phone_number, &body)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(AddPhoneNumberResponse {
+        message: "Verification code sent".to_owned(),
+        verification_id: verification_id.as_ref().to_owned(),
+    });
+
+    Ok((StatusCode::OK, response).into_response())
+}
+
+/// Finishes SMS enrollment, replacing any earlier number with the pending one,
+/// and switches the user's 2FA codes to text messages.
+#[tracing::instrument(name = "Verify phone number", skip_all)]
+pub async fn verify_phone_number(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), &state.settings.auth).await?;
+
+    let verification_id = LoginAttemptId::parse(request.verification_id)
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let code = TwoFACode::parse(Secret::new(request.code))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_store = &state.user_store;
+    let two_fa_code_store = &state.two_fa_code_store;
+
+    // Only a code texted to the pending number can verify it
+    let phone_number = user_store
+        .get_user(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .pending_phone_number
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+    let purpose = TwoFACodePurpose::PhoneVerification(phone_number.clone());
+
+    let taken = two_fa_code_store
+        .take_code(&email, &purpose, &verification_id, &code)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if !taken {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+
+    match user_store.verify_phone_number(&email, &phone_number).await {
+        Ok(()) => {}
+        Err(UserStoreError::PhoneNumberMismatch) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    user_store
+        .set_two_fa_channel(&email, TwoFAChannel::Sms)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(
+        &state,
+        &context,
+        AuditEventType::PhoneNumberVerified,
+        Some(&email),
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct AddPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddPhoneNumberResponse {
+    pub message: String,
+    #[serde(rename = "verificationId")]
+    pub verification_id: String,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    #[serde(rename = "verificationId")]
+    pub verification_id: String,
+    pub code: String,
+}
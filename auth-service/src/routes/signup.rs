@@ -1,34 +1,67 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{header::ACCEPT_LANGUAGE, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User},
+    domain::{
+        AuditEventType, AuthAPIError, Email, Locale, Password, User, UserStoreError,
+        WebhookEventType,
+    },
+    utils::{
+        audit::record_audit_event, password::check_new_password, request_context::RequestContext,
+        webhooks::emit_webhook_event,
+    },
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email =
         Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    check_new_password(&state, &request.password, &email).await?;
     let password = Password::parse(request.password)
         .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+    // Prefer the locale the client asked for, then the browser's languages
+    let locale = request
+        .locale
+        .as_deref()
+        .and_then(Locale::from_language_tag)
+        .or_else(|| {
+            headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Locale::from_accept_language)
+        })
+        .unwrap_or_default();
 
-    let mut user_store = state.user_store.write().await;
+    let user = User {
+        locale,
+        ..User::new(email, password, request.requires_2fa)
+    };
 
-    if user_store.get_user(&user.email).await.is_ok() {
-        return Err(AuthAPIError::UserAlreadyExists);
-    }
+    let email = user.email.clone();
 
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    // The store rejects a taken email itself, so concurrent signups cannot both succeed
+    match state.user_store.add_user(user).await {
+        Ok(()) => {}
+        Err(UserStoreError::UserAlreadyExists) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
 
+    record_audit_event(&state, &context, AuditEventType::Signup, Some(&email)).await;
+    emit_webhook_event(&state, WebhookEventType::UserSignedUp, &email).await;
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
@@ -42,6 +75,9 @@ pub struct SignupRequest {
     pub password: Secret<String>,
     #[serde(rename = "requires2FA")]
     pub requires_2fa: bool,
+    /// Language tag such as `es-MX` for the user's emails.
+    #[serde(default)]
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -0,0 +1,48 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, TwoFAChannel},
+    utils::auth::authenticate,
+};
+
+/// Chooses whether 2FA codes are sent by email or SMS.
+#[tracing::instrument(name = "Set 2FA channel", skip_all)]
+pub async fn set_two_fa_channel(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<SetTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticate(&jar, state.banned_token_store.clone(), &state.settings.auth).await?;
+    let channel: TwoFAChannel = request
+        .channel
+        .parse()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let user_store = &state.user_store;
+
+    if channel == TwoFAChannel::Sms {
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        if user.phone_number.is_none() || !user.phone_verified {
+            return Err(AuthAPIError::PhoneNumberNotVerified);
+        }
+    }
+
+    user_store
+        .set_two_fa_channel(&email, channel)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct SetTwoFAChannelRequest {
+    pub channel: String,
+}
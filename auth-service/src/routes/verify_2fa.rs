@@ -5,13 +5,20 @@ use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEventType, AuthAPIError, Email, LoginAttemptId, TwoFACode, TwoFACodePurpose,
+        WebhookEventType,
+    },
+    utils::{
+        audit::record_audit_event, auth::generate_auth_cookie, request_context::RequestContext,
+        webhooks::emit_webhook_event,
+    },
 };
 
 #[tracing::instrument(skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
@@ -30,28 +37,43 @@ pub async fn verify_2fa(
         Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
     };
 
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    let two_fa_code_store = &state.two_fa_code_store;
 
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
-        Ok(code_tuple) => code_tuple,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
-    };
-
-    if !code_tuple.0.eq(&login_attempt_id) || !code_tuple.1.eq(&two_fa_code) {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    // Taking the code checks and consumes it at once, so that two requests
+    // presenting the same code cannot both sign in
+    match two_fa_code_store
+        .take_code(
+            &email,
+            &TwoFACodePurpose::Login,
+            &login_attempt_id,
+            &two_fa_code,
+        )
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            record_audit_event(&state, &context, AuditEventType::TwoFAFailed, Some(&email)).await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     }
 
-    if let Err(e) = two_fa_code_store.remove_code(&email).await {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
-    }
-
-    let cookie = match generate_auth_cookie(&email) {
+    let cookie = match generate_auth_cookie(&email, &state.settings.auth) {
         Ok(cookie) => cookie,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
     let updated_jar = jar.add(cookie);
 
+    record_audit_event(
+        &state,
+        &context,
+        AuditEventType::TwoFAVerified,
+        Some(&email),
+    )
+    .await;
+    emit_webhook_event(&state, WebhookEventType::UserLoggedIn, &email).await;
+
     (updated_jar, Ok(()))
 }
 
@@ -8,7 +8,13 @@ pub async fn verify_token(
     State(state): State<AppState>,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
+    match validate_token(
+        &request.token,
+        state.banned_token_store.clone(),
+        &state.settings.auth,
+    )
+    .await
+    {
         Ok(_) => Ok(StatusCode::OK),
         Err(_) => Err(AuthAPIError::InvalidToken),
     }
@@ -0,0 +1,175 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::admin::authorize_admin;
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, WebhookDeliveryLogEntry, WebhookEventType, WebhookSubscription},
+};
+
+#[tracing::instrument(skip_all)]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    authorize_admin(&state, &headers)?;
+
+    let is_http =
+        Url::parse(&request.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+
+    if !is_http || request.event_types.is_empty() {
+        return Err(AuthAPIError::InvalidCredentials);
+    }
+
+    let subscription = WebhookSubscription::new(request.url, request.event_types);
+    let response = CreateWebhookResponse {
+        secret: subscription.secret.expose_secret().to_owned(),
+        subscription: WebhookSubscriptionResponse::from(&subscription),
+    };
+
+    state
+        .webhook_store
+        .add_subscription(subscription)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_webhooks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<WebhooksResponse>, AuthAPIError> {
+    authorize_admin(&state, &headers)?;
+
+    let subscriptions = state
+        .webhook_store
+        .get_subscriptions()
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(WebhooksResponse {
+        webhooks: subscriptions
+            .iter()
+            .map(WebhookSubscriptionResponse::from)
+            .collect(),
+    }))
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, AuthAPIError> {
+    authorize_admin(&state, &headers)?;
+
+    state
+        .webhook_store
+        .remove_subscription(&id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn get_webhook_deliveries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<Json<WebhookDeliveriesResponse>, AuthAPIError> {
+    authorize_admin(&state, &headers)?;
+
+    let log = state
+        .webhook_store
+        .get_delivery_log(&id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(WebhookDeliveriesResponse {
+        deliveries: log.into_iter().map(WebhookDeliveryResponse::from).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWebhookResponse {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscriptionResponse,
+    /// Only ever returned here, so that the subscriber can verify signatures.
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhooksResponse {
+    pub webhooks: Vec<WebhookSubscriptionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookSubscriptionResponse {
+    pub id: Uuid,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&WebhookSubscription> for WebhookSubscriptionResponse {
+    fn from(subscription: &WebhookSubscription) -> Self {
+        Self {
+            id: subscription.id,
+            url: subscription.url.clone(),
+            event_types: subscription.event_types.clone(),
+            created_at: subscription.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDeliveryResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryResponse {
+    pub delivery_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub status: String,
+    pub attempted_at: DateTime<Utc>,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl From<WebhookDeliveryLogEntry> for WebhookDeliveryResponse {
+    fn from(entry: WebhookDeliveryLogEntry) -> Self {
+        Self {
+            delivery_id: entry.delivery_id,
+            event_id: entry.event_id,
+            event_type: entry.event_type,
+            status: entry.status.as_str().to_owned(),
+            attempted_at: entry.attempt.attempted_at,
+            response_status: entry.attempt.response_status,
+            error: entry.attempt.error,
+        }
+    }
+}
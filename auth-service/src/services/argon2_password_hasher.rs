@@ -0,0 +1,257 @@
+use std::sync::Arc;
+
+use argon2::{
+    password_hash::{self, SaltString},
+    Algorithm, Argon2, KeyId, Params, ParamsBuilder, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::Semaphore;
+
+/// Stored in the `keyid` field of hashes computed with the pepper, so hashes
+/// from before the pepper was configured can still be verified and upgraded.
+const PEPPER_KEY_ID: &[u8] = b"pepper";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordVerification {
+    Valid,
+    /// The password matched, but the hash is weaker than the current policy.
+    NeedsRehash,
+    Mismatch,
+}
+
+/// Hashes passwords with Argon2id on the blocking pool, running at most a
+/// fixed number of hashes at once so login bursts cannot exhaust the pool.
+#[derive(Clone)]
+pub struct Argon2PasswordHasher {
+    params: Params,
+    pepper: Option<Secret<String>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Argon2PasswordHasher {
+    pub fn new(params: Params, pepper: Option<Secret<String>>, max_concurrent: usize) -> Self {
+        Self {
+            params,
+            pepper,
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+        }
+    }
+
+    #[tracing::instrument(name = "Computing password hash", skip_all)]
+    pub async fn hash(&self, password: Secret<String>) -> Result<Secret<String>> {
+        let hasher = self.clone();
+
+        self.run_blocking(move || {
+            let params = match hasher.pepper {
+                Some(_) => ParamsBuilder::new()
+                    .m_cost(hasher.params.m_cost())
+                    .t_cost(hasher.params.t_cost())
+                    .p_cost(hasher.params.p_cost())
+                    .keyid(KeyId::new(PEPPER_KEY_ID)?)
+                    .build()?,
+                None => hasher.params.clone(),
+            };
+
+            let salt = SaltString::generate(&mut rand::thread_rng());
+            let password_hash = hasher
+                .argon2(hasher.pepper.is_some(), params)?
+                .hash_password(password.expose_secret().as_bytes(), &salt)?
+                .to_string();
+
+            Ok(Secret::new(password_hash))
+        })
+        .await
+    }
+
+    /// Fails only if the hash cannot be checked, e.g. because it is malformed
+    /// or needs a pepper that is not set.
+    #[tracing::instrument(name = "Verify password hash", skip_all)]
+    pub async fn verify(
+        &self,
+        expected_password_hash: Secret<String>,
+        password_candidate: Secret<String>,
+    ) -> Result<PasswordVerification> {
+        let hasher = self.clone();
+
+        self.run_blocking(move || {
+            let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())?;
+            let params = Params::try_from(&expected_password_hash)?;
+            let peppered = params.keyid() == PEPPER_KEY_ID;
+
+            let result = hasher.argon2(peppered, params.clone())?.verify_password(
+                password_candidate.expose_secret().as_bytes(),
+                &expected_password_hash,
+            );
+            match result {
+                Ok(()) => {}
+                Err(password_hash::Error::Password) => return Ok(PasswordVerification::Mismatch),
+                Err(e) => return Err(e.into()),
+            }
+
+            if hasher.needs_rehash(&expected_password_hash, &params, peppered) {
+                Ok(PasswordVerification::NeedsRehash)
+            } else {
+                Ok(PasswordVerification::Valid)
+            }
+        })
+        .await
+    }
+
+    fn argon2(&self, peppered: bool, params: Params) -> Result<Argon2<'_>> {
+        match (peppered, &self.pepper) {
+            (true, Some(pepper)) => Ok(Argon2::new_with_secret(
+                pepper.expose_secret().as_bytes(),
+                Algorithm::Argon2id,
+                Version::V0x13,
+                params,
+            )?),
+            (true, None) => Err(eyre!("password hash is peppered, but no pepper is set")),
+            (false, _) => Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params)),
+        }
+    }
+
+    fn needs_rehash(&self, hash: &PasswordHash<'_>, params: &Params, peppered: bool) -> bool {
+        hash.algorithm != Algorithm::Argon2id.ident()
+            || hash.version != Some(Version::V0x13.into())
+            || params.m_cost() < self.params.m_cost()
+            || params.t_cost() < self.params.t_cost()
+            || params.p_cost() < self.params.p_cost()
+            || peppered != self.pepper.is_some()
+    }
+
+    async fn run_blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        // Wait here rather than queueing on the blocking pool, which other
+        // blocking work such as DNS lookups and file IO shares. The permit
+        // moves into the task, so it is held even if this future is dropped.
+        let permit = self.permits.clone().acquire_owned().await?;
+
+        let current_span: tracing::Span = tracing::Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            current_span.in_scope(f)
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(m_cost: u32, t_cost: u32) -> Params {
+        Params::new(m_cost, t_cost, 1, None).unwrap()
+    }
+
+    fn secret(value: &str) -> Secret<String> {
+        Secret::new(value.to_owned())
+    }
+
+    #[tokio::test]
+    async fn should_verify_matching_password() {
+        let hasher = Argon2PasswordHasher::new(params(1024, 1), None, 1);
+
+        let hash = hasher.hash(secret("password123")).await.unwrap();
+
+        assert!(hash
+            .expose_secret()
+            .starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert_eq!(
+            hasher
+                .verify(hash.clone(), secret("password123"))
+                .await
+                .unwrap(),
+            PasswordVerification::Valid
+        );
+        assert_eq!(
+            hasher.verify(hash, secret("password124")).await.unwrap(),
+            PasswordVerification::Mismatch
+        );
+    }
+
+    #[tokio::test]
+    async fn should_request_rehash_for_weaker_params() {
+        let weak = Argon2PasswordHasher::new(params(1024, 1), None, 1);
+        let hash = weak.hash(secret("password123")).await.unwrap();
+
+        for stronger in [params(2048, 1), params(1024, 2)] {
+            let hasher = Argon2PasswordHasher::new(stronger, None, 1);
+            assert_eq!(
+                hasher
+                    .verify(hash.clone(), secret("password123"))
+                    .await
+                    .unwrap(),
+                PasswordVerification::NeedsRehash
+            );
+        }
+
+        // Hashes stronger than the policy are left alone
+        let hasher = Argon2PasswordHasher::new(params(512, 1), None, 1);
+        assert_eq!(
+            hasher.verify(hash, secret("password123")).await.unwrap(),
+            PasswordVerification::Valid
+        );
+    }
+
+    #[tokio::test]
+    async fn should_pepper_hashes() {
+        let hasher = Argon2PasswordHasher::new(params(1024, 1), Some(secret("pepper")), 1);
+        let hash = hasher.hash(secret("password123")).await.unwrap();
+
+        assert_eq!(
+            hasher
+                .verify(hash.clone(), secret("password123"))
+                .await
+                .unwrap(),
+            PasswordVerification::Valid
+        );
+
+        let other_pepper = Argon2PasswordHasher::new(params(1024, 1), Some(secret("salt")), 1);
+        assert_eq!(
+            other_pepper
+                .verify(hash.clone(), secret("password123"))
+                .await
+                .unwrap(),
+            PasswordVerification::Mismatch
+        );
+
+        let no_pepper = Argon2PasswordHasher::new(params(1024, 1), None, 1);
+        assert!(no_pepper.verify(hash, secret("password123")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_request_rehash_when_pepper_is_added() {
+        let hash = Argon2PasswordHasher::new(params(1024, 1), None, 1)
+            .hash(secret("password123"))
+            .await
+            .unwrap();
+
+        let hasher = Argon2PasswordHasher::new(params(1024, 1), Some(secret("pepper")), 1);
+
+        assert_eq!(
+            hasher.verify(hash, secret("password123")).await.unwrap(),
+            PasswordVerification::NeedsRehash
+        );
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_a_free_permit() {
+        let hasher = Argon2PasswordHasher::new(params(1024, 1), None, 2);
+        let busy = hasher.permits.clone().acquire_many_owned(2).await.unwrap();
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            hasher.hash(secret("password123")),
+        )
+        .await;
+        assert!(waiting.is_err());
+
+        drop(busy);
+        assert!(hasher.hash(secret("password123")).await.is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    EmailQueueDepth, OutboxEmail, OutboxEmailStatus,
+};
+
+#[derive(Default)]
+pub struct HashmapEmailOutboxStore {
+    emails: RwLock<HashMap<Uuid, StoredEmail>>,
+}
+
+struct StoredEmail {
+    email: OutboxEmail,
+    status: OutboxEmailStatus,
+    next_attempt_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+fn get_mut<'a>(
+    emails: &'a mut HashMap<Uuid, StoredEmail>,
+    id: &Uuid,
+) -> Result<&'a mut StoredEmail, EmailOutboxStoreError> {
+    emails
+        .get_mut(id)
+        .ok_or(EmailOutboxStoreError::EmailNotFound)
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for HashmapEmailOutboxStore {
+    async fn enqueue_email(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        self.emails.write().unwrap().insert(
+            email.id,
+            StoredEmail {
+                email,
+                status: OutboxEmailStatus::Pending,
+                next_attempt_at: Utc::now(),
+                created_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn claim_due_emails(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        let now = Utc::now();
+
+        let mut emails = self.emails.write().unwrap();
+        let mut due: Vec<&mut StoredEmail> = emails
+            .values_mut()
+            .filter(|stored| {
+                stored.status == OutboxEmailStatus::Pending && stored.next_attempt_at <= now
+            })
+            .collect();
+
+        due.sort_by_key(|stored| stored.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|stored| {
+                stored.next_attempt_at = lease_until;
+                stored.email.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_sent(&self, id: &Uuid) -> Result<(), EmailOutboxStoreError> {
+        let mut emails = self.emails.write().unwrap();
+        let stored = get_mut(&mut emails, id)?;
+        stored.email.attempts += 1;
+        stored.status = OutboxEmailStatus::Sent;
+        stored.email.message.html_body.clear();
+        stored.email.message.text_body.clear();
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        id: &Uuid,
+        _error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let mut emails = self.emails.write().unwrap();
+        let stored = get_mut(&mut emails, id)?;
+        stored.email.attempts += 1;
+        match retry_at {
+            Some(retry_at) => stored.next_attempt_at = retry_at,
+            None => stored.status = OutboxEmailStatus::DeadLettered,
+        }
+        Ok(())
+    }
+
+    async fn queue_depth(&self) -> Result<EmailQueueDepth, EmailOutboxStoreError> {
+        let emails = self.emails.read().unwrap();
+        let count = |status| {
+            emails
+                .values()
+                .filter(|stored| stored.status == status)
+                .count() as u64
+        };
+
+        Ok(EmailQueueDepth {
+            pending: count(OutboxEmailStatus::Pending),
+            dead_lettered: count(OutboxEmailStatus::DeadLettered),
+        })
+    }
+
+    async fn delete_finished(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, EmailOutboxStoreError> {
+        let mut emails = self.emails.write().unwrap();
+        let before = emails.len();
+        emails.retain(|_, stored| {
+            stored.status == OutboxEmailStatus::Pending || stored.created_at >= created_before
+        });
+        Ok((before - emails.len()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::domain::{Email, EmailMessage};
+
+    fn email() -> OutboxEmail {
+        OutboxEmail::new(EmailMessage {
+            recipient: Email::parse("test@example.com".to_owned()).unwrap(),
+            subject: "Subject".to_owned(),
+            html_body: "<p>Content</p>".to_owned(),
+            text_body: "Content".to_owned(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_claimed_emails_are_leased() {
+        let store = HashmapEmailOutboxStore::default();
+        store.enqueue_email(email()).await.unwrap();
+
+        let lease_until = Utc::now() + Duration::minutes(1);
+        let first = store.claim_due_emails(10, lease_until).await.unwrap();
+        let second = store.claim_due_emails(10, lease_until).await.unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert!(second.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sent_emails_leave_the_queue() {
+        let store = HashmapEmailOutboxStore::default();
+        let email = email();
+        store.enqueue_email(email.clone()).await.unwrap();
+
+        store.mark_sent(&email.id).await.unwrap();
+
+        let due = store.claim_due_emails(10, Utc::now()).await.unwrap();
+        assert!(due.is_empty());
+        assert_eq!(
+            store.queue_depth().await.unwrap(),
+            EmailQueueDepth::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed_retries_then_dead_letters() {
+        let store = HashmapEmailOutboxStore::default();
+        let email = email();
+        store.enqueue_email(email.clone()).await.unwrap();
+
+        store
+            .mark_failed(&email.id, "HTTP 500", Some(Utc::now()))
+            .await
+            .unwrap();
+
+        let retried = store.claim_due_emails(10, Utc::now()).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].attempts, 1);
+
+        store
+            .mark_failed(&email.id, "HTTP 500", None)
+            .await
+            .unwrap();
+
+        let due = store.claim_due_emails(10, Utc::now()).await.unwrap();
+        assert!(due.is_empty());
+        assert_eq!(
+            store.queue_depth().await.unwrap(),
+            EmailQueueDepth {
+                pending: 0,
+                dead_lettered: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_sent_clears_the_bodies() {
+        let store = HashmapEmailOutboxStore::default();
+        let email = email();
+        store.enqueue_email(email.clone()).await.unwrap();
+
+        store.mark_sent(&email.id).await.unwrap();
+
+        let emails = store.emails.read().unwrap();
+        let message = &emails[&email.id].email.message;
+        assert!(message.html_body.is_empty());
+        assert!(message.text_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_finished_keeps_pending_and_recent_emails() {
+        let store = HashmapEmailOutboxStore::default();
+        let (pending, sent, dead_lettered) = (email(), email(), email());
+        for email in [&pending, &sent, &dead_lettered] {
+            store.enqueue_email(email.clone()).await.unwrap();
+        }
+        store.mark_sent(&sent.id).await.unwrap();
+        store
+            .mark_failed(&dead_lettered.id, "HTTP 500", None)
+            .await
+            .unwrap();
+
+        let deleted = store
+            .delete_finished(Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 0);
+
+        let deleted = store
+            .delete_finished(Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        let emails = store.emails.read().unwrap();
+        assert_eq!(emails.len(), 1);
+        assert!(emails.contains_key(&pending.id));
+    }
+
+    #[tokio::test]
+    async fn test_mark_sent_for_unknown_email() {
+        let store = HashmapEmailOutboxStore::default();
+
+        let result = store.mark_sent(&Uuid::new_v4()).await;
+
+        assert!(matches!(result, Err(EmailOutboxStoreError::EmailNotFound)));
+    }
+}
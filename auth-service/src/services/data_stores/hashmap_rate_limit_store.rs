@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+
+use crate::{
+    domain::data_stores::{
+        ExpiringStore, RateLimitDecision, RateLimitPolicy, RateLimitStore, RateLimitStoreError,
+    },
+    utils::clock::{ClockType, SystemClock},
+};
+
+pub struct HashmapRateLimitStore {
+    /// Keys can come from request bodies, so buckets that have refilled are
+    /// swept away rather than kept forever.
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    clock: ClockType,
+}
+
+impl Default for HashmapRateLimitStore {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl HashmapRateLimitStore {
+    pub fn with_clock(clock: ClockType) -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn consume_token(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let now = self.clock.now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| TokenBucket::full(policy, now));
+
+        Ok(bucket.consume(policy, now))
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapRateLimitStore {
+    /// A bucket that has refilled behaves like a missing one, so it can go.
+    async fn delete_expired(&self) -> Result<u64> {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| bucket.full_at > now);
+        Ok((before - buckets.len()) as u64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+    /// When the bucket will have refilled, as of the last update.
+    full_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(policy: &RateLimitPolicy, now: Instant) -> Self {
+        Self {
+            tokens: policy.capacity as f64,
+            updated_at: now,
+            full_at: now,
+        }
+    }
+
+    fn consume(&mut self, policy: &RateLimitPolicy, now: Instant) -> RateLimitDecision {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * policy.refill_per_second).min(policy.capacity as f64);
+        self.updated_at = now;
+
+        let decision = if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            RateLimitDecision::Allowed {
+                remaining: self.tokens.floor() as u32,
+            }
+        } else {
+            let missing = 1.0 - self.tokens;
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs_f64(missing / policy.refill_per_second),
+            }
+        };
+
+        let missing = policy.capacity as f64 - self.tokens;
+        self.full_at = if missing > 0.0 {
+            now + Duration::from_secs_f64(missing / policy.refill_per_second)
+        } else {
+            now
+        };
+
+        decision
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::clock::ManualClock;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_consume_token_until_limited() {
+        let store = HashmapRateLimitStore::default();
+        let policy = RateLimitPolicy::new(2, 0.001);
+
+        let result = store.consume_token("key", &policy).await;
+        assert_eq!(result.unwrap(), RateLimitDecision::Allowed { remaining: 1 });
+
+        let result = store.consume_token("key", &policy).await;
+        assert_eq!(result.unwrap(), RateLimitDecision::Allowed { remaining: 0 });
+
+        let result = store.consume_token("key", &policy).await;
+        assert!(matches!(result.unwrap(), RateLimitDecision::Limited { .. }));
+
+        // Other keys have their own bucket
+        let result = store.consume_token("other_key", &policy).await;
+        assert_eq!(result.unwrap(), RateLimitDecision::Allowed { remaining: 1 });
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let policy = RateLimitPolicy::new(1, 2.0);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&policy, start);
+
+        assert_eq!(
+            bucket.consume(&policy, start),
+            RateLimitDecision::Allowed { remaining: 0 }
+        );
+        assert_eq!(
+            bucket.consume(&policy, start),
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_millis(500)
+            }
+        );
+        assert_eq!(
+            bucket.consume(&policy, start + Duration::from_millis(500)),
+            RateLimitDecision::Allowed { remaining: 0 }
+        );
+    }
+
+    #[test]
+    fn test_bucket_never_exceeds_capacity() {
+        let policy = RateLimitPolicy::new(3, 1.0);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(&policy, start);
+
+        let decision = bucket.consume(&policy, start + Duration::from_secs(3600));
+
+        assert_eq!(decision, RateLimitDecision::Allowed { remaining: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_refilled_buckets() {
+        let clock = ManualClock::default();
+        let store = HashmapRateLimitStore::with_clock(Arc::new(clock.clone()));
+        let policy = RateLimitPolicy::new(2, 1.0);
+
+        store.consume_token("idle", &policy).await.unwrap();
+        clock.advance(Duration::from_millis(500));
+        store.consume_token("busy", &policy).await.unwrap();
+        store.consume_token("busy", &policy).await.unwrap();
+        clock.advance(Duration::from_millis(500));
+
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert!(!store.buckets.lock().unwrap().contains_key("idle"));
+        assert!(store.buckets.lock().unwrap().contains_key("busy"));
+
+        clock.advance(Duration::from_secs(2));
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert!(store.buckets.lock().unwrap().is_empty());
+    }
+}
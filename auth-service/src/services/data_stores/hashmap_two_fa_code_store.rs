@@ -1,91 +1,215 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use color_eyre::eyre::Result;
 
-use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    email::Email,
+use crate::{
+    domain::{
+        data_stores::{
+            ExpiringStore, LoginAttemptId, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+            TwoFACodeStoreError, TWO_FA_CODE_TTL,
+        },
+        email::Email,
+    },
+    utils::clock::{ClockType, SystemClock},
 };
 
-#[derive(Default)]
+struct Entry {
+    login_attempt_id: LoginAttemptId,
+    code: TwoFACode,
+    expires_at: Instant,
+}
+
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    /// Behind a lock so that lookups can evict the expired codes they come across.
+    codes: Mutex<HashMap<(Email, TwoFACodePurpose), Entry>>,
+    clock: ClockType,
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn with_clock(clock: ClockType) -> Self {
+        Self {
+            codes: Mutex::new(HashMap::new()),
+            clock,
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
+        purpose: &TwoFACodePurpose,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        let entry = Entry {
+            login_attempt_id,
+            code,
+            expires_at: self.clock.now() + TWO_FA_CODE_TTL,
+        };
+        self.codes
+            .lock()
+            .unwrap()
+            .insert((email, purpose.clone()), entry);
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(email) {
-            Some(_) => Ok(()),
-            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
-        }
+    async fn remove_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+    ) -> Result<(), TwoFACodeStoreError> {
+        self.codes
+            .lock()
+            .unwrap()
+            .remove(&(email.clone(), purpose.clone()));
+        Ok(())
     }
 
     async fn get_code(
         &self,
         email: &Email,
+        purpose: &TwoFACodePurpose,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        match self.codes.get(email) {
-            Some(value) => Ok(value.clone()),
+        let key = (email.clone(), purpose.clone());
+        let now = self.clock.now();
+        let mut codes = self.codes.lock().unwrap();
+
+        match codes.get(&key) {
+            Some(entry) if entry.expires_at > now => {
+                Ok((entry.login_attempt_id.clone(), entry.code.clone()))
+            }
+            Some(_) => {
+                codes.remove(&key);
+                Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+            }
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn take_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<bool, TwoFACodeStoreError> {
+        let key = (email.clone(), purpose.clone());
+        let now = self.clock.now();
+        let mut codes = self.codes.lock().unwrap();
+
+        let matches = codes.get(&key).is_some_and(|entry| {
+            entry.expires_at > now
+                && entry.login_attempt_id == *login_attempt_id
+                && entry.code == *code
+        });
+        if matches {
+            codes.remove(&key);
+        }
+
+        Ok(matches)
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashmapTwoFACodeStore {
+    async fn delete_expired(&self) -> Result<u64> {
+        let now = self.clock.now();
+        let mut codes = self.codes.lock().unwrap();
+        let before = codes.len();
+        codes.retain(|_, entry| entry.expires_at > now);
+        Ok((before - codes.len()) as u64)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
+    use crate::utils::clock::ManualClock;
+
     use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_string()).unwrap()
+    }
+
     #[tokio::test]
     async fn test_add_code() {
-        let mut store = HashmapTwoFACodeStore::default();
-        let email = Email::parse("test@example.com".to_string()).unwrap();
+        let store = HashmapTwoFACodeStore::default();
+        let email = email();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
 
         let result = store
-            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                login_attempt_id.clone(),
+                code.clone(),
+            )
             .await;
 
         assert!(result.is_ok());
-        assert_eq!(store.codes.get(&email), Some(&(login_attempt_id, code)));
+        assert_eq!(
+            store.get_code(&email, &TwoFACodePurpose::Login).await,
+            Ok((login_attempt_id, code))
+        );
     }
 
     #[tokio::test]
     async fn test_remove_code() {
-        let mut store = HashmapTwoFACodeStore::default();
-        let email = Email::parse("test@example.com".to_string()).unwrap();
-        let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
-
+        let store = HashmapTwoFACodeStore::default();
+        let email = email();
         store
-            .codes
-            .insert(email.clone(), (login_attempt_id.clone(), code.clone()));
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
 
-        let result = store.remove_code(&email).await;
+        let result = store.remove_code(&email, &TwoFACodePurpose::Login).await;
 
         assert!(result.is_ok());
-        assert_eq!(store.codes.get(&email), None);
+        assert!(store
+            .codes
+            .lock()
+            .unwrap()
+            .get(&(email, TwoFACodePurpose::Login))
+            .is_none());
     }
 
     #[tokio::test]
     async fn test_get_code() {
-        let mut store = HashmapTwoFACodeStore::default();
-        let email = Email::parse("test@example.com".to_string()).unwrap();
+        let store = HashmapTwoFACodeStore::default();
+        let email = email();
         let login_attempt_id = LoginAttemptId::default();
         let code = TwoFACode::default();
         store
-            .codes
-            .insert(email.clone(), (login_attempt_id.clone(), code.clone()));
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                login_attempt_id.clone(),
+                code.clone(),
+            )
+            .await
+            .unwrap();
 
-        let result = store.get_code(&email).await;
+        let result = store.get_code(&email, &TwoFACodePurpose::Login).await;
 
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), (login_attempt_id, code));
@@ -94,9 +218,9 @@ mod tests {
     #[tokio::test]
     async fn test_get_code_not_found() {
         let store = HashmapTwoFACodeStore::default();
-        let email = Email::parse("test@example.com".to_string()).unwrap();
+        let email = email();
 
-        let result = store.get_code(&email).await;
+        let result = store.get_code(&email, &TwoFACodePurpose::Login).await;
 
         assert!(result.is_err());
         assert_eq!(
@@ -104,4 +228,104 @@ mod tests {
             TwoFACodeStoreError::LoginAttemptIdNotFound
         );
     }
+
+    #[tokio::test]
+    async fn test_code_expires_after_ttl() {
+        let clock = ManualClock::default();
+        let store = HashmapTwoFACodeStore::with_clock(Arc::new(clock.clone()));
+        let email = email();
+        store
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+
+        clock.advance(TWO_FA_CODE_TTL - Duration::from_secs(1));
+        assert!(store
+            .get_code(&email, &TwoFACodePurpose::Login)
+            .await
+            .is_ok());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            store.get_code(&email, &TwoFACodePurpose::Login).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert!(store.codes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_new_code_restarts_expiry() {
+        let clock = ManualClock::default();
+        let store = HashmapTwoFACodeStore::with_clock(Arc::new(clock.clone()));
+        let email = email();
+        store
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+
+        clock.advance(TWO_FA_CODE_TTL / 2);
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+        store
+            .add_code(
+                email.clone(),
+                &TwoFACodePurpose::Login,
+                login_attempt_id.clone(),
+                code.clone(),
+            )
+            .await
+            .unwrap();
+        clock.advance(TWO_FA_CODE_TTL / 2);
+
+        assert_eq!(
+            store.get_code(&email, &TwoFACodePurpose::Login).await,
+            Ok((login_attempt_id, code))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_expired_codes() {
+        let clock = ManualClock::default();
+        let store = HashmapTwoFACodeStore::with_clock(Arc::new(clock.clone()));
+        let old_email = Email::parse("old@example.com".to_string()).unwrap();
+        let new_email = Email::parse("new@example.com".to_string()).unwrap();
+
+        store
+            .add_code(
+                old_email,
+                &TwoFACodePurpose::Login,
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        clock.advance(TWO_FA_CODE_TTL / 2);
+        store
+            .add_code(
+                new_email.clone(),
+                &TwoFACodePurpose::Login,
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        clock.advance(TWO_FA_CODE_TTL / 2);
+
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert_eq!(store.delete_expired().await.unwrap(), 0);
+        assert!(store
+            .get_code(&new_email, &TwoFACodePurpose::Login)
+            .await
+            .is_ok());
+    }
 }
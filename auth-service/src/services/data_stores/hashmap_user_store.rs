@@ -1,24 +1,59 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::RwLock,
+};
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use crate::domain::{Email, Password, PhoneNumber, TwoFAChannel, User, UserStore, UserStoreError};
 
 #[derive(Default)]
 pub struct HashmapUserStore {
+    /// One lock for both maps, so that a password and its history change together.
+    inner: RwLock<Inner>,
+    password_history_size: usize,
+}
+
+#[derive(Default)]
+struct Inner {
     users: HashMap<Email, User>,
+    /// Each user's recent passwords, newest first.
+    password_history: HashMap<Email, VecDeque<Password>>,
+}
+
+impl HashmapUserStore {
+    pub fn new(password_history_size: usize) -> Self {
+        Self {
+            password_history_size,
+            ..Default::default()
+        }
+    }
+}
+
+impl Inner {
+    fn remember_password(&mut self, email: &Email, password: Password, history_size: usize) {
+        let history = self.password_history.entry(email.clone()).or_default();
+        history.push_front(password);
+        history.truncate(history_size);
+    }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if self.users.contains_key(&user.email) {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        if inner.users.contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
-        self.users.insert(user.email.clone(), user);
+        inner.remember_password(
+            &user.email,
+            user.password.clone(),
+            self.password_history_size,
+        );
+        inner.users.insert(user.email.clone(), user);
         Ok(())
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+        match self.inner.read().unwrap().users.get(email) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
@@ -29,7 +64,7 @@ impl UserStore for HashmapUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
+        match self.inner.read().unwrap().users.get(email) {
             Some(user) => {
                 if user.password.eq(password) {
                     Ok(())
@@ -40,21 +75,91 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let Inner {
+            users,
+            password_history,
+        } = &mut *inner;
+        let user = users.get_mut(email).ok_or(UserStoreError::UserNotFound)?;
+
+        let history = password_history.get(email);
+        let reused = self.password_history_size > 0
+            && (user.password == password || history.is_some_and(|h| h.contains(&password)));
+        if reused {
+            return Err(UserStoreError::PasswordReused);
+        }
+
+        user.password = password.clone();
+        inner.remember_password(email, password, self.password_history_size);
+        Ok(())
+    }
+
+    async fn set_pending_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let user = inner
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.pending_phone_number = Some(phone_number);
+        Ok(())
+    }
+
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let user = inner
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        if user.pending_phone_number.as_ref() != Some(phone_number) {
+            return Err(UserStoreError::PhoneNumberMismatch);
+        }
+        user.phone_number = user.pending_phone_number.take();
+        user.phone_verified = true;
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let user = inner
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.two_fa_channel = channel;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use secrecy::{Secret};
     use super::*;
+    use secrecy::Secret;
 
     #[tokio::test]
     async fn test_add_user() {
-        let mut user_store = HashmapUserStore::default();
-        let user = User {
-            email: Email::parse("test@example.com".to_owned()).unwrap(),
-            password: Password::parse(Secret::new("password".to_owned())).unwrap(),
-            requires_2fa: false,
-        };
+        let user_store = HashmapUserStore::default();
+        let user = User::new(
+            Email::parse("test@example.com".to_owned()).unwrap(),
+            Password::parse(Secret::new("password".to_owned())).unwrap(),
+            false,
+        );
 
         // Test adding a new user
         let result = user_store.add_user(user.clone()).await;
@@ -67,17 +172,22 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse("test@example.com".to_owned()).unwrap();
 
-        let user = User {
-            email: email.clone(),
-            password: Password::parse(Secret::new("password".to_owned())).unwrap(),
-            requires_2fa: false,
-        };
+        let user = User::new(
+            email.clone(),
+            Password::parse(Secret::new("password".to_owned())).unwrap(),
+            false,
+        );
 
         // Test getting a user that exists
-        user_store.users.insert(email.clone(), user.clone());
+        user_store
+            .inner
+            .write()
+            .unwrap()
+            .users
+            .insert(email.clone(), user.clone());
         let result = user_store.get_user(&email).await;
         assert_eq!(result, Ok(user));
 
@@ -91,18 +201,19 @@ mod tests {
 
     #[tokio::test]
     async fn test_validate_user() {
-        let mut user_store = HashmapUserStore::default();
+        let user_store = HashmapUserStore::default();
         let email = Email::parse("test@example.com".to_owned()).unwrap();
         let password = Password::parse(Secret::new("password".to_owned())).unwrap();
 
-        let user = User {
-            email: email.clone(),
-            password: password.clone(),
-            requires_2fa: false,
-        };
+        let user = User::new(email.clone(), password.clone(), false);
 
         // Test validating a user that exists with correct password
-        user_store.users.insert(email.clone(), user.clone());
+        user_store
+            .inner
+            .write()
+            .unwrap()
+            .users
+            .insert(email.clone(), user.clone());
         let result = user_store.validate_user(&email, &password).await;
         assert_eq!(result, Ok(()));
 
@@ -121,4 +232,136 @@ mod tests {
 
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let old_password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let new_password = Password::parse(Secret::new("new-password".to_owned())).unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), old_password.clone(), false))
+            .await
+            .unwrap();
+
+        let result = user_store
+            .update_password(&email, new_password.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.validate_user(&email, &new_password).await,
+            Ok(())
+        );
+        assert_eq!(
+            user_store.validate_user(&email, &old_password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+
+        // Without a history, any password can be reused
+        let result = user_store.update_password(&email, old_password).await;
+        assert_eq!(result, Ok(()));
+
+        // Test updating a user that doesn't exist
+        let result = user_store
+            .update_password(
+                &Email::parse("nonexistent@example.com".to_owned()).unwrap(),
+                new_password,
+            )
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_update_password_rejects_recent_passwords() {
+        let user_store = HashmapUserStore::new(2);
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let password = |s: &str| Password::parse(Secret::new(s.to_owned())).unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password("password-1"), false))
+            .await
+            .unwrap();
+
+        // The current password counts towards the history
+        let result = user_store
+            .update_password(&email, password("password-1"))
+            .await;
+        assert_eq!(result, Err(UserStoreError::PasswordReused));
+
+        let result = user_store
+            .update_password(&email, password("password-2"))
+            .await;
+        assert_eq!(result, Ok(()));
+        let result = user_store
+            .update_password(&email, password("password-1"))
+            .await;
+        assert_eq!(result, Err(UserStoreError::PasswordReused));
+
+        // Older passwords are pruned and can be used again
+        let result = user_store
+            .update_password(&email, password("password-3"))
+            .await;
+        assert_eq!(result, Ok(()));
+        let result = user_store
+            .update_password(&email, password("password-1"))
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.inner.read().unwrap().password_history[&email].len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_phone_number_verification() {
+        let user_store = HashmapUserStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse("+14155550123".to_owned()).unwrap();
+        let other_phone_number = PhoneNumber::parse("+14155550199".to_owned()).unwrap();
+
+        user_store
+            .add_user(User::new(email.clone(), password, true))
+            .await
+            .unwrap();
+        user_store
+            .set_pending_phone_number(&email, phone_number.clone())
+            .await
+            .unwrap();
+
+        // Test verifying a number that is not the pending one
+        let result = user_store
+            .verify_phone_number(&email, &other_phone_number)
+            .await;
+        assert_eq!(result, Err(UserStoreError::PhoneNumberMismatch));
+
+        user_store
+            .verify_phone_number(&email, &phone_number)
+            .await
+            .unwrap();
+        user_store
+            .set_two_fa_channel(&email, TwoFAChannel::Sms)
+            .await
+            .unwrap();
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.sms_phone_number(), Some(&phone_number));
+
+        // Test that the verified number keeps working until a new one is verified
+        user_store
+            .set_pending_phone_number(&email, other_phone_number.clone())
+            .await
+            .unwrap();
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.sms_phone_number(), Some(&phone_number));
+        assert_eq!(user.pending_phone_number, Some(other_phone_number.clone()));
+
+        user_store
+            .verify_phone_number(&email, &other_phone_number)
+            .await
+            .unwrap();
+        let user = user_store.get_user(&email).await.unwrap();
+        assert_eq!(user.sms_phone_number(), Some(&other_phone_number));
+        assert_eq!(user.pending_phone_number, None);
+    }
 }
@@ -0,0 +1,318 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{WebhookStore, WebhookStoreError},
+    WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryLogEntry, WebhookDeliveryStatus,
+    WebhookEvent, WebhookSubscription,
+};
+
+#[derive(Default)]
+pub struct HashmapWebhookStore {
+    /// One lock for everything, so that removing a subscription also removes its
+    /// deliveries and attempts.
+    inner: RwLock<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    subscriptions: HashMap<Uuid, WebhookSubscription>,
+    deliveries: HashMap<Uuid, StoredDelivery>,
+    attempts: Vec<WebhookDeliveryAttempt>,
+}
+
+struct StoredDelivery {
+    delivery: WebhookDelivery,
+    status: WebhookDeliveryStatus,
+    next_attempt_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for HashmapWebhookStore {
+    async fn add_subscription(
+        &self,
+        subscription: WebhookSubscription,
+    ) -> Result<(), WebhookStoreError> {
+        self.inner
+            .write()
+            .unwrap()
+            .subscriptions
+            .insert(subscription.id, subscription);
+        Ok(())
+    }
+
+    async fn get_subscriptions(&self) -> Result<Vec<WebhookSubscription>, WebhookStoreError> {
+        let inner = self.inner.read().unwrap();
+        let mut subscriptions: Vec<_> = inner.subscriptions.values().cloned().collect();
+        subscriptions.sort_by_key(|subscription| subscription.created_at);
+        Ok(subscriptions)
+    }
+
+    async fn remove_subscription(&self, id: &Uuid) -> Result<(), WebhookStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        inner.subscriptions.remove(id);
+
+        let removed: Vec<Uuid> = inner
+            .deliveries
+            .iter()
+            .filter(|(_, stored)| stored.delivery.subscription_id == *id)
+            .map(|(delivery_id, _)| *delivery_id)
+            .collect();
+
+        for delivery_id in removed.iter() {
+            inner.deliveries.remove(delivery_id);
+        }
+        inner
+            .attempts
+            .retain(|attempt| !removed.contains(&attempt.delivery_id));
+
+        Ok(())
+    }
+
+    async fn enqueue_event(&self, event: &WebhookEvent) -> Result<(), WebhookStoreError> {
+        let payload = event.payload();
+        let mut inner = self.inner.write().unwrap();
+        let Inner {
+            subscriptions,
+            deliveries,
+            ..
+        } = &mut *inner;
+
+        for subscription in subscriptions.values() {
+            if !subscription.is_subscribed_to(event.event_type) {
+                continue;
+            }
+
+            let delivery = WebhookDelivery {
+                id: Uuid::new_v4(),
+                subscription_id: subscription.id,
+                url: subscription.url.clone(),
+                secret: subscription.secret.clone(),
+                event_id: event.id,
+                event_type: event.event_type,
+                payload: payload.clone(),
+                attempts: 0,
+            };
+
+            deliveries.insert(
+                delivery.id,
+                StoredDelivery {
+                    delivery,
+                    status: WebhookDeliveryStatus::Pending,
+                    next_attempt_at: event.occurred_at,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn claim_due_deliveries(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        let now = Utc::now();
+
+        let mut inner = self.inner.write().unwrap();
+        let mut due: Vec<&mut StoredDelivery> = inner
+            .deliveries
+            .values_mut()
+            .filter(|stored| {
+                stored.status == WebhookDeliveryStatus::Pending && stored.next_attempt_at <= now
+            })
+            .collect();
+
+        due.sort_by_key(|stored| stored.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|stored| {
+                stored.next_attempt_at = lease_until;
+                stored.delivery.clone()
+            })
+            .collect())
+    }
+
+    async fn record_attempt(
+        &self,
+        attempt: WebhookDeliveryAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookStoreError> {
+        let mut inner = self.inner.write().unwrap();
+        let stored = inner
+            .deliveries
+            .get_mut(&attempt.delivery_id)
+            .ok_or(WebhookStoreError::DeliveryNotFound)?;
+
+        stored.delivery.attempts += 1;
+        match (attempt.succeeded(), retry_at) {
+            (true, _) => stored.status = WebhookDeliveryStatus::Delivered,
+            (false, Some(retry_at)) => stored.next_attempt_at = retry_at,
+            (false, None) => stored.status = WebhookDeliveryStatus::Failed,
+        }
+
+        inner.attempts.push(attempt);
+
+        Ok(())
+    }
+
+    async fn get_delivery_log(
+        &self,
+        subscription_id: &Uuid,
+    ) -> Result<Vec<WebhookDeliveryLogEntry>, WebhookStoreError> {
+        let inner = self.inner.read().unwrap();
+        let mut log: Vec<WebhookDeliveryLogEntry> = inner
+            .attempts
+            .iter()
+            .rev()
+            .filter_map(|attempt| {
+                let stored = inner.deliveries.get(&attempt.delivery_id)?;
+                (stored.delivery.subscription_id == *subscription_id).then(|| {
+                    WebhookDeliveryLogEntry {
+                        delivery_id: stored.delivery.id,
+                        event_id: stored.delivery.event_id,
+                        event_type: stored.delivery.event_type,
+                        status: stored.status,
+                        attempt: attempt.clone(),
+                    }
+                })
+            })
+            .collect();
+
+        log.sort_by_key(|entry| std::cmp::Reverse(entry.attempt.attempted_at));
+
+        Ok(log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::domain::{Email, WebhookEventType};
+
+    fn event(event_type: WebhookEventType) -> WebhookEvent {
+        WebhookEvent::new(
+            event_type,
+            Email::parse("test@example.com".to_owned()).unwrap(),
+        )
+    }
+
+    fn failed_attempt(delivery_id: Uuid) -> WebhookDeliveryAttempt {
+        WebhookDeliveryAttempt {
+            delivery_id,
+            attempted_at: Utc::now(),
+            response_status: Some(500),
+            error: Some("HTTP 500".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_event_only_for_matching_subscriptions() {
+        let store = HashmapWebhookStore::default();
+        let subscribed = WebhookSubscription::new(
+            "http://subscribed".to_owned(),
+            vec![WebhookEventType::UserSignedUp],
+        );
+        let not_subscribed = WebhookSubscription::new(
+            "http://not-subscribed".to_owned(),
+            vec![WebhookEventType::UserLoggedIn],
+        );
+        store.add_subscription(subscribed.clone()).await.unwrap();
+        store.add_subscription(not_subscribed).await.unwrap();
+
+        let event = event(WebhookEventType::UserSignedUp);
+        store.enqueue_event(&event).await.unwrap();
+
+        let due = store
+            .claim_due_deliveries(10, Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].subscription_id, subscribed.id);
+        assert_eq!(due[0].event_id, event.id);
+        assert_eq!(due[0].payload, event.payload());
+    }
+
+    #[tokio::test]
+    async fn test_claimed_deliveries_are_leased() {
+        let store = HashmapWebhookStore::default();
+        let subscription = WebhookSubscription::new(
+            "http://subscribed".to_owned(),
+            vec![WebhookEventType::UserSignedUp],
+        );
+        store.add_subscription(subscription).await.unwrap();
+        store
+            .enqueue_event(&event(WebhookEventType::UserSignedUp))
+            .await
+            .unwrap();
+
+        let lease_until = Utc::now() + Duration::minutes(1);
+        let first = store.claim_due_deliveries(10, lease_until).await.unwrap();
+        let second = store.claim_due_deliveries(10, lease_until).await.unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert!(second.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_record_attempt_retries_then_fails() {
+        let store = HashmapWebhookStore::default();
+        let subscription = WebhookSubscription::new(
+            "http://subscribed".to_owned(),
+            vec![WebhookEventType::UserSignedUp],
+        );
+        store.add_subscription(subscription.clone()).await.unwrap();
+        store
+            .enqueue_event(&event(WebhookEventType::UserSignedUp))
+            .await
+            .unwrap();
+        let delivery = store
+            .claim_due_deliveries(10, Utc::now())
+            .await
+            .unwrap()
+            .remove(0);
+
+        // Retry immediately
+        store
+            .record_attempt(failed_attempt(delivery.id), Some(Utc::now()))
+            .await
+            .unwrap();
+
+        let retried = store.claim_due_deliveries(10, Utc::now()).await.unwrap();
+        assert_eq!(retried.len(), 1);
+        assert_eq!(retried[0].attempts, 1);
+
+        // Give up
+        store
+            .record_attempt(failed_attempt(delivery.id), None)
+            .await
+            .unwrap();
+
+        let due = store.claim_due_deliveries(10, Utc::now()).await.unwrap();
+        assert!(due.is_empty());
+
+        let log = store.get_delivery_log(&subscription.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log
+            .iter()
+            .all(|entry| entry.status == WebhookDeliveryStatus::Failed));
+    }
+
+    #[tokio::test]
+    async fn test_record_attempt_for_unknown_delivery() {
+        let store = HashmapWebhookStore::default();
+
+        let result = store
+            .record_attempt(failed_attempt(Uuid::new_v4()), None)
+            .await;
+
+        assert!(matches!(result, Err(WebhookStoreError::DeliveryNotFound)));
+    }
+}
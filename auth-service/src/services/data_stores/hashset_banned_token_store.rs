@@ -1,46 +1,223 @@
-use std::collections::HashSet;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use crate::domain::data_stores::{BannedTokenStore, BannedTokenStoreError};
+use color_eyre::eyre::Result;
+
+use crate::{
+    domain::{
+        data_stores::{BannedTokenStore, BannedTokenStoreError, ExpiringStore, TokenEpoch},
+        Email,
+    },
+    utils::clock::{ClockType, SystemClock},
+};
 
-#[derive(Default)]
 pub struct HashsetBannedTokenStore {
-    tokens: HashSet<String>,
+    /// When each token stops needing to be remembered. Behind a lock so that
+    /// lookups can evict the expired tokens they come across.
+    tokens: Mutex<HashMap<String, Instant>>,
+    /// Each user's epoch, and when it stops mattering.
+    epochs: Mutex<HashMap<String, (TokenEpoch, Instant)>>,
+    token_ttl: Duration,
+    clock: ClockType,
+}
+
+impl HashsetBannedTokenStore {
+    pub fn new(token_ttl: Duration) -> Self {
+        Self::with_clock(token_ttl, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(token_ttl: Duration, clock: ClockType) -> Self {
+        Self {
+            tokens: Mutex::new(HashMap::new()),
+            epochs: Mutex::new(HashMap::new()),
+            token_ttl,
+            clock,
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for HashsetBannedTokenStore {
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
-        self.tokens.insert(token);
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = self.clock.now() + self.token_ttl;
+        self.tokens.lock().unwrap().insert(token, expires_at);
         Ok(())
     }
 
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
-        Ok(self.tokens.contains(token))
+        let now = self.clock.now();
+        let mut tokens = self.tokens.lock().unwrap();
+
+        match tokens.get(token) {
+            Some(expires_at) if *expires_at > now => Ok(true),
+            Some(_) => {
+                tokens.remove(token);
+                Ok(false)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError> {
+        let expires_at = self.clock.now() + self.token_ttl;
+        self.epochs
+            .lock()
+            .unwrap()
+            .insert(email.as_ref().to_owned(), (epoch, expires_at));
+        Ok(())
+    }
+
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError> {
+        let now = self.clock.now();
+        let mut epochs = self.epochs.lock().unwrap();
+
+        match epochs.get(email.as_ref()) {
+            Some((epoch, expires_at)) if *expires_at > now => Ok(Some(epoch.clone())),
+            Some(_) => {
+                epochs.remove(email.as_ref());
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for HashsetBannedTokenStore {
+    async fn delete_expired(&self) -> Result<u64> {
+        let now = self.clock.now();
+
+        let mut tokens = self.tokens.lock().unwrap();
+        let before = tokens.len();
+        tokens.retain(|_, expires_at| *expires_at > now);
+        let removed = before - tokens.len();
+
+        let mut epochs = self.epochs.lock().unwrap();
+        let before = epochs.len();
+        epochs.retain(|_, (_, expires_at)| *expires_at > now);
+
+        Ok((removed + before - epochs.len()) as u64)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::utils::clock::ManualClock;
+
     use super::*;
+
+    const TOKEN_TTL: Duration = Duration::from_secs(600);
+
+    fn token_epoch(epoch: i64) -> TokenEpoch {
+        TokenEpoch {
+            epoch,
+            exempt_jti: None,
+        }
+    }
+
     #[tokio::test]
     async fn test_add_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::new(TOKEN_TTL);
         let token = "test_token".to_owned();
 
         let result = store.add_token(token.clone()).await;
 
         assert!(result.is_ok());
-        assert!(store.tokens.contains(&token));
+        assert!(store.tokens.lock().unwrap().contains_key(&token));
     }
 
     #[tokio::test]
     async fn test_contains_token() {
-        let mut store = HashsetBannedTokenStore::default();
+        let store = HashsetBannedTokenStore::new(TOKEN_TTL);
         let token = "test_token".to_owned();
-        store.tokens.insert(token.clone());
+        store.add_token(token.clone()).await.unwrap();
 
         let result = store.contains_token(&token).await;
 
         assert!(result.unwrap());
     }
+
+    #[tokio::test]
+    async fn test_token_expires_after_ttl() {
+        let clock = ManualClock::default();
+        let store = HashsetBannedTokenStore::with_clock(TOKEN_TTL, Arc::new(clock.clone()));
+        store.add_token("test_token".to_owned()).await.unwrap();
+
+        clock.advance(TOKEN_TTL - Duration::from_secs(1));
+        assert!(store.contains_token("test_token").await.unwrap());
+
+        clock.advance(Duration::from_secs(1));
+        assert!(!store.contains_token("test_token").await.unwrap());
+        assert!(store.tokens.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_banning_again_extends_expiry() {
+        let clock = ManualClock::default();
+        let store = HashsetBannedTokenStore::with_clock(TOKEN_TTL, Arc::new(clock.clone()));
+        store.add_token("test_token".to_owned()).await.unwrap();
+
+        clock.advance(TOKEN_TTL / 2);
+        store.add_token("test_token".to_owned()).await.unwrap();
+        clock.advance(TOKEN_TTL / 2);
+
+        assert!(store.contains_token("test_token").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_epoch_expires_after_ttl() {
+        let clock = ManualClock::default();
+        let store = HashsetBannedTokenStore::with_clock(TOKEN_TTL, Arc::new(clock.clone()));
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+
+        assert_eq!(store.get_token_epoch(&email).await.unwrap(), None);
+
+        store
+            .set_token_epoch(&email, token_epoch(100))
+            .await
+            .unwrap();
+        store
+            .set_token_epoch(&email, token_epoch(200))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_token_epoch(&email).await.unwrap(),
+            Some(token_epoch(200))
+        );
+
+        clock.advance(TOKEN_TTL);
+        assert_eq!(store.get_token_epoch(&email).await.unwrap(), None);
+        assert!(store.epochs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sweep_removes_only_expired_tokens() {
+        let clock = ManualClock::default();
+        let store = HashsetBannedTokenStore::with_clock(TOKEN_TTL, Arc::new(clock.clone()));
+        store.add_token("old_token".to_owned()).await.unwrap();
+        clock.advance(TOKEN_TTL / 2);
+        store.add_token("new_token".to_owned()).await.unwrap();
+        store
+            .set_token_epoch(
+                &Email::parse("test@example.com".to_owned()).unwrap(),
+                token_epoch(0),
+            )
+            .await
+            .unwrap();
+        clock.advance(TOKEN_TTL / 2);
+
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+        assert_eq!(store.delete_expired().await.unwrap(), 0);
+        assert!(store.tokens.lock().unwrap().contains_key("new_token"));
+    }
 }
@@ -1,13 +1,41 @@
+mod hashmap_email_outbox_store;
+mod hashmap_rate_limit_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
+mod hashmap_webhook_store;
 mod hashset_banned_token_store;
+mod postgres_audit_log_store;
+mod postgres_banned_token_store;
+mod postgres_email_outbox_store;
+mod postgres_two_fa_code_store;
 mod postgres_user_store;
+mod postgres_webhook_store;
 mod redis_banned_token_store;
+mod redis_connection;
+mod redis_rate_limit_store;
 mod redis_two_fa_code_store;
+mod sqlite_banned_token_store;
+mod sqlite_two_fa_code_store;
+mod sqlite_user_store;
+mod vec_audit_log_store;
 
+pub use hashmap_email_outbox_store::*;
+pub use hashmap_rate_limit_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
+pub use hashmap_webhook_store::*;
 pub use hashset_banned_token_store::*;
+pub use postgres_audit_log_store::*;
+pub use postgres_banned_token_store::*;
+pub use postgres_email_outbox_store::*;
+pub use postgres_two_fa_code_store::*;
 pub use postgres_user_store::*;
+pub use postgres_webhook_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_connection::*;
+pub use redis_rate_limit_store::*;
 pub use redis_two_fa_code_store::*;
+pub use sqlite_banned_token_store::*;
+pub use sqlite_two_fa_code_store::*;
+pub use sqlite_user_store::*;
+pub use vec_audit_log_store::*;
@@ -0,0 +1,90 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{AuditLogStore, AuditLogStoreError},
+    AuditEvent, AuditLogQuery, Email,
+};
+
+pub struct PostgresAuditLogStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditLogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for PostgresAuditLogStore {
+    #[tracing::instrument(name = "Appending audit event to PostgreSQL", skip_all)]
+    async fn append_event(&self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (event_type, email, ip_address, user_agent, request_id, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            event.event_type.as_str(),
+            event.email.as_ref().map(|email| email.as_ref()),
+            event.ip_address.map(|ip| ip.to_string()),
+            event.user_agent,
+            event.request_id,
+            event.occurred_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query_events(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT event_type, email, ip_address, user_agent, request_id, occurred_at
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR email = $1)
+              AND ($2::TIMESTAMPTZ IS NULL OR occurred_at >= $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR occurred_at < $3)
+            ORDER BY occurred_at DESC, id DESC
+            LIMIT $4
+            "#,
+            query.email.as_ref().map(|email| email.as_ref()),
+            query.from,
+            query.to,
+            i64::from(query.limit)
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AuditEvent {
+                    event_type: row
+                        .event_type
+                        .parse()
+                        .map_err(AuditLogStoreError::UnexpectedError)?,
+                    email: row
+                        .email
+                        .map(Email::parse)
+                        .transpose()
+                        .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?,
+                    ip_address: row
+                        .ip_address
+                        .map(|ip| ip.parse::<std::net::IpAddr>())
+                        .transpose()
+                        .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?,
+                    user_agent: row.user_agent,
+                    request_id: row.request_id,
+                    occurred_at: row.occurred_at,
+                })
+            })
+            .collect()
+    }
+}
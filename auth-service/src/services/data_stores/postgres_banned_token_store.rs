@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{BannedTokenStore, BannedTokenStoreError, ExpiringStore, TokenEpoch},
+    Email,
+};
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+    token_ttl: Duration,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool, token_ttl: Duration) -> Self {
+        Self { pool, token_ttl }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Utc::now() + self.token_ttl;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            "#,
+            token,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        let is_banned = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens WHERE token = $1 AND expires_at > NOW()
+            ) AS "is_banned!"
+            "#,
+            token
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(is_banned)
+    }
+
+    #[tracing::instrument(name = "Setting token epoch in PostgreSQL", skip_all)]
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Utc::now() + self.token_ttl;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO token_epochs (email, epoch, exempt_jti, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (email) DO UPDATE
+            SET epoch = EXCLUDED.epoch,
+                exempt_jti = EXCLUDED.exempt_jti,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref(),
+            epoch.epoch,
+            epoch.exempt_jti,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving token epoch from PostgreSQL", skip_all)]
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT epoch, exempt_jti
+            FROM token_epochs
+            WHERE email = $1 AND expires_at > NOW()
+            "#,
+            email.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.map(|row| TokenEpoch {
+            epoch: row.epoch,
+            exempt_jti: row.exempt_jti,
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for PostgresBannedTokenStore {
+    #[tracing::instrument(
+        name = "Deleting expired banned tokens and token epochs from PostgreSQL",
+        skip_all
+    )]
+    async fn delete_expired(&self) -> Result<u64> {
+        let tokens = sqlx::query!("DELETE FROM banned_tokens WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+        let epochs = sqlx::query!("DELETE FROM token_epochs WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(tokens.rows_affected() + epochs.rows_affected())
+    }
+}
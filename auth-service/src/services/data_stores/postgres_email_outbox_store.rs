@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{EmailOutboxStore, EmailOutboxStoreError},
+    Email, EmailMessage, EmailQueueDepth, OutboxEmail, OutboxEmailStatus,
+};
+
+pub struct PostgresEmailOutboxStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailOutboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailOutboxStore for PostgresEmailOutboxStore {
+    #[tracing::instrument(name = "Enqueuing email in PostgreSQL outbox", skip_all)]
+    async fn enqueue_email(&self, email: OutboxEmail) -> Result<(), EmailOutboxStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_outbox (id, recipient, subject, html_body, text_body)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            email.id,
+            email.message.recipient.as_ref(),
+            email.message.subject,
+            email.message.html_body,
+            email.message.text_body
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due emails in PostgreSQL outbox", skip_all)]
+    async fn claim_due_emails(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<OutboxEmail>, EmailOutboxStoreError> {
+        // SKIP LOCKED lets several workers claim disjoint batches concurrently
+        let rows = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id
+                FROM email_outbox
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, recipient, subject, html_body, text_body, attempts
+            "#,
+            i64::from(limit),
+            lease_until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(OutboxEmail {
+                    id: row.id,
+                    message: EmailMessage {
+                        recipient: Email::parse(row.recipient)
+                            .map_err(|e| EmailOutboxStoreError::UnexpectedError(eyre!(e)))?,
+                        subject: row.subject,
+                        html_body: row.html_body,
+                        text_body: row.text_body,
+                    },
+                    attempts: row
+                        .attempts
+                        .try_into()
+                        .wrap_err("failed to cast attempts to u32")
+                        .map_err(EmailOutboxStoreError::UnexpectedError)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Clears the bodies of sent emails, as they may hold 2FA codes and reset links.
+    #[tracing::instrument(name = "Marking outbox email as sent in PostgreSQL", skip_all)]
+    async fn mark_sent(&self, id: &Uuid) -> Result<(), EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = $2,
+                attempts = attempts + 1,
+                sent_at = NOW(),
+                html_body = '',
+                text_body = ''
+            WHERE id = $1
+            "#,
+            id,
+            OutboxEmailStatus::Sent.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Marking outbox email as failed in PostgreSQL", skip_all)]
+    async fn mark_failed(
+        &self,
+        id: &Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), EmailOutboxStoreError> {
+        let status = match retry_at {
+            Some(_) => OutboxEmailStatus::Pending,
+            None => OutboxEmailStatus::DeadLettered,
+        };
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE email_outbox
+            SET status = $2,
+                attempts = attempts + 1,
+                last_error = $3,
+                next_attempt_at = COALESCE($4, next_attempt_at)
+            WHERE id = $1
+            "#,
+            id,
+            status.as_str(),
+            error,
+            retry_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailOutboxStoreError::EmailNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Measuring email outbox depth in PostgreSQL", skip_all)]
+    async fn queue_depth(&self) -> Result<EmailQueueDepth, EmailOutboxStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE status = 'pending') AS "pending!",
+                COUNT(*) FILTER (WHERE status = 'dead_lettered') AS "dead_lettered!"
+            FROM email_outbox
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(EmailQueueDepth {
+            pending: row.pending.try_into().unwrap_or_default(),
+            dead_lettered: row.dead_lettered.try_into().unwrap_or_default(),
+        })
+    }
+
+    #[tracing::instrument(name = "Deleting finished emails from PostgreSQL outbox", skip_all)]
+    async fn delete_finished(
+        &self,
+        created_before: DateTime<Utc>,
+    ) -> Result<u64, EmailOutboxStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM email_outbox
+            WHERE status IN ($1, $2) AND created_at < $3
+            "#,
+            OutboxEmailStatus::Sent.as_str(),
+            OutboxEmailStatus::DeadLettered.as_str(),
+            created_before
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailOutboxStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected())
+    }
+}
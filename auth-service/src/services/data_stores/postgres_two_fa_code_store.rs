@@ -0,0 +1,142 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{
+        ExpiringStore, LoginAttemptId, TwoFACode, TwoFACodePurpose, TwoFACodeStore,
+        TwoFACodeStoreError, TWO_FA_CODE_TTL,
+    },
+    Email,
+};
+
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &self,
+        email: Email,
+        purpose: &TwoFACodePurpose,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let expires_at = Utc::now() + TWO_FA_CODE_TTL;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO two_fa_codes (email, purpose, login_attempt_id, code, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (email, purpose) DO UPDATE
+            SET login_attempt_id = EXCLUDED.login_attempt_id,
+                code = EXCLUDED.code,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            email.as_ref(),
+            purpose.as_key(),
+            login_attempt_id.as_ref(),
+            code.as_ref(),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+    ) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query!(
+            "DELETE FROM two_fa_codes WHERE email = $1 AND purpose = $2",
+            email.as_ref(),
+            purpose.as_key()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = $1 AND purpose = $2 AND expires_at > NOW()
+            "#,
+            email.as_ref(),
+            purpose.as_key()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id = LoginAttemptId::parse(row.login_attempt_id)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(Secret::new(row.code))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+
+    #[tracing::instrument(name = "Taking 2FA code from PostgreSQL", skip_all)]
+    async fn take_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<bool, TwoFACodeStoreError> {
+        let taken = sqlx::query!(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = $1 AND purpose = $2 AND login_attempt_id = $3 AND code = $4
+                AND expires_at > NOW()
+            RETURNING email
+            "#,
+            email.as_ref(),
+            purpose.as_key(),
+            login_attempt_id.as_ref(),
+            code.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TwoFACodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(taken.is_some())
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Deleting expired 2FA codes from PostgreSQL", skip_all)]
+    async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM two_fa_codes WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
@@ -1,49 +1,154 @@
 use color_eyre::eyre::{eyre, Result};
 
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-
 use secrecy::{ExposeSecret, Secret};
 
 use sqlx::PgPool;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, PhoneNumber, TwoFAChannel, User,
+    },
+    services::argon2_password_hasher::{Argon2PasswordHasher, PasswordVerification},
 };
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    hasher: Argon2PasswordHasher,
+    /// How many recent passwords, including the current one, cannot be reused.
+    password_history_size: usize,
 }
 
 impl PostgresUserStore {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, hasher: Argon2PasswordHasher, password_history_size: usize) -> Self {
+        Self {
+            pool,
+            hasher,
+            password_history_size,
+        }
+    }
+
+    /// Whether the password matches the current one or any kept in the history.
+    #[tracing::instrument(name = "Checking password history in PostgreSQL", skip_all)]
+    async fn is_recent_password(&self, email: &Email, password: &Password) -> Result<bool> {
+        if self.password_history_size == 0 {
+            return Ok(false);
+        }
+
+        // The current hash is usually the newest history entry, but differs
+        // once it has been upgraded, or if the user predates the history
+        let password_hashes = sqlx::query_scalar!(
+            r#"
+            SELECT password_hash AS "password_hash!" FROM users WHERE email = $1
+            UNION
+            (SELECT password_hash FROM password_history WHERE email = $1 ORDER BY id DESC LIMIT $2)
+            "#,
+            email.as_ref(),
+            self.password_history_size as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for password_hash in password_hashes {
+            let verification = self
+                .hasher
+                .verify(Secret::new(password_hash), password.as_ref().to_owned())
+                .await?;
+            if verification != PasswordVerification::Mismatch {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Replaces a hash that is weaker than the current policy, unless the
+    /// password changed since it was read.
+    #[tracing::instrument(name = "Upgrading password hash in PostgreSQL", skip_all)]
+    async fn upgrade_password_hash(
+        &self,
+        email: &Email,
+        old_password_hash: &Secret<String>,
+        password: &Password,
+    ) -> Result<()> {
+        let password_hash = self.hasher.hash(password.as_ref().to_owned()).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $3
+            WHERE email = $1 AND password_hash = $2
+            "#,
+            email.as_ref(),
+            old_password_hash.expose_secret(),
+            password_hash.expose_secret()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 }
 
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password.as_ref().to_owned())
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .hasher
+            .hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
 
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users
+                (email, password_hash, requires_2fa, locale, phone_number, phone_verified,
+                 pending_phone_number, two_fa_channel)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             "#,
             user.email.as_ref(),
             password_hash.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.locale.as_str(),
+            user.phone_number.as_ref().map(AsRef::as_ref),
+            user.phone_verified,
+            user.pending_phone_number.as_ref().map(AsRef::as_ref),
+            user.two_fa_channel.as_str()
         )
-        .execute(&self.pool)
+        .execute(&mut *transaction)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if self.password_history_size > 0 {
+            sqlx::query!(
+                r#"
+                INSERT INTO password_history (email, password_hash)
+                VALUES ($1, $2)
+                "#,
+                user.email.as_ref(),
+                password_hash.expose_secret()
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
         Ok(())
     }
@@ -52,7 +157,8 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
         sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, locale, phone_number, phone_verified,
+                pending_phone_number, two_fa_channel
             FROM users
             WHERE email = $1
             "#,
@@ -68,6 +174,23 @@ impl UserStore for PostgresUserStore {
                 password: Password::parse(Secret::new(row.password_hash))
                     .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
                 requires_2fa: row.requires_2fa,
+                // Fall back to the default for locales that are no longer supported
+                locale: row.locale.parse().unwrap_or_default(),
+                phone_number: row
+                    .phone_number
+                    .map(PhoneNumber::parse)
+                    .transpose()
+                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+                phone_verified: row.phone_verified,
+                pending_phone_number: row
+                    .pending_phone_number
+                    .map(PhoneNumber::parse)
+                    .transpose()
+                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+                two_fa_channel: row
+                    .two_fa_channel
+                    .parse()
+                    .map_err(UserStoreError::UnexpectedError)?,
             })
         })
         .ok_or(UserStoreError::UserNotFound)?
@@ -80,56 +203,189 @@ impl UserStore for PostgresUserStore {
         password: &Password,
     ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
+        let password_hash = user.password.as_ref().to_owned();
+
+        let verification = self
+            .hasher
+            .verify(password_hash.clone(), password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        if verification == PasswordVerification::Mismatch {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        if verification == PasswordVerification::NeedsRehash {
+            // The credentials are valid either way, so only log a failed upgrade
+            // and try again on the next login
+            if let Err(e) = self
+                .upgrade_password_hash(email, &password_hash, password)
+                .await
+            {
+                tracing::warn!(error = ?e, "failed to upgrade password hash");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        if self
+            .is_recent_password(email, &password)
+            .await
+            .map_err(UserStoreError::UnexpectedError)?
+        {
+            return Err(UserStoreError::PasswordReused);
+        }
+
+        let password_hash = self
+            .hasher
+            .hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-        verify_password_hash(
-            user.password.as_ref().to_owned(),
-            password.as_ref().to_owned(),
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            password_hash.expose_secret()
         )
+        .execute(&mut *transaction)
         .await
-        .map_err(|_| UserStoreError::InvalidCredentials)
-    }
-}
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(password_candidate.expose_secret().as_bytes(), &expected_password_hash)
-                .map_err(|e| e.into())
-        })
-    })
-    .await;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
 
-    result?
-}
+        sqlx::query!(
+            r#"
+            INSERT INTO password_history (email, password_hash)
+            VALUES ($1, $2)
+            "#,
+            email.as_ref(),
+            password_hash.expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
+        // Prune entries that have fallen out of the history
+        sqlx::query!(
+            r#"
+            DELETE FROM password_history
+            WHERE email = $1 AND id NOT IN (
+                SELECT id FROM password_history WHERE email = $1 ORDER BY id DESC LIMIT $2
             )
-            .hash_password(password.expose_secret().as_bytes(), &salt)?
-            .to_string();
+            "#,
+            email.as_ref(),
+            self.password_history_size as i64
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-            Ok(Secret::new(password_hash))
-        })
-    })
-    .await;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting pending phone number in PostgreSQL", skip_all)]
+    async fn set_pending_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET pending_phone_number = $2
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            phone_number.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Verifying phone number in PostgreSQL", skip_all)]
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET phone_number = pending_phone_number,
+                phone_verified = TRUE,
+                pending_phone_number = NULL
+            WHERE email = $1 AND pending_phone_number = $2
+            "#,
+            email.as_ref(),
+            phone_number.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            // Tell a missing user apart from one whose pending number changed since
+            self.get_user(email).await?;
+            return Err(UserStoreError::PhoneNumberMismatch);
+        }
+
+        Ok(())
+    }
 
-    result?
+    #[tracing::instrument(name = "Setting 2FA channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_channel = $2
+            WHERE email = $1
+            "#,
+            email.as_ref(),
+            channel.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
 }
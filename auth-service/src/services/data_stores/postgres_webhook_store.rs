@@ -0,0 +1,286 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{WebhookStore, WebhookStoreError},
+    WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryLogEntry, WebhookDeliveryStatus,
+    WebhookEvent, WebhookSubscription,
+};
+
+pub struct PostgresWebhookStore {
+    pool: PgPool,
+}
+
+impl PostgresWebhookStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for PostgresWebhookStore {
+    #[tracing::instrument(name = "Adding webhook subscription to PostgreSQL", skip_all)]
+    async fn add_subscription(
+        &self,
+        subscription: WebhookSubscription,
+    ) -> Result<(), WebhookStoreError> {
+        let event_types: Vec<String> = subscription
+            .event_types
+            .iter()
+            .map(|event_type| event_type.as_str().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_subscriptions (id, url, secret, event_types, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            subscription.id,
+            subscription.url,
+            subscription.secret.expose_secret(),
+            &event_types,
+            subscription.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook subscriptions from PostgreSQL", skip_all)]
+    async fn get_subscriptions(&self) -> Result<Vec<WebhookSubscription>, WebhookStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, url, secret, event_types, created_at
+            FROM webhook_subscriptions
+            ORDER BY created_at
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookSubscription {
+                    id: row.id,
+                    url: row.url,
+                    secret: Secret::new(row.secret),
+                    event_types: row
+                        .event_types
+                        .iter()
+                        .map(|event_type| event_type.parse())
+                        .collect::<Result<_, _>>()
+                        .map_err(WebhookStoreError::UnexpectedError)?,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Removing webhook subscription from PostgreSQL", skip_all)]
+    async fn remove_subscription(&self, id: &Uuid) -> Result<(), WebhookStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM webhook_subscriptions
+            WHERE id = $1
+            "#,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Enqueuing webhook event in PostgreSQL", skip_all)]
+    async fn enqueue_event(&self, event: &WebhookEvent) -> Result<(), WebhookStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (id, subscription_id, event_id, event_type, payload, next_attempt_at)
+            SELECT gen_random_uuid(), id, $1, $2, $3, $4
+            FROM webhook_subscriptions
+            WHERE $2 = ANY(event_types)
+            "#,
+            event.id,
+            event.event_type.as_str(),
+            event.payload(),
+            event.occurred_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Claiming due webhook deliveries in PostgreSQL", skip_all)]
+    async fn claim_due_deliveries(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        // SKIP LOCKED lets several dispatchers claim disjoint batches concurrently
+        let rows = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries AS d
+            SET next_attempt_at = $2
+            FROM webhook_subscriptions AS s
+            WHERE d.subscription_id = s.id
+              AND d.id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+              )
+            RETURNING d.id, d.subscription_id, s.url, s.secret, d.event_id, d.event_type, d.payload, d.attempts
+            "#,
+            i64::from(limit),
+            lease_until
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookDelivery {
+                    id: row.id,
+                    subscription_id: row.subscription_id,
+                    url: row.url,
+                    secret: Secret::new(row.secret),
+                    event_id: row.event_id,
+                    event_type: row
+                        .event_type
+                        .parse()
+                        .map_err(WebhookStoreError::UnexpectedError)?,
+                    payload: row.payload,
+                    attempts: row
+                        .attempts
+                        .try_into()
+                        .wrap_err("failed to cast attempts to u32")
+                        .map_err(WebhookStoreError::UnexpectedError)?,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Recording webhook delivery attempt in PostgreSQL", skip_all)]
+    async fn record_attempt(
+        &self,
+        attempt: WebhookDeliveryAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookStoreError> {
+        let status = match (attempt.succeeded(), retry_at) {
+            (true, _) => WebhookDeliveryStatus::Delivered,
+            (false, Some(_)) => WebhookDeliveryStatus::Pending,
+            (false, None) => WebhookDeliveryStatus::Failed,
+        };
+
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET attempts = attempts + 1,
+                status = $2,
+                next_attempt_at = COALESCE($3, next_attempt_at)
+            WHERE id = $1
+            "#,
+            attempt.delivery_id,
+            status.as_str(),
+            retry_at
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookStoreError::DeliveryNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_delivery_attempts (delivery_id, attempted_at, response_status, error)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            attempt.delivery_id,
+            attempt.attempted_at,
+            attempt.response_status.map(i32::from),
+            attempt.error
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook delivery log from PostgreSQL", skip_all)]
+    async fn get_delivery_log(
+        &self,
+        subscription_id: &Uuid,
+    ) -> Result<Vec<WebhookDeliveryLogEntry>, WebhookStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT d.id, d.event_id, d.event_type, d.status, a.attempted_at, a.response_status, a.error
+            FROM webhook_delivery_attempts AS a
+            JOIN webhook_deliveries AS d ON d.id = a.delivery_id
+            WHERE d.subscription_id = $1
+            ORDER BY a.attempted_at DESC, a.id DESC
+            LIMIT $2
+            "#,
+            subscription_id,
+            DELIVERY_LOG_LIMIT
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(WebhookDeliveryLogEntry {
+                    delivery_id: row.id,
+                    event_id: row.event_id,
+                    event_type: row
+                        .event_type
+                        .parse()
+                        .map_err(WebhookStoreError::UnexpectedError)?,
+                    status: row
+                        .status
+                        .parse()
+                        .map_err(WebhookStoreError::UnexpectedError)?,
+                    attempt: WebhookDeliveryAttempt {
+                        delivery_id: row.id,
+                        attempted_at: row.attempted_at,
+                        response_status: row
+                            .response_status
+                            .map(u16::try_from)
+                            .transpose()
+                            .wrap_err("failed to cast response status to u16")
+                            .map_err(WebhookStoreError::UnexpectedError)?,
+                        error: row.error,
+                    },
+                })
+            })
+            .collect()
+    }
+}
+
+const DELIVERY_LOG_LIMIT: i64 = 500;
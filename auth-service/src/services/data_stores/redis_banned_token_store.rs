@@ -1,42 +1,41 @@
 use color_eyre::eyre::Context;
-use std::sync::Arc;
+use std::time::Duration;
 
-use redis::{Commands, Connection};
-use tokio::sync::RwLock;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 
-use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
-    utils::auth::TOKEN_TTL_SECONDS,
+use super::RedisConnection;
+use crate::domain::{
+    data_stores::{BannedTokenStore, BannedTokenStoreError, TokenEpoch},
+    Email,
 };
 
 pub struct RedisBannedTokenStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    token_ttl: Duration,
 }
 
 impl RedisBannedTokenStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection, token_ttl: Duration) -> Self {
+        Self { conn, token_ttl }
     }
 }
 
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(skip_all)]
-    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
-        let token_key = get_key(token.as_str());
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        let token_key = self.conn.key(BANNED_TOKEN_KEY_PREFIX, &token);
 
         let value = true;
 
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        let ttl = self.token_ttl.as_secs();
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .set_ex(&token_key, value, ttl)
+            .await
             .wrap_err("failed to set banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
@@ -45,22 +44,71 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
     #[tracing::instrument(skip_all)]
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
-        let token_key = get_key(token);
+        let token_key = self.conn.key(BANNED_TOKEN_KEY_PREFIX, token);
 
         let is_banned: bool = self
             .conn
-            .write()
-            .await
+            .clone()
             .exists(&token_key)
+            .await
             .wrap_err("failed to check if token exists in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)?;
 
         Ok(is_banned)
     }
-}
 
-const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+    #[tracing::instrument(skip_all)]
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError> {
+        let key = self.conn.key(TOKEN_EPOCH_KEY_PREFIX, email.as_ref());
 
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
+        let serialized_epoch =
+            serde_json::to_string(&TokenEpochTuple(epoch.epoch, epoch.exempt_jti))
+                .wrap_err("failed to serialize token epoch")
+                .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .clone()
+            .set_ex(&key, serialized_epoch, self.token_ttl.as_secs())
+            .await
+            .wrap_err("failed to set token epoch in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError> {
+        let key = self.conn.key(TOKEN_EPOCH_KEY_PREFIX, email.as_ref());
+
+        let serialized_epoch: Option<String> = self
+            .conn
+            .clone()
+            .get(&key)
+            .await
+            .wrap_err("failed to get token epoch from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let Some(serialized_epoch) = serialized_epoch else {
+            return Ok(None);
+        };
+        let TokenEpochTuple(epoch, exempt_jti) = serde_json::from_str(&serialized_epoch)
+            .wrap_err("failed to deserialize token epoch")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(Some(TokenEpoch { epoch, exempt_jti }))
+    }
 }
+
+#[derive(Serialize, Deserialize)]
+struct TokenEpochTuple(pub i64, pub Option<String>);
+
+const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const TOKEN_EPOCH_KEY_PREFIX: &str = "token_epoch:";
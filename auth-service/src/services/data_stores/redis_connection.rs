@@ -0,0 +1,386 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use redis::{
+    aio::{ConnectionLike, ConnectionManager, ConnectionManagerConfig, PubSub},
+    cluster::ClusterClientBuilder,
+    cluster_async::ClusterConnection,
+    sentinel::{SentinelClient, SentinelClientBuilder, SentinelServerType},
+    Cmd, ConnectionAddr, ConnectionInfo, ErrorKind, Pipeline, RedisConnectionInfo, RedisError,
+    RedisFuture, RedisResult, TlsMode, Value,
+};
+use secrecy::ExposeSecret;
+use tokio::sync::Mutex;
+
+use crate::settings::RedisSettings;
+
+const DEFAULT_PORT: u16 = 6379;
+const DEFAULT_SENTINEL_PORT: u16 = 26379;
+
+/// A connection to a standalone server, a Sentinel-managed primary or a
+/// cluster, shared by the Redis stores. Cheap to clone.
+#[derive(Clone)]
+pub struct RedisConnection {
+    inner: Inner,
+    namespace: Arc<str>,
+}
+
+#[derive(Clone)]
+enum Inner {
+    Standalone(ConnectionManager),
+    Sentinel(Arc<SentinelConnection>),
+    Cluster(ClusterConnection),
+}
+
+impl RedisConnection {
+    pub async fn connect(settings: &RedisSettings) -> RedisResult<Self> {
+        let inner = match settings.mode.as_str() {
+            "standalone" => {
+                let address = parse_address(&settings.host_name, DEFAULT_PORT)?;
+                let client = redis::Client::open(connection_info(settings, address))?;
+                Inner::Standalone(
+                    ConnectionManager::new_with_config(client, manager_config(settings)).await?,
+                )
+            }
+            "sentinel" => Inner::Sentinel(Arc::new(SentinelConnection::connect(settings).await?)),
+            "cluster" => Inner::Cluster(connect_cluster(settings).await?),
+            mode => {
+                return Err(RedisError::from((
+                    ErrorKind::InvalidClientConfig,
+                    "Unsupported Redis mode",
+                    mode.to_owned(),
+                )))
+            }
+        };
+
+        Ok(Self {
+            inner,
+            namespace: settings.key_namespace.as_str().into(),
+        })
+    }
+
+    /// Builds the key for `id` under `prefix`. The id is wrapped in a hash tag,
+    /// so a cluster keeps every key about the same token or user on one node
+    /// while still spreading different users across the cluster.
+    pub fn key(&self, prefix: &str, id: &str) -> String {
+        build_key(&self.namespace, prefix, id)
+    }
+
+    /// Names a pub/sub channel under the key namespace.
+    pub fn channel(&self, name: &str) -> String {
+        format!("{}{}", self.namespace, name)
+    }
+
+    /// Opens a dedicated pub/sub connection, which does not reconnect by
+    /// itself. On a cluster any node will do, as messages reach every node.
+    pub async fn pubsub(settings: &RedisSettings) -> RedisResult<PubSub> {
+        match settings.mode.as_str() {
+            "sentinel" => {
+                sentinel_client(settings)?
+                    .async_get_client()
+                    .await?
+                    .get_async_pubsub()
+                    .await
+            }
+            "cluster" => {
+                let mut last_error = None;
+                for node in &settings.nodes {
+                    let address = parse_address(node, DEFAULT_PORT)?;
+                    match redis::Client::open(connection_info(settings, address))?
+                        .get_async_pubsub()
+                        .await
+                    {
+                        Ok(pubsub) => return Ok(pubsub),
+                        Err(e) => last_error = Some(e),
+                    }
+                }
+                Err(last_error.unwrap_or_else(|| {
+                    RedisError::from((ErrorKind::InvalidClientConfig, "No Redis cluster nodes"))
+                }))
+            }
+            _ => {
+                let address = parse_address(&settings.host_name, DEFAULT_PORT)?;
+                redis::Client::open(connection_info(settings, address))?
+                    .get_async_pubsub()
+                    .await
+            }
+        }
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        match &mut self.inner {
+            Inner::Standalone(conn) => conn.req_packed_command(cmd),
+            Inner::Sentinel(conn) => Box::pin(conn.req_packed_command(cmd)),
+            Inner::Cluster(conn) => conn.req_packed_command(cmd),
+        }
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        match &mut self.inner {
+            Inner::Standalone(conn) => conn.req_packed_commands(cmd, offset, count),
+            Inner::Sentinel(conn) => Box::pin(conn.req_packed_commands(cmd, offset, count)),
+            Inner::Cluster(conn) => conn.req_packed_commands(cmd, offset, count),
+        }
+    }
+
+    fn get_db(&self) -> i64 {
+        0
+    }
+}
+
+fn build_key(namespace: &str, prefix: &str, id: &str) -> String {
+    format!("{}{}{{{}}}", namespace, prefix, id)
+}
+
+/// Follows the primary the sentinels report. After a failover the old primary
+/// refuses writes or disappears, so the sentinels are asked again and the
+/// command is retried once on the new primary.
+struct SentinelConnection {
+    sentinel: Mutex<SentinelClient>,
+    config: ConnectionManagerConfig,
+    /// The current primary, with a generation so that concurrent failures only
+    /// trigger one lookup.
+    primary: RwLock<(u64, ConnectionManager)>,
+}
+
+impl SentinelConnection {
+    async fn connect(settings: &RedisSettings) -> RedisResult<Self> {
+        let mut sentinel = sentinel_client(settings)?;
+        let config = manager_config(settings);
+        let primary = Self::connect_to_primary(&mut sentinel, &config).await?;
+
+        Ok(Self {
+            sentinel: Mutex::new(sentinel),
+            config,
+            primary: RwLock::new((0, primary)),
+        })
+    }
+
+    async fn connect_to_primary(
+        sentinel: &mut SentinelClient,
+        config: &ConnectionManagerConfig,
+    ) -> RedisResult<ConnectionManager> {
+        let client = sentinel.async_get_client().await?;
+        ConnectionManager::new_with_config(client, config.clone()).await
+    }
+
+    fn primary(&self) -> (u64, ConnectionManager) {
+        self.primary.read().unwrap().clone()
+    }
+
+    async fn failover(&self, failed_generation: u64) -> RedisResult<ConnectionManager> {
+        let mut sentinel = self.sentinel.lock().await;
+
+        let (generation, primary) = self.primary();
+        if generation != failed_generation {
+            return Ok(primary);
+        }
+
+        tracing::warn!("Redis primary unavailable, asking the sentinels for a new one");
+        let primary = Self::connect_to_primary(&mut sentinel, &self.config).await?;
+        *self.primary.write().unwrap() = (generation + 1, primary.clone());
+
+        Ok(primary)
+    }
+
+    async fn req_packed_command(&self, cmd: &Cmd) -> RedisResult<Value> {
+        let (generation, mut primary) = self.primary();
+        match primary.req_packed_command(cmd).await {
+            Err(e) if is_failover(&e) => {
+                self.failover(generation)
+                    .await?
+                    .req_packed_command(cmd)
+                    .await
+            }
+            result => result,
+        }
+    }
+
+    async fn req_packed_commands(
+        &self,
+        cmd: &Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisResult<Vec<Value>> {
+        let (generation, mut primary) = self.primary();
+        match primary.req_packed_commands(cmd, offset, count).await {
+            Err(e) if is_failover(&e) => {
+                self.failover(generation)
+                    .await?
+                    .req_packed_commands(cmd, offset, count)
+                    .await
+            }
+            result => result,
+        }
+    }
+}
+
+/// Asks the sentinels where the current primary is.
+fn sentinel_client(settings: &RedisSettings) -> RedisResult<SentinelClient> {
+    let sentinels = settings
+        .nodes
+        .iter()
+        .map(|node| {
+            parse_address(node, DEFAULT_SENTINEL_PORT).map(|address| tls_address(settings, address))
+        })
+        .collect::<RedisResult<Vec<_>>>()?;
+
+    let mut builder = SentinelClientBuilder::new(
+        sentinels,
+        settings.sentinel_service_name.clone(),
+        SentinelServerType::Master,
+    )?;
+    if settings.tls {
+        builder = builder.set_client_to_redis_tls_mode(TlsMode::Secure);
+    }
+    if let Some(username) = &settings.username {
+        builder = builder.set_client_to_redis_username(username.clone());
+    }
+    if let Some(password) = &settings.password {
+        builder = builder.set_client_to_redis_password(password.expose_secret().clone());
+    }
+
+    builder.build()
+}
+
+fn is_failover(e: &RedisError) -> bool {
+    e.kind() == ErrorKind::ReadOnly
+        || e.is_io_error()
+        || e.is_connection_dropped()
+        || e.is_connection_refusal()
+}
+
+async fn connect_cluster(settings: &RedisSettings) -> RedisResult<ClusterConnection> {
+    let nodes = settings
+        .nodes
+        .iter()
+        .map(|node| {
+            parse_address(node, DEFAULT_PORT).map(|address| connection_info(settings, address))
+        })
+        .collect::<RedisResult<Vec<_>>>()?;
+
+    let mut builder = ClusterClientBuilder::new(nodes)
+        .retries(settings.connection_retries as u32)
+        .max_retry_wait(settings.max_retry_delay_secs * 1000)
+        .connection_timeout(Duration::from_secs(settings.connection_timeout_secs))
+        .response_timeout(Duration::from_secs(settings.response_timeout_secs));
+    if settings.tls {
+        builder = builder.tls(TlsMode::Secure);
+    }
+
+    builder.build()?.get_async_connection().await
+}
+
+fn manager_config(settings: &RedisSettings) -> ConnectionManagerConfig {
+    ConnectionManagerConfig::new()
+        .set_number_of_retries(settings.connection_retries)
+        .set_max_delay(settings.max_retry_delay_secs * 1000)
+        .set_connection_timeout(Duration::from_secs(settings.connection_timeout_secs))
+        .set_response_timeout(Duration::from_secs(settings.response_timeout_secs))
+}
+
+fn connection_info(settings: &RedisSettings, address: (String, u16)) -> ConnectionInfo {
+    ConnectionInfo {
+        addr: tls_address(settings, address),
+        redis: RedisConnectionInfo {
+            username: settings.username.clone(),
+            password: settings
+                .password
+                .as_ref()
+                .map(|password| password.expose_secret().clone()),
+            ..Default::default()
+        },
+    }
+}
+
+/// The same as a `rediss://` URL when TLS is enabled.
+fn tls_address(settings: &RedisSettings, (host, port): (String, u16)) -> ConnectionAddr {
+    if settings.tls {
+        ConnectionAddr::TcpTls {
+            host,
+            port,
+            insecure: false,
+            tls_params: None,
+        }
+    } else {
+        ConnectionAddr::Tcp(host, port)
+    }
+}
+
+/// Splits `host[:port]`, also accepting bracketed IPv6 hosts.
+pub fn parse_address(address: &str, default_port: u16) -> RedisResult<(String, u16)> {
+    let invalid = || {
+        RedisError::from((
+            ErrorKind::InvalidClientConfig,
+            "Invalid Redis address",
+            address.to_owned(),
+        ))
+    };
+
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+            (host, port.parse().map_err(|_| invalid())?)
+        }
+        _ => (address, default_port),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok((host.to_owned(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_namespaced_and_hash_tagged() {
+        assert_eq!(
+            build_key("staging:", "banned_token:", "abc"),
+            "staging:banned_token:{abc}"
+        );
+        assert_eq!(
+            build_key("", "two_fa_code:", "a@example.com"),
+            "two_fa_code:{a@example.com}"
+        );
+    }
+
+    #[test]
+    fn addresses_default_the_port() {
+        assert_eq!(
+            parse_address("redis", DEFAULT_PORT).unwrap(),
+            ("redis".to_owned(), 6379)
+        );
+        assert_eq!(
+            parse_address("10.0.0.1:26380", DEFAULT_SENTINEL_PORT).unwrap(),
+            ("10.0.0.1".to_owned(), 26380)
+        );
+        assert_eq!(
+            parse_address("[::1]:7000", DEFAULT_PORT).unwrap(),
+            ("::1".to_owned(), 7000)
+        );
+        assert_eq!(
+            parse_address("::1", DEFAULT_PORT).unwrap(),
+            ("::1".to_owned(), 6379)
+        );
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected() {
+        assert!(parse_address("", DEFAULT_PORT).is_err());
+        assert!(parse_address("redis:port", DEFAULT_PORT).is_err());
+        assert!(parse_address(":6379", DEFAULT_PORT).is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use color_eyre::eyre::Context;
+use std::time::Duration;
+
+use redis::Script;
+
+use super::RedisConnection;
+use crate::domain::data_stores::{
+    RateLimitDecision, RateLimitPolicy, RateLimitStore, RateLimitStoreError,
+};
+
+pub struct RedisRateLimitStore {
+    conn: RedisConnection,
+    script: Script,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(conn: RedisConnection) -> Self {
+        Self {
+            conn,
+            script: Script::new(TOKEN_BUCKET_SCRIPT),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(skip_all)]
+    async fn consume_token(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+    ) -> Result<RateLimitDecision, RateLimitStoreError> {
+        let key = self.conn.key(RATE_LIMIT_KEY_PREFIX, key);
+
+        let (allowed, remaining, retry_after_ms): (u8, u32, u64) = self
+            .script
+            .key(&key)
+            .arg(policy.capacity)
+            .arg(policy.refill_per_second)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .wrap_err("failed to consume rate limit token in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        match allowed {
+            1 => Ok(RateLimitDecision::Allowed { remaining }),
+            _ => Ok(RateLimitDecision::Limited {
+                retry_after: Duration::from_millis(retry_after_ms),
+            }),
+        }
+    }
+}
+
+const RATE_LIMIT_KEY_PREFIX: &str = "rate_limit:";
+
+// Refill and consume in a single round trip so that concurrent requests hitting
+// different instances cannot both take the last token. Redis' own clock is used
+// so that instances with skewed clocks agree on the bucket state.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_second = tonumber(ARGV[2])
+
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+
+local elapsed = math.max(0, now - updated_at) / 1000
+tokens = math.min(capacity, tokens + elapsed * refill_per_second)
+
+local allowed = 0
+local retry_after = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+else
+    retry_after = math.ceil((1 - tokens) / refill_per_second * 1000)
+end
+
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', tostring(now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_second * 1000))
+
+return { allowed, math.floor(tokens), retry_after }
+"#;
@@ -1,22 +1,40 @@
 use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use redis::{AsyncCommands, Script};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
 
+use super::RedisConnection;
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{
+        LoginAttemptId, TwoFACode, TwoFACodePurpose, TwoFACodeStore, TwoFACodeStoreError,
+        TWO_FA_CODE_TTL,
+    },
     Email,
 };
 
 pub struct RedisTwoFACodeStore {
-    conn: Arc<RwLock<Connection>>,
+    conn: RedisConnection,
+    take_script: Script,
 }
 
 impl RedisTwoFACodeStore {
-    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+    pub fn new(conn: RedisConnection) -> Self {
+        Self {
+            conn,
+            take_script: Script::new(TAKE_CODE_SCRIPT),
+        }
+    }
+
+    /// Login codes keep their original keys, so that codes issued before
+    /// purposes existed still verify.
+    fn code_key(&self, email: &Email, purpose: &TwoFACodePurpose) -> String {
+        match purpose {
+            TwoFACodePurpose::Login => self.conn.key(TWO_FA_CODE_PREFIX, email.as_ref()),
+            _ => self.conn.key(
+                &format!("{}{}:", TWO_FA_CODE_PREFIX, purpose.as_key()),
+                email.as_ref(),
+            ),
+        }
     }
 }
 
@@ -24,12 +42,13 @@ impl RedisTwoFACodeStore {
 impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(skip_all)]
     async fn add_code(
-        &mut self,
+        &self,
         email: Email,
+        purpose: &TwoFACodePurpose,
         login_attempt_id: LoginAttemptId,
         code: TwoFACode,
     ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
+        let key = self.code_key(&email, purpose);
 
         let data = TwoFATuple(
             login_attempt_id.as_ref().to_owned(),
@@ -41,9 +60,9 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
 
         let _: () = self
             .conn
-            .write()
+            .clone()
+            .set_ex(&key, serialized_data, TWO_FA_CODE_TTL.as_secs())
             .await
-            .set_ex(&key, serialized_data, TEN_MINUTES_IN_SECONDS)
             .wrap_err("failed to set 2FA code in Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -51,14 +70,18 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     }
 
     #[tracing::instrument(skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
+    async fn remove_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = self.code_key(email, purpose);
 
         let _: () = self
             .conn
-            .write()
-            .await
+            .clone()
             .del(&key)
+            .await
             .wrap_err("failed to delete 2FA code from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
@@ -69,11 +92,20 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     async fn get_code(
         &self,
         email: &Email,
+        purpose: &TwoFACodePurpose,
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        let key = get_key(email);
+        let key = self.code_key(email, purpose);
+
+        let value: Option<String> = self
+            .conn
+            .clone()
+            .get(&key)
+            .await
+            .wrap_err("failed to get 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        match self.conn.write().await.get::<_, String>(&key) {
-            Ok(value) => {
+        match value {
+            Some(value) => {
                 let data: TwoFATuple = serde_json::from_str(&value)
                     .wrap_err("failed to deserialize 2FA tuple")
                     .map_err(TwoFACodeStoreError::UnexpectedError)?;
@@ -81,22 +113,58 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
                 let login_attempt_id =
                     LoginAttemptId::parse(data.0).map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let email_code =
-                    TwoFACode::parse(Secret::new(data.1)).map_err(TwoFACodeStoreError::UnexpectedError)?;
+                let email_code = TwoFACode::parse(Secret::new(data.1))
+                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
                 Ok((login_attempt_id, email_code))
             }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    #[tracing::instrument(skip_all)]
+    async fn take_code(
+        &self,
+        email: &Email,
+        purpose: &TwoFACodePurpose,
+        login_attempt_id: &LoginAttemptId,
+        code: &TwoFACode,
+    ) -> Result<bool, TwoFACodeStoreError> {
+        let key = self.code_key(email, purpose);
+
+        let data = TwoFATuple(
+            login_attempt_id.as_ref().to_owned(),
+            code.as_ref().to_owned(),
+        );
+        let serialized_data = serde_json::to_string(&data)
+            .wrap_err("failed to serialize 2FA tuple")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let deleted: u8 = self
+            .take_script
+            .key(&key)
+            .arg(serialized_data)
+            .invoke_async(&mut self.conn.clone())
+            .await
+            .wrap_err("failed to take 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(deleted == 1)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 struct TwoFATuple(pub String, pub String);
 
-const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref())
-}
+// Compare and delete in a single round trip, so that two requests presenting
+// the same code cannot both see it before either deletes it. Codes are stored
+// as the same JSON the caller sends, so comparing the strings compares both
+// the login attempt ID and the code.
+const TAKE_CODE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    data_stores::{BannedTokenStore, BannedTokenStoreError, ExpiringStore, TokenEpoch},
+    Email,
+};
+
+pub struct SqliteBannedTokenStore {
+    pool: SqlitePool,
+    token_ttl: Duration,
+}
+
+impl SqliteBannedTokenStore {
+    pub fn new(pool: SqlitePool, token_ttl: Duration) -> Self {
+        Self { pool, token_ttl }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for SqliteBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to SQLite", skip_all)]
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Utc::now() + self.token_ttl;
+
+        sqlx::query(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES (?1, ?2)
+            ON CONFLICT (token) DO UPDATE SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(token)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in SQLite", skip_all)]
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        // Timestamps are stored as UTC RFC 3339 text, which sorts chronologically
+        let is_banned = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens WHERE token = ?1 AND expires_at > ?2
+            )
+            "#,
+        )
+        .bind(token)
+        .bind(Utc::now())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(is_banned)
+    }
+
+    #[tracing::instrument(name = "Setting token epoch in SQLite", skip_all)]
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Utc::now() + self.token_ttl;
+
+        sqlx::query(
+            r#"
+            INSERT INTO token_epochs (email, epoch, exempt_jti, expires_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (email) DO UPDATE
+            SET epoch = excluded.epoch,
+                exempt_jti = excluded.exempt_jti,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(epoch.epoch)
+        .bind(epoch.exempt_jti)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving token epoch from SQLite", skip_all)]
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError> {
+        let row: Option<(i64, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT epoch, exempt_jti
+            FROM token_epochs
+            WHERE email = ?1 AND expires_at > ?2
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BannedTokenStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.map(|(epoch, exempt_jti)| TokenEpoch { epoch, exempt_jti }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for SqliteBannedTokenStore {
+    #[tracing::instrument(
+        name = "Deleting expired banned tokens and token epochs from SQLite",
+        skip_all
+    )]
+    async fn delete_expired(&self) -> Result<u64> {
+        let now = Utc::now();
+        let tokens = sqlx::query("DELETE FROM banned_tokens WHERE expires_at <= ?1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        let epochs = sqlx::query("DELETE FROM token_epochs WHERE expires_at <= ?1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(tokens.rows_affected() + epochs.rows_affected())
+    }
+}
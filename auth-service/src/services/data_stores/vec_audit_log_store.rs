@@ -0,0 +1,97 @@
+use std::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{AuditLogStore, AuditLogStoreError},
+    AuditEvent, AuditLogQuery,
+};
+
+#[derive(Default)]
+pub struct VecAuditLogStore {
+    events: RwLock<Vec<AuditEvent>>,
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for VecAuditLogStore {
+    async fn append_event(&self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        self.events.write().unwrap().push(event);
+        Ok(())
+    }
+
+    async fn query_events(
+        &self,
+        query: &AuditLogQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let mut events: Vec<AuditEvent> = self
+            .events
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|event| query.matches(event))
+            .cloned()
+            .collect();
+
+        // Newest first, keeping insertion order for events with equal timestamps
+        events.reverse();
+        events.sort_by_key(|event| std::cmp::Reverse(event.occurred_at));
+        events.truncate(query.limit as usize);
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::domain::{AuditEventType, Email};
+
+    fn event_at(email: &Email, minutes_ago: i64) -> AuditEvent {
+        let mut event = AuditEvent::new(AuditEventType::LoginSucceeded, Some(email.clone()));
+        event.occurred_at = Utc::now() - Duration::minutes(minutes_ago);
+        event
+    }
+
+    #[tokio::test]
+    async fn test_append_event() {
+        let store = VecAuditLogStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let event = AuditEvent::new(AuditEventType::Signup, Some(email));
+
+        let result = store.append_event(event.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(*store.events.read().unwrap(), vec![event]);
+    }
+
+    #[tokio::test]
+    async fn test_query_events_filters_by_email_and_time_range() {
+        let store = VecAuditLogStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other_email = Email::parse("other@example.com".to_owned()).unwrap();
+
+        let old = event_at(&email, 60);
+        let recent = event_at(&email, 5);
+        let newest = event_at(&email, 1);
+        let other = event_at(&other_email, 5);
+        for event in [&old, &recent, &newest, &other] {
+            store.append_event(event.clone()).await.unwrap();
+        }
+
+        let query = AuditLogQuery {
+            email: Some(email),
+            from: Some(Utc::now() - Duration::minutes(30)),
+            to: None,
+            limit: 100,
+        };
+
+        let result = store.query_events(&query).await.unwrap();
+
+        assert_eq!(result, vec![newest.clone(), recent]);
+
+        let query = AuditLogQuery { limit: 1, ..query };
+        let result = store.query_events(&query).await.unwrap();
+
+        assert_eq!(result, vec![newest]);
+    }
+}
@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use futures_util::future::join_all;
+
+use crate::{
+    app_state::{EmailClientType, EmailOutboxStoreType},
+    domain::ExpiringStore,
+    utils::backoff::Backoff,
+};
+
+pub const PENDING_EMAILS_GAUGE: &str = "email_outbox_pending";
+pub const DEAD_LETTERED_EMAILS_GAUGE: &str = "email_outbox_dead_lettered";
+pub const SENT_EMAILS_COUNTER: &str = "email_outbox_sent_total";
+pub const FAILED_EMAILS_COUNTER: &str = "email_outbox_failed_attempts_total";
+
+#[derive(Debug, Clone)]
+pub struct EmailOutboxWorkerConfig {
+    /// How often the worker looks for due emails.
+    pub poll_interval: Duration,
+    /// Maximum number of emails claimed per poll.
+    pub batch_size: u32,
+    /// How long a claimed email stays hidden from other workers.
+    pub lease: Duration,
+    pub backoff: Backoff,
+}
+
+/// Sends emails queued in the outbox, retrying failures with backoff and
+/// dead-lettering those that run out of attempts.
+pub struct EmailOutboxWorker {
+    store: EmailOutboxStoreType,
+    email_client: EmailClientType,
+    config: EmailOutboxWorkerConfig,
+}
+
+impl EmailOutboxWorker {
+    pub fn new(
+        store: EmailOutboxStoreType,
+        email_client: EmailClientType,
+        config: EmailOutboxWorkerConfig,
+    ) -> Self {
+        Self {
+            store,
+            email_client,
+            config,
+        }
+    }
+
+    /// Polls for due emails until the task is dropped.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_due().await {
+                tracing::error!(error = ?e, "failed to deliver outbox emails");
+            }
+
+            if let Err(e) = self.record_queue_depth().await {
+                tracing::error!(error = ?e, "failed to measure email outbox depth");
+            }
+        }
+    }
+
+    /// Attempts every email that is currently due and returns how many were attempted.
+    #[tracing::instrument(name = "Delivering due outbox emails", skip_all)]
+    pub async fn deliver_due(&self) -> Result<usize> {
+        let lease_until = Utc::now() + self.config.lease;
+        let emails = self
+            .store
+            .claim_due_emails(self.config.batch_size, lease_until)
+            .await?;
+
+        // Sent concurrently, so that a batch takes about as long as its slowest
+        // email rather than all of them in turn, and finishes within the lease
+        let count = emails.len();
+        let results = join_all(
+            emails
+                .iter()
+                .map(|email| self.email_client.send_email(&email.message)),
+        )
+        .await;
+
+        for (email, result) in emails.into_iter().zip(results) {
+            match result {
+                Ok(()) => {
+                    metrics::counter!(SENT_EMAILS_COUNTER).increment(1);
+                    self.store.mark_sent(&email.id).await?;
+                }
+                Err(e) => {
+                    metrics::counter!(FAILED_EMAILS_COUNTER).increment(1);
+
+                    let retry_at = self
+                        .config
+                        .backoff
+                        .next_delay(email.attempts + 1)
+                        .map(|delay| Utc::now() + delay);
+
+                    tracing::warn!(
+                        email_id = %email.id,
+                        will_retry = retry_at.is_some(),
+                        "failed to send outbox email: {:?}",
+                        e
+                    );
+
+                    self.store
+                        .mark_failed(&email.id, &format!("{:#}", e), retry_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    async fn record_queue_depth(&self) -> Result<()> {
+        let depth = self.store.queue_depth().await?;
+
+        metrics::gauge!(PENDING_EMAILS_GAUGE).set(depth.pending as f64);
+        metrics::gauge!(DEAD_LETTERED_EMAILS_GAUGE).set(depth.dead_lettered as f64);
+
+        Ok(())
+    }
+}
+
+/// Deletes sent and dead-lettered emails once they are older than `retention`,
+/// so that the outbox table does not grow forever.
+pub struct FinishedEmailPruner {
+    store: EmailOutboxStoreType,
+    retention: Duration,
+}
+
+impl FinishedEmailPruner {
+    pub fn new(store: EmailOutboxStoreType, retention: Duration) -> Self {
+        Self { store, retention }
+    }
+}
+
+#[async_trait::async_trait]
+impl ExpiringStore for FinishedEmailPruner {
+    async fn delete_expired(&self) -> Result<u64> {
+        let created_before = Utc::now() - self.retention;
+        let deleted = self.store.delete_finished(created_before).await?;
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+    use crate::{
+        domain::{
+            data_stores::EmailOutboxStore, Email, EmailClient, EmailMessage, EmailQueueDepth,
+            OutboxEmail,
+        },
+        services::data_stores::HashmapEmailOutboxStore,
+    };
+
+    /// Fails the first `failures` sends, then succeeds.
+    struct FlakyEmailClient {
+        failures: usize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmailClient for FlakyEmailClient {
+        async fn send_email(&self, _: &EmailMessage) -> Result<()> {
+            match self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                true => Err(eyre!("email provider unavailable")),
+                false => Ok(()),
+            }
+        }
+    }
+
+    async fn worker(
+        failures: usize,
+        max_attempts: u32,
+    ) -> (EmailOutboxWorker, EmailOutboxStoreType) {
+        let store = HashmapEmailOutboxStore::default();
+        store
+            .enqueue_email(OutboxEmail::new(EmailMessage {
+                recipient: Email::parse("test@example.com".to_owned()).unwrap(),
+                subject: "Subject".to_owned(),
+                html_body: "<p>Content</p>".to_owned(),
+                text_body: "Content".to_owned(),
+            }))
+            .await
+            .unwrap();
+
+        let store: EmailOutboxStoreType = Arc::new(store);
+        let email_client = Arc::new(FlakyEmailClient {
+            failures,
+            calls: AtomicUsize::new(0),
+        });
+        let config = EmailOutboxWorkerConfig {
+            poll_interval: Duration::from_millis(10),
+            batch_size: 10,
+            lease: Duration::from_secs(30),
+            backoff: Backoff::new(Duration::ZERO, Duration::ZERO, max_attempts),
+        };
+
+        (
+            EmailOutboxWorker::new(store.clone(), email_client, config),
+            store,
+        )
+    }
+
+    #[tokio::test]
+    async fn deliver_due_retries_until_sent() {
+        let (worker, store) = worker(1, 3).await;
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+
+        let depth = store.queue_depth().await.unwrap();
+        assert_eq!(depth, EmailQueueDepth::default());
+    }
+
+    #[tokio::test]
+    async fn deliver_due_dead_letters_after_max_attempts() {
+        let (worker, store) = worker(usize::MAX, 2).await;
+
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert_eq!(worker.deliver_due().await.unwrap(), 1);
+        assert_eq!(worker.deliver_due().await.unwrap(), 0);
+
+        let depth = store.queue_depth().await.unwrap();
+        assert_eq!(
+            depth,
+            EmailQueueDepth {
+                pending: 0,
+                dead_lettered: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use askama::Template;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+
+use crate::domain::{Email, EmailMessage, Locale};
+
+/// The emails we send, each rendered from an HTML and a plain text template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailTemplate {
+    TwoFACode {
+        code: String,
+    },
+    PasswordReset {
+        url: String,
+    },
+    Verification {
+        url: String,
+    },
+    SecurityAlert {
+        alert: SecurityAlert,
+        occurred_at: DateTime<Utc>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityAlert {
+    NewLogin,
+    PasswordChanged,
+}
+
+impl EmailTemplate {
+    pub fn render(&self, recipient: &Email, locale: Locale) -> Result<EmailMessage> {
+        let strings = self.strings(locale);
+        let lang = locale.as_str();
+
+        let (html_body, text_body) = match self {
+            Self::TwoFACode { code } => (
+                TwoFACodeHtml {
+                    lang,
+                    strings: &strings,
+                    code,
+                }
+                .render()?,
+                TwoFACodeText {
+                    strings: &strings,
+                    code,
+                }
+                .render()?,
+            ),
+            Self::PasswordReset { url } => (
+                PasswordResetHtml {
+                    lang,
+                    strings: &strings,
+                    url,
+                }
+                .render()?,
+                PasswordResetText {
+                    strings: &strings,
+                    url,
+                }
+                .render()?,
+            ),
+            Self::Verification { url } => (
+                VerificationHtml {
+                    lang,
+                    strings: &strings,
+                    url,
+                }
+                .render()?,
+                VerificationText {
+                    strings: &strings,
+                    url,
+                }
+                .render()?,
+            ),
+            Self::SecurityAlert { occurred_at, .. } => {
+                let occurred_at = occurred_at.format("%Y-%m-%d %H:%M UTC").to_string();
+                (
+                    SecurityAlertHtml {
+                        lang,
+                        strings: &strings,
+                        occurred_at: &occurred_at,
+                    }
+                    .render()?,
+                    SecurityAlertText {
+                        strings: &strings,
+                        occurred_at: &occurred_at,
+                    }
+                    .render()?,
+                )
+            }
+        };
+
+        Ok(EmailMessage {
+            recipient: recipient.clone(),
+            subject: strings.subject.to_owned(),
+            html_body,
+            text_body,
+        })
+    }
+
+    fn strings(&self, locale: Locale) -> Strings {
+        match (self, locale) {
+            (Self::TwoFACode { .. }, Locale::En) => Strings {
+                subject: "Your sign-in code",
+                heading: "Your sign-in code",
+                body: "Enter this code to finish signing in. It expires in 10 minutes.",
+                action: "",
+                footer: "If you did not try to sign in, you can ignore this email.",
+            },
+            (Self::TwoFACode { .. }, Locale::Es) => Strings {
+                subject: "Tu código de inicio de sesión",
+                heading: "Tu código de inicio de sesión",
+                body: "Introduce este código para terminar de iniciar sesión. Caduca en 10 minutos.",
+                action: "",
+                footer: "Si no intentaste iniciar sesión, puedes ignorar este correo.",
+            },
+            (Self::PasswordReset { .. }, Locale::En) => Strings {
+                subject: "Reset your password",
+                heading: "Reset your password",
+                body: "We received a request to reset the password for your account.",
+                action: "Choose a new password",
+                footer: "If you did not ask to reset your password, you can ignore this email.",
+            },
+            (Self::PasswordReset { .. }, Locale::Es) => Strings {
+                subject: "Restablece tu contraseña",
+                heading: "Restablece tu contraseña",
+                body: "Hemos recibido una solicitud para restablecer la contraseña de tu cuenta.",
+                action: "Elige una nueva contraseña",
+                footer: "Si no pediste restablecer tu contraseña, puedes ignorar este correo.",
+            },
+            (Self::Verification { .. }, Locale::En) => Strings {
+                subject: "Verify your email address",
+                heading: "Verify your email address",
+                body: "Confirm that this is your email address to finish setting up your account.",
+                action: "Verify email address",
+                footer: "If you did not create an account, you can ignore this email.",
+            },
+            (Self::Verification { .. }, Locale::Es) => Strings {
+                subject: "Verifica tu dirección de correo",
+                heading: "Verifica tu dirección de correo",
+                body: "Confirma que esta es tu dirección de correo para terminar de configurar tu cuenta.",
+                action: "Verificar dirección de correo",
+                footer: "Si no creaste una cuenta, puedes ignorar este correo.",
+            },
+            (Self::SecurityAlert { alert, .. }, Locale::En) => Strings {
+                subject: "Security alert for your account",
+                heading: match alert {
+                    SecurityAlert::NewLogin => "New sign-in to your account",
+                    SecurityAlert::PasswordChanged => "Your password was changed",
+                },
+                body: match alert {
+                    SecurityAlert::NewLogin => "Your account was just signed in to.",
+                    SecurityAlert::PasswordChanged => "The password for your account was just changed.",
+                },
+                action: "If this was not you, reset your password right away.",
+                footer: "This is an automated security notification.",
+            },
+            (Self::SecurityAlert { alert, .. }, Locale::Es) => Strings {
+                subject: "Alerta de seguridad de tu cuenta",
+                heading: match alert {
+                    SecurityAlert::NewLogin => "Nuevo inicio de sesión en tu cuenta",
+                    SecurityAlert::PasswordChanged => "Se cambió tu contraseña",
+                },
+                body: match alert {
+                    SecurityAlert::NewLogin => "Se acaba de iniciar sesión en tu cuenta.",
+                    SecurityAlert::PasswordChanged => "Se acaba de cambiar la contraseña de tu cuenta.",
+                },
+                action: "Si no fuiste tú, restablece tu contraseña de inmediato.",
+                footer: "Esta es una notificación de seguridad automática.",
+            },
+        }
+    }
+}
+
+/// Localized copy shared by the HTML and text variants of a template.
+struct Strings {
+    subject: &'static str,
+    heading: &'static str,
+    body: &'static str,
+    action: &'static str,
+    footer: &'static str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.html")]
+struct TwoFACodeHtml<'a> {
+    lang: &'a str,
+    strings: &'a Strings,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/two_fa_code.txt")]
+struct TwoFACodeText<'a> {
+    strings: &'a Strings,
+    code: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.html")]
+struct PasswordResetHtml<'a> {
+    lang: &'a str,
+    strings: &'a Strings,
+    url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/password_reset.txt")]
+struct PasswordResetText<'a> {
+    strings: &'a Strings,
+    url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.html")]
+struct VerificationHtml<'a> {
+    lang: &'a str,
+    strings: &'a Strings,
+    url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/verification.txt")]
+struct VerificationText<'a> {
+    strings: &'a Strings,
+    url: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_alert.html")]
+struct SecurityAlertHtml<'a> {
+    lang: &'a str,
+    strings: &'a Strings,
+    occurred_at: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "emails/security_alert.txt")]
+struct SecurityAlertText<'a> {
+    strings: &'a Strings,
+    occurred_at: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    fn all_templates() -> Vec<EmailTemplate> {
+        vec![
+            EmailTemplate::TwoFACode {
+                code: "123456".to_owned(),
+            },
+            EmailTemplate::PasswordReset {
+                url: "https://example.com/reset?token=abc".to_owned(),
+            },
+            EmailTemplate::Verification {
+                url: "https://example.com/verify?token=abc".to_owned(),
+            },
+            EmailTemplate::SecurityAlert {
+                alert: SecurityAlert::NewLogin,
+                occurred_at: Utc::now(),
+            },
+        ]
+    }
+
+    #[test]
+    fn two_fa_code_email_contains_code_in_both_bodies() {
+        let template = EmailTemplate::TwoFACode {
+            code: "123456".to_owned(),
+        };
+
+        let message = template.render(&recipient(), Locale::En).unwrap();
+
+        assert_eq!(message.recipient, recipient());
+        assert_eq!(message.subject, "Your sign-in code");
+        assert!(message.html_body.contains("<html lang=\"en\">"));
+        assert!(message.html_body.contains("123456"));
+        assert!(message.text_body.contains("123456"));
+        assert!(!message.text_body.contains('<'));
+    }
+
+    #[test]
+    fn every_template_renders_in_every_locale() {
+        for template in all_templates() {
+            for locale in [Locale::En, Locale::Es] {
+                let message = template.render(&recipient(), locale).unwrap();
+
+                assert!(!message.subject.is_empty());
+                assert!(message
+                    .html_body
+                    .contains(&format!("<html lang=\"{}\">", locale.as_str())));
+                assert!(!message.text_body.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn emails_are_localized() {
+        let template = EmailTemplate::PasswordReset {
+            url: "https://example.com/reset".to_owned(),
+        };
+
+        let en = template.render(&recipient(), Locale::En).unwrap();
+        let es = template.render(&recipient(), Locale::Es).unwrap();
+
+        assert_eq!(en.subject, "Reset your password");
+        assert_eq!(es.subject, "Restablece tu contraseña");
+        assert!(es.text_body.contains("https://example.com/reset"));
+    }
+
+    #[test]
+    fn html_body_escapes_values() {
+        let template = EmailTemplate::Verification {
+            url: "https://example.com/verify?a=1&b=\"2\"".to_owned(),
+        };
+
+        let message = template.render(&recipient(), Locale::En).unwrap();
+
+        assert!(message.html_body.contains("a=1&amp;b=&quot;2&quot;"));
+        assert!(message.text_body.contains("a=1&b=\"2\""));
+    }
+}
@@ -0,0 +1,78 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::domain::ExpiringStore;
+
+pub const SWEPT_ENTRIES_COUNTER: &str = "expired_entries_swept_total";
+
+pub type ExpiringStoreType = Arc<dyn ExpiringStore + Send + Sync>;
+
+/// Periodically deletes expired entries from stores that keep them around,
+/// so that their tables do not grow forever.
+pub struct ExpiredEntrySweeper {
+    stores: Vec<ExpiringStoreType>,
+    interval: Duration,
+}
+
+impl ExpiredEntrySweeper {
+    pub fn new(stores: Vec<ExpiringStoreType>, interval: Duration) -> Self {
+        Self { stores, interval }
+    }
+
+    /// Sweeps every `interval` until the task is dropped.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.interval);
+
+        loop {
+            interval.tick().await;
+
+            self.sweep().await;
+        }
+    }
+
+    /// Deletes expired entries from every store and returns how many were deleted.
+    /// A store that fails is logged and skipped, so it cannot hold up the rest.
+    #[tracing::instrument(name = "Sweeping expired entries", skip_all)]
+    pub async fn sweep(&self) -> u64 {
+        let mut deleted = 0;
+        for store in &self.stores {
+            match store.delete_expired().await {
+                Ok(count) => deleted += count,
+                Err(e) => tracing::error!(error = ?e, "failed to delete expired entries"),
+            }
+        }
+
+        metrics::counter!(SWEPT_ENTRIES_COUNTER).increment(deleted);
+
+        deleted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::{eyre, Result};
+
+    use super::*;
+
+    struct FixedStore(Option<u64>);
+
+    #[async_trait::async_trait]
+    impl ExpiringStore for FixedStore {
+        async fn delete_expired(&self) -> Result<u64> {
+            self.0.ok_or_else(|| eyre!("store unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sweep_continues_past_failing_store() {
+        let sweeper = ExpiredEntrySweeper::new(
+            vec![
+                Arc::new(FixedStore(Some(2))),
+                Arc::new(FixedStore(None)),
+                Arc::new(FixedStore(Some(3))),
+            ],
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(sweeper.sweep().await, 5);
+    }
+}
@@ -0,0 +1,120 @@
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::EmailClientType,
+    domain::{EmailClient, EmailMessage},
+};
+
+/// Sends through the primary provider, falling back to the secondary when it fails.
+pub struct FailoverEmailClient {
+    primary: EmailClientType,
+    secondary: EmailClientType,
+}
+
+impl FailoverEmailClient {
+    pub fn new(primary: EmailClientType, secondary: EmailClientType) -> Self {
+        Self { primary, secondary }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FailoverEmailClient {
+    #[tracing::instrument(name = "Sending email with failover", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        match self.primary.send_email(message).await {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                tracing::warn!(error = ?e, "primary email provider failed, trying secondary");
+                metrics::counter!("email_provider_failovers_total").increment(1);
+
+                self.secondary
+                    .send_email(message)
+                    .await
+                    .map_err(|secondary| {
+                        secondary.wrap_err(format!("Primary provider also failed: {}", e))
+                    })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use reqwest::Client;
+    use secrecy::Secret;
+    use wiremock::matchers::any;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::{
+        domain::Email, services::postmark_email_client::PostmarkEmailClient, utils::constants::test,
+    };
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: email(),
+            subject: "Subject".to_owned(),
+            html_body: "<p>Body</p>".to_owned(),
+            text_body: "Body".to_owned(),
+        }
+    }
+
+    fn email_client(base_url: String) -> EmailClientType {
+        let http_client = Client::builder()
+            .timeout(test::email_client::TIMEOUT)
+            .build()
+            .unwrap();
+        Arc::new(PostmarkEmailClient::new(
+            base_url,
+            email(),
+            Secret::new("token".to_owned()),
+            http_client,
+        ))
+    }
+
+    async fn provider(status: u16, expected_calls: u64) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(status))
+            .expect(expected_calls)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    #[tokio::test]
+    async fn send_email_only_uses_primary_when_it_succeeds() {
+        let primary = provider(200, 1).await;
+        let secondary = provider(200, 0).await;
+        let client =
+            FailoverEmailClient::new(email_client(primary.uri()), email_client(secondary.uri()));
+
+        assert!(client.send_email(&message()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_falls_back_to_secondary_when_primary_fails() {
+        let primary = provider(500, 1).await;
+        let secondary = provider(200, 1).await;
+        let client =
+            FailoverEmailClient::new(email_client(primary.uri()), email_client(secondary.uri()));
+
+        assert!(client.send_email(&message()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_when_both_providers_fail() {
+        let primary = provider(500, 1).await;
+        let secondary = provider(503, 1).await;
+        let client =
+            FailoverEmailClient::new(email_client(primary.uri()), email_client(secondary.uri()));
+
+        assert!(client.send_email(&message()).await.is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use lettre::message::Mailbox;
+use mail_parser::MessageParser;
+use uuid::Uuid;
+
+use super::smtp_email_client::build_mime_message;
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+const EML_EXTENSION: &str = "eml";
+
+/// Writes every email as an `.eml` file instead of sending it, for local development.
+pub struct FileEmailClient {
+    directory: PathBuf,
+    sender: Mailbox,
+}
+
+impl FileEmailClient {
+    pub fn new(directory: PathBuf, sender: Email) -> Result<Self> {
+        std::fs::create_dir_all(&directory)?;
+
+        Ok(Self {
+            directory,
+            sender: sender.as_ref().parse()?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for FileEmailClient {
+    #[tracing::instrument(name = "Writing email to file", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_mime_message(&self.sender, message)?;
+
+        // Name files by time so they sort in the order they were sent
+        let id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%dT%H%M%S%6fZ"),
+            Uuid::new_v4().simple()
+        );
+        let path = self.directory.join(&id).with_extension(EML_EXTENSION);
+        let partial_path = path.with_extension("partial");
+
+        // Rename into place so the mailbox never sees a half-written file
+        tokio::fs::write(&partial_path, email.formatted()).await?;
+        tokio::fs::rename(&partial_path, &path).await?;
+
+        tracing::info!(path = %path.display(), "wrote email to file");
+
+        Ok(())
+    }
+}
+
+/// An email captured by [`FileEmailClient`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedEmail {
+    pub id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub text_body: String,
+    pub html_body: String,
+}
+
+/// Reads back the emails that a [`FileEmailClient`] wrote to a directory.
+#[derive(Debug, Clone)]
+pub struct DevMailbox {
+    directory: PathBuf,
+}
+
+impl DevMailbox {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /// Every captured email, newest first.
+    pub async fn list(&self) -> Result<Vec<CapturedEmail>> {
+        let mut ids = Vec::new();
+
+        let mut entries = match tokio::fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            // Nothing has been sent yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == EML_EXTENSION) {
+                if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    ids.push(stem.to_owned());
+                }
+            }
+        }
+
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut emails = Vec::with_capacity(ids.len());
+        for id in ids {
+            emails.push(self.read(id).await?);
+        }
+
+        Ok(emails)
+    }
+
+    /// Looks an email up by id, or returns `None` if there is no such email.
+    pub async fn get(&self, id: &str) -> Result<Option<CapturedEmail>> {
+        // Only ever read files we wrote, so the id cannot escape the directory
+        let is_valid_id =
+            !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+        if !is_valid_id {
+            return Ok(None);
+        }
+
+        match self.read(id.to_owned()).await {
+            Ok(email) => Ok(Some(email)),
+            Err(e) if is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn read(&self, id: String) -> Result<CapturedEmail> {
+        let path = self.directory.join(&id).with_extension(EML_EXTENSION);
+        let contents = tokio::fs::read(&path).await?;
+        let message = MessageParser::default()
+            .parse(&contents)
+            .ok_or_else(|| eyre!("Failed to parse {}", path.display()))?;
+
+        Ok(CapturedEmail {
+            recipient: message
+                .to()
+                .and_then(|to| to.first())
+                .and_then(|addr| addr.address())
+                .unwrap_or_default()
+                .to_owned(),
+            subject: message.subject().unwrap_or_default().to_owned(),
+            sent_at: message
+                .date()
+                .and_then(|date| DateTime::from_timestamp(date.to_timestamp(), 0)),
+            text_body: message.body_text(0).unwrap_or_default().into_owned(),
+            html_body: message.body_html(0).unwrap_or_default().into_owned(),
+            id,
+        })
+    }
+}
+
+fn is_not_found(e: &color_eyre::eyre::Report) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> PathBuf {
+        std::env::temp_dir().join(format!("auth-service-mailbox-{}", Uuid::new_v4()))
+    }
+
+    fn message(subject: &str) -> EmailMessage {
+        EmailMessage {
+            recipient: Email::parse("recipient@example.com".to_owned()).unwrap(),
+            subject: subject.to_owned(),
+            html_body: "<p>Code: <b>123456</b></p>".to_owned(),
+            text_body: "Code: 123456".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn sent_emails_can_be_read_back_newest_first() {
+        let directory = directory();
+        let client = FileEmailClient::new(
+            directory.clone(),
+            Email::parse("sender@example.com".to_owned()).unwrap(),
+        )
+        .unwrap();
+        let mailbox = DevMailbox::new(directory.clone());
+
+        client.send_email(&message("First")).await.unwrap();
+        client.send_email(&message("Second")).await.unwrap();
+
+        let emails = mailbox.list().await.unwrap();
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].subject, "Second");
+        assert_eq!(emails[1].subject, "First");
+        assert_eq!(emails[0].recipient, "recipient@example.com");
+        assert_eq!(emails[0].text_body.trim(), "Code: 123456");
+        assert!(emails[0].html_body.contains("<b>123456</b>"));
+        assert!(emails[0].sent_at.is_some());
+
+        let email = mailbox.get(&emails[1].id).await.unwrap();
+        assert_eq!(email, Some(emails[1].clone()));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn missing_directory_is_an_empty_mailbox() {
+        let mailbox = DevMailbox::new(directory());
+
+        assert!(mailbox.list().await.unwrap().is_empty());
+        assert_eq!(mailbox.get("unknown").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn get_rejects_ids_outside_the_mailbox() {
+        let mailbox = DevMailbox::new(directory());
+
+        assert_eq!(mailbox.get("..").await.unwrap(), None);
+        assert_eq!(mailbox.get("../secret").await.unwrap(), None);
+        assert_eq!(mailbox.get("/etc/passwd").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    cmp::Ordering,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::{eyre, Context, Result};
+use secrecy::Secret;
+
+use crate::domain::{password_sha1_hex, BreachedPasswordChecker};
+
+const MAGIC: &[u8; 8] = b"HIBPIDX1";
+/// Hashes are grouped into buckets by their first two bytes.
+const BUCKETS: usize = 1 << 16;
+/// Bytes of each hash stored after the bucket bytes. With 80 bits in total,
+/// false positives stay negligible across the billion hashes in the corpus.
+const KEY_LEN: usize = 8;
+const HEADER_LEN: u64 = (MAGIC.len() + 8 + (BUCKETS + 1) * 8) as u64;
+const PREFIX_LEN: usize = 5;
+
+type Sha1Hash = [u8; 20];
+
+/// A compact on-disk index of breached password hashes. The header holds the
+/// record count and where each bucket starts, followed by the truncated hashes
+/// in ascending order, so a lookup is a binary search within one bucket.
+#[derive(Clone)]
+pub struct HibpIndex {
+    file: Arc<Mutex<File>>,
+    buckets: Arc<Vec<u64>>,
+}
+
+impl HibpIndex {
+    /// Builds an index from a download in the range API layout: either a
+    /// directory of files named by 5 hex digit prefix (optionally with `.txt`),
+    /// each listing `SUFFIX:COUNT` lines, or one file of `HASH:COUNT` lines.
+    /// Hashes must be in ascending order, as they are published.
+    pub fn build(source: &Path, destination: &Path) -> Result<u64> {
+        let temp_path = destination.with_extension("tmp");
+        let mut writer = IndexWriter::create(&temp_path)?;
+
+        if source.is_dir() {
+            for (prefix, path) in range_files(source)? {
+                read_hashes(&path, &prefix, &mut writer)?;
+            }
+        } else {
+            read_hashes(source, "", &mut writer)?;
+        }
+
+        let count = writer.finish()?;
+        fs::rename(&temp_path, destination)
+            .wrap_err_with(|| format!("failed to move index to {}", destination.display()))?;
+
+        Ok(count)
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)
+            .wrap_err_with(|| format!("failed to open HIBP index {}", path.display()))?;
+
+        let mut header = vec![0; HEADER_LEN as usize];
+        file.read_exact(&mut header)
+            .wrap_err_with(|| format!("{} is not a HIBP index", path.display()))?;
+        if &header[..MAGIC.len()] != MAGIC {
+            return Err(eyre!("{} is not a HIBP index", path.display()));
+        }
+
+        let mut words = header[MAGIC.len()..]
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().expect("chunks are 8 bytes")));
+        let count = words.next().unwrap_or_default();
+        let buckets: Vec<u64> = words.collect();
+
+        let expected_len = HEADER_LEN + count * KEY_LEN as u64;
+        if buckets.last() != Some(&count) || file.metadata()?.len() != expected_len {
+            return Err(eyre!("HIBP index {} is corrupt", path.display()));
+        }
+
+        Ok(Self {
+            file: Arc::new(Mutex::new(file)),
+            buckets: Arc::new(buckets),
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.buckets[BUCKETS]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, hash: &Sha1Hash) -> Result<bool> {
+        let bucket = u16::from_be_bytes([hash[0], hash[1]]) as usize;
+        let key = &hash[2..2 + KEY_LEN];
+        let (mut low, mut high) = (self.buckets[bucket], self.buckets[bucket + 1]);
+
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| eyre!("HIBP index lock is poisoned"))?;
+        let mut record = [0; KEY_LEN];
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            file.seek(SeekFrom::Start(HEADER_LEN + middle * KEY_LEN as u64))?;
+            file.read_exact(&mut record)?;
+
+            match record.as_slice().cmp(key) {
+                Ordering::Less => low = middle + 1,
+                Ordering::Greater => high = middle,
+                Ordering::Equal => return Ok(true),
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Checks passwords against a [`HibpIndex`] without any network access.
+pub struct HibpOfflineChecker {
+    index: HibpIndex,
+}
+
+impl HibpOfflineChecker {
+    pub fn new(index: HibpIndex) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HibpOfflineChecker {
+    #[tracing::instrument(name = "Checking HIBP index", skip_all)]
+    async fn is_breached(&self, password: &Secret<String>) -> Result<bool> {
+        let hash = parse_hash(&password_sha1_hex(password))?;
+        let index = self.index.clone();
+
+        tokio::task::spawn_blocking(move || index.contains(&hash)).await?
+    }
+}
+
+/// Writes records after a placeholder header, which is filled in once the
+/// size of every bucket is known.
+struct IndexWriter {
+    writer: BufWriter<File>,
+    bucket_sizes: Vec<u64>,
+    previous: Option<Sha1Hash>,
+}
+
+impl IndexWriter {
+    fn create(path: &Path) -> Result<Self> {
+        let file =
+            File::create(path).wrap_err_with(|| format!("failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&vec![0; HEADER_LEN as usize])?;
+
+        Ok(Self {
+            writer,
+            bucket_sizes: vec![0; BUCKETS],
+            previous: None,
+        })
+    }
+
+    fn push(&mut self, hash: Sha1Hash) -> Result<()> {
+        match self.previous.map(|previous| previous.cmp(&hash)) {
+            Some(Ordering::Greater) => return Err(eyre!("hashes are not in ascending order")),
+            Some(Ordering::Equal) => return Ok(()),
+            _ => {}
+        }
+
+        self.writer.write_all(&hash[2..2 + KEY_LEN])?;
+        self.bucket_sizes[u16::from_be_bytes([hash[0], hash[1]]) as usize] += 1;
+        self.previous = Some(hash);
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<u64> {
+        let count: u64 = self.bucket_sizes.iter().sum();
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&count.to_le_bytes());
+        let mut start: u64 = 0;
+        for size in &self.bucket_sizes {
+            header.extend_from_slice(&start.to_le_bytes());
+            start += size;
+        }
+        header.extend_from_slice(&count.to_le_bytes());
+
+        self.writer.seek(SeekFrom::Start(0))?;
+        self.writer.write_all(&header)?;
+        self.writer.flush()?;
+        self.writer.get_ref().sync_all()?;
+
+        Ok(count)
+    }
+}
+
+/// The range files in a directory, in prefix order.
+fn range_files(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let is_prefix = stem.len() == PREFIX_LEN && stem.chars().all(|c| c.is_ascii_hexdigit());
+        let is_text = path.extension().is_none_or(|extension| extension == "txt");
+
+        if is_prefix && is_text {
+            files.push((stem.to_ascii_uppercase(), path));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn read_hashes(path: &Path, prefix: &str, writer: &mut IndexWriter) -> Result<()> {
+    let file = File::open(path).wrap_err_with(|| format!("failed to open {}", path.display()))?;
+
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let location = || format!("{}:{}", path.display(), number + 1);
+        let (hash, count) = line
+            .split_once(':')
+            .ok_or_else(|| eyre!("expected HASH:COUNT"))
+            .wrap_err_with(location)?;
+
+        // Padding entries from the range API have a count of 0
+        if count.trim() == "0" {
+            continue;
+        }
+
+        let hash = parse_hash(&format!("{}{}", prefix, hash)).wrap_err_with(location)?;
+        writer.push(hash).wrap_err_with(location)?;
+    }
+
+    Ok(())
+}
+
+fn parse_hash(hex: &str) -> Result<Sha1Hash> {
+    let mut hash = [0; 20];
+    hex::decode_to_slice(hex, &mut hash).map_err(|_| eyre!("invalid SHA-1 hash: {}", hex))?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("hibp-{}", Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn secret(value: &str) -> Secret<String> {
+        Secret::new(value.to_owned())
+    }
+
+    fn sorted_hashes(passwords: &[&str]) -> Vec<String> {
+        let mut hashes: Vec<String> = passwords
+            .iter()
+            .map(|password| password_sha1_hex(&secret(password)))
+            .collect();
+        hashes.sort();
+        hashes
+    }
+
+    async fn checker(dir: &TempDir, source: &Path) -> HibpOfflineChecker {
+        let index_path = dir.0.join("hibp.idx");
+        HibpIndex::build(source, &index_path).unwrap();
+        HibpOfflineChecker::new(HibpIndex::open(&index_path).unwrap())
+    }
+
+    #[tokio::test]
+    async fn finds_hashes_from_a_single_file() {
+        let dir = TempDir::new();
+        let source = dir.0.join("pwned-passwords-sha1.txt");
+        let lines: Vec<String> = sorted_hashes(&["password", "123456", "letmein"])
+            .into_iter()
+            .map(|hash| format!("{}:42\r\n", hash))
+            .collect();
+        fs::write(&source, lines.concat()).unwrap();
+
+        let checker = checker(&dir, &source).await;
+
+        assert_eq!(checker.index.len(), 3);
+        for password in ["password", "123456", "letmein"] {
+            assert!(checker.is_breached(&secret(password)).await.unwrap());
+        }
+        assert!(!checker
+            .is_breached(&secret("correct horse battery staple"))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn finds_hashes_from_range_files() {
+        let dir = TempDir::new();
+        let source = dir.0.join("ranges");
+        fs::create_dir_all(&source).unwrap();
+
+        for hash in sorted_hashes(&["password", "qwerty"]) {
+            let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+            fs::write(
+                source.join(format!("{}.txt", prefix)),
+                format!("{}:7\n", suffix),
+            )
+            .unwrap();
+        }
+        // Padding and unrelated files are ignored
+        let padding = password_sha1_hex(&secret("letmein"));
+        let (prefix, suffix) = padding.split_at(PREFIX_LEN);
+        fs::write(source.join(prefix), format!("{}:0\n", suffix)).unwrap();
+        fs::write(source.join("README.md"), "not a range").unwrap();
+
+        let checker = checker(&dir, &source).await;
+
+        assert_eq!(checker.index.len(), 2);
+        assert!(checker.is_breached(&secret("password")).await.unwrap());
+        assert!(checker.is_breached(&secret("qwerty")).await.unwrap());
+        assert!(!checker.is_breached(&secret("letmein")).await.unwrap());
+    }
+
+    #[test]
+    fn unsorted_and_malformed_sources_are_rejected() {
+        let dir = TempDir::new();
+        let index_path = dir.0.join("hibp.idx");
+
+        let mut hashes = sorted_hashes(&["password", "123456"]);
+        hashes.reverse();
+        let unsorted = dir.0.join("unsorted.txt");
+        fs::write(&unsorted, format!("{}:1\n{}:1\n", hashes[0], hashes[1])).unwrap();
+        let error = format!(
+            "{:?}",
+            HibpIndex::build(&unsorted, &index_path).unwrap_err()
+        );
+        assert!(error.contains("not in ascending order"), "{}", error);
+        assert!(error.contains("unsorted.txt:2"), "{}", error);
+
+        let malformed = dir.0.join("malformed.txt");
+        fs::write(&malformed, "not-a-hash:1\n").unwrap();
+        assert!(HibpIndex::build(&malformed, &index_path).is_err());
+    }
+
+    #[test]
+    fn truncated_indexes_are_rejected() {
+        let dir = TempDir::new();
+        let source = dir.0.join("source.txt");
+        fs::write(&source, format!("{}:1\n", sorted_hashes(&["password"])[0])).unwrap();
+        let index_path = dir.0.join("hibp.idx");
+        HibpIndex::build(&source, &index_path).unwrap();
+
+        let bytes = fs::read(&index_path).unwrap();
+        fs::write(&index_path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(HibpIndex::open(&index_path).is_err());
+
+        fs::write(&index_path, b"something else").unwrap();
+        assert!(HibpIndex::open(&index_path).is_err());
+    }
+}
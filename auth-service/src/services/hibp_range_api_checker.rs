@@ -0,0 +1,129 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+use secrecy::Secret;
+
+use crate::domain::{password_sha1_hex, BreachedPasswordChecker};
+
+const PREFIX_LEN: usize = 5;
+
+/// Checks passwords with the Pwned Passwords range API, or a local stand-in
+/// serving the same layout. Only the first 5 hex digits of the SHA-1 hash
+/// leave the service, so the API never learns which password was checked.
+pub struct HibpRangeApiChecker {
+    http_client: Client,
+    base_url: String,
+}
+
+impl HibpRangeApiChecker {
+    pub fn new(base_url: String, http_client: Client) -> Self {
+        Self {
+            http_client,
+            base_url,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HibpRangeApiChecker {
+    #[tracing::instrument(name = "Checking HIBP range API", skip_all)]
+    async fn is_breached(&self, password: &Secret<String>) -> Result<bool> {
+        let hash = password_sha1_hex(password);
+        let (prefix, suffix) = hash.split_at(PREFIX_LEN);
+        let url = Url::parse(&self.base_url)?.join(&format!("/range/{}", prefix))?;
+
+        // See https://haveibeenpwned.com/API/v3#PwnedPasswordsPadding
+        let body = self
+            .http_client
+            .get(url)
+            .header("Add-Padding", "true")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        // Padding entries have a count of 0
+        Ok(body.lines().any(|line| {
+            line.trim()
+                .split_once(':')
+                .is_some_and(|(candidate, count)| {
+                    candidate.eq_ignore_ascii_case(suffix) && count.trim() != "0"
+                })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    // SHA-1 of "password" is 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+    const RANGE_PATH: &str = "/range/5BAA6";
+    const SUFFIX: &str = "1E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    fn checker(base_url: String) -> HibpRangeApiChecker {
+        HibpRangeApiChecker::new(base_url, Client::new())
+    }
+
+    fn password() -> Secret<String> {
+        Secret::new("password".to_owned())
+    }
+
+    #[tokio::test]
+    async fn finds_breached_password_by_prefix() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path(RANGE_PATH))
+            .and(header("Add-Padding", "true"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "003D68EB55068C33ACE09247EE4C639306B:3\r\n{}:10434004\r\n",
+                SUFFIX.to_lowercase()
+            )))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(checker(mock_server.uri())
+            .is_breached(&password())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn ignores_missing_and_padding_entries() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path(RANGE_PATH))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "003D68EB55068C33ACE09247EE4C639306B:3\r\n{}:0\r\n",
+                SUFFIX
+            )))
+            .mount(&mock_server)
+            .await;
+
+        assert!(!checker(mock_server.uri())
+            .is_breached(&password())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn fails_if_the_api_returns_an_error() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&mock_server)
+            .await;
+
+        assert!(checker(mock_server.uri())
+            .is_breached(&password())
+            .await
+            .is_err());
+    }
+}
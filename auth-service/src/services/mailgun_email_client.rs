@@ -0,0 +1,167 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+/// Mailgun takes the API key as the password for this fixed username.
+const MAILGUN_API_USER: &str = "api";
+
+/// Sends emails through the Mailgun Messages API.
+pub struct MailgunEmailClient {
+    http_client: Client,
+    base_url: String,
+    domain: String,
+    sender: Email,
+    api_key: Secret<String>,
+}
+
+impl MailgunEmailClient {
+    pub fn new(
+        base_url: String,
+        domain: String,
+        sender: Email,
+        api_key: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            domain,
+            sender,
+            api_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for MailgunEmailClient {
+    #[tracing::instrument(name = "Sending email through Mailgun", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join(&format!("/v3/{}/messages", self.domain))?;
+
+        // See https://documentation.mailgun.com/docs/mailgun/api-reference/openapi-final/tag/Messages/
+        let form = [
+            ("from", self.sender.as_ref()),
+            ("to", message.recipient.as_ref()),
+            ("subject", &message.subject),
+            ("text", &message.text_body),
+            ("html", &message.html_body),
+        ];
+
+        self.http_client
+            .post(url)
+            .basic_auth(MAILGUN_API_USER, Some(self.api_key.expose_secret()))
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::Fake;
+    use wiremock::matchers::{any, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::utils::constants::test;
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> Email {
+        Email::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: email(),
+            subject: subject(),
+            html_body: content(),
+            text_body: content(),
+        }
+    }
+
+    fn email_client(base_url: String) -> MailgunEmailClient {
+        let http_client = Client::builder()
+            .timeout(test::email_client::TIMEOUT)
+            .build()
+            .unwrap();
+        MailgunEmailClient::new(
+            base_url,
+            "mg.example.com".to_owned(),
+            email(),
+            Secret::new("key-123".to_owned()),
+            http_client,
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // base64("api:key-123")
+        Mock::given(header("Authorization", "Basic YXBpOmtleS0xMjM="))
+            .and(header("Content-Type", "application/x-www-form-urlencoded"))
+            .and(path("/v3/mg.example.com/messages"))
+            .and(method("POST"))
+            .and(body_string_contains("from="))
+            .and(body_string_contains("to="))
+            .and(body_string_contains("subject="))
+            .and(body_string_contains("text="))
+            .and(body_string_contains("html="))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -1,22 +1,17 @@
-use color_eyre::eyre::{Result};
+use crate::domain::{EmailClient, EmailMessage};
+use color_eyre::eyre::Result;
 use tracing::debug;
-use crate::domain::{Email, EmailClient};
 
 pub struct MockEmailClient;
 
 #[async_trait::async_trait]
 impl EmailClient for MockEmailClient {
-    async fn send_email(
-        &self,
-        recipient: &Email,
-        subject: &str,
-        content: &str,
-    ) -> Result<()> {
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
         debug!(
             "Sending email to {} with subject: {} and content: {}",
-            recipient.as_ref(),
-            subject,
-            content
+            message.recipient.as_ref(),
+            message.subject,
+            message.text_body
         );
 
         Ok(())
@@ -0,0 +1,15 @@
+use color_eyre::eyre::Result;
+use tracing::debug;
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+pub struct MockSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for MockSmsClient {
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        debug!("Sending SMS to {} with body: {}", recipient.as_ref(), body);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,22 @@
+pub mod argon2_password_hasher;
 pub mod data_stores;
+pub mod email_outbox_worker;
+pub mod email_templates;
+pub mod expired_entry_sweeper;
+pub mod failover_email_client;
+pub mod file_email_client;
+pub mod hibp_offline_checker;
+pub mod hibp_range_api_checker;
+pub mod mailgun_email_client;
 pub mod mock_email_client;
+pub mod mock_sms_client;
+pub mod postgres_revocation_broadcaster;
 pub mod postmark_email_client;
+pub mod redis_revocation_broadcaster;
+pub mod revocation_cache;
+pub mod sendgrid_email_client;
+pub mod ses_email_client;
+pub mod sms_templates;
+pub mod smtp_email_client;
+pub mod twilio_sms_client;
+pub mod webhook_dispatcher;
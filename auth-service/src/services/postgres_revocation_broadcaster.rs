@@ -0,0 +1,79 @@
+use std::{sync::Arc, time::Duration};
+
+use color_eyre::eyre::{Context, Result};
+use sqlx::{postgres::PgListener, PgPool};
+
+use crate::services::revocation_cache::{Revocation, RevocationBroadcaster, RevocationCache};
+
+const REVOCATIONS_CHANNEL: &str = "revocations";
+const RELISTEN_DELAY: Duration = Duration::from_secs(1);
+
+/// Broadcasts revocations with `NOTIFY` to every instance using the same database.
+pub struct PostgresRevocationBroadcaster {
+    pool: PgPool,
+}
+
+impl PostgresRevocationBroadcaster {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Applies revocations until the listener fails.
+    async fn receive(&self, cache: &RevocationCache) -> Result<()> {
+        let mut listener = PgListener::connect_with(&self.pool)
+            .await
+            .wrap_err("failed to connect to PostgreSQL")?;
+        listener
+            .listen(REVOCATIONS_CHANNEL)
+            .await
+            .wrap_err("failed to listen for revocations")?;
+        // Anything revoked before now may have been missed
+        cache.clear();
+
+        loop {
+            match listener.try_recv().await? {
+                Some(notification) => {
+                    match serde_json::from_str::<Revocation>(notification.payload()) {
+                        Ok(revocation) => cache.apply(&revocation),
+                        Err(e) => {
+                            tracing::warn!(error = ?e, "ignoring invalid revocation notification")
+                        }
+                    }
+                }
+                // The listener reconnects on the next call, but notifications
+                // sent in between are lost
+                None => cache.clear(),
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RevocationBroadcaster for PostgresRevocationBroadcaster {
+    #[tracing::instrument(name = "Publishing revocation to PostgreSQL", skip_all)]
+    async fn publish(&self, revocation: &Revocation) -> Result<()> {
+        let payload =
+            serde_json::to_string(revocation).wrap_err("failed to serialize revocation")?;
+
+        // Unchecked, as `pg_notify` returns `void`, which the query macros cannot describe
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(REVOCATIONS_CHANNEL)
+            .bind(payload)
+            .execute(&self.pool)
+            .await
+            .wrap_err("failed to publish revocation")?;
+
+        Ok(())
+    }
+
+    async fn listen(self: Arc<Self>, cache: Arc<RevocationCache>) {
+        loop {
+            if let Err(e) = self.receive(&cache).await {
+                tracing::warn!(error = ?e, "failed to receive revocations from PostgreSQL");
+            }
+
+            cache.clear();
+            tokio::time::sleep(RELISTEN_DELAY).await;
+        }
+    }
+}
@@ -2,7 +2,7 @@ use color_eyre::eyre::Result; // For improved error handling and reporting
 use reqwest::{Client, Url}; // For making HTTP requests
 use secrecy::{ExposeSecret, Secret}; // For securely handling sensitive data
 
-use crate::domain::{Email, EmailClient}; // Import domain-specific modules
+use crate::domain::{Email, EmailClient, EmailMessage}; // Import domain-specific modules
 
 // Define the PostmarkEmailClient struct
 pub struct PostmarkEmailClient {
@@ -32,7 +32,7 @@ impl PostmarkEmailClient {
 #[async_trait::async_trait]
 impl EmailClient for PostmarkEmailClient {
     #[tracing::instrument(name = "Sending email", skip_all)] // Trace this function, skipping logging its parameters
-    async fn send_email(&self, recipient: &Email, subject: &str, content: &str) -> Result<()> {
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
         // Parse the base URL and join it with the email endpoint
         let base = Url::parse(&self.base_url)?;
         let url = base.join("/email")?;
@@ -40,10 +40,10 @@ impl EmailClient for PostmarkEmailClient {
         // Create the request body for sending the email
         let request_body = SendEmailRequest {
             from: self.sender.as_ref(),
-            to: recipient.as_ref(),
-            subject,
-            html_body: content,
-            text_body: content,
+            to: message.recipient.as_ref(),
+            subject: &message.subject,
+            html_body: &message.html_body,
+            text_body: &message.text_body,
             message_stream: MESSAGE_STREAM,
         };
 
@@ -109,6 +109,16 @@ mod tests {
         Email::parse(SafeEmail().fake()).unwrap()
     }
 
+    // Helper function to generate a test message
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: email(),
+            subject: subject(),
+            html_body: content(),
+            text_body: content(),
+        }
+    }
+
     // Helper function to create a test email client
     fn email_client(base_url: String) -> PostmarkEmailClient {
         let http_client = Client::builder()
@@ -155,9 +165,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&message()).await;
 
         assert!(outcome.is_ok());
     }
@@ -176,9 +184,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&message()).await;
 
         assert!(outcome.is_err());
     }
@@ -198,9 +204,7 @@ mod tests {
             .await;
 
         // Execute the send_email function and check the outcome
-        let outcome = email_client
-            .send_email(&email(), &subject(), &content())
-            .await;
+        let outcome = email_client.send_email(&message()).await;
 
         assert!(outcome.is_err());
     }
@@ -0,0 +1,91 @@
+use std::{sync::Arc, time::Duration};
+
+use color_eyre::eyre::{Context, Result};
+use futures_util::StreamExt;
+use redis::AsyncCommands;
+
+use crate::{
+    services::{
+        data_stores::RedisConnection,
+        revocation_cache::{Revocation, RevocationBroadcaster, RevocationCache},
+    },
+    settings::RedisSettings,
+};
+
+const REVOCATIONS_CHANNEL: &str = "revocations";
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Broadcasts revocations over Redis pub/sub.
+pub struct RedisRevocationBroadcaster {
+    conn: RedisConnection,
+    /// For the separate connection subscriptions need.
+    settings: RedisSettings,
+}
+
+impl RedisRevocationBroadcaster {
+    pub fn new(conn: RedisConnection, settings: RedisSettings) -> Self {
+        Self { conn, settings }
+    }
+
+    fn channel(&self) -> String {
+        self.conn.channel(REVOCATIONS_CHANNEL)
+    }
+
+    /// Applies revocations until the subscription is lost.
+    async fn receive(&self, cache: &RevocationCache) -> Result<()> {
+        let mut pubsub = RedisConnection::pubsub(&self.settings)
+            .await
+            .wrap_err("failed to connect to Redis")?;
+        pubsub
+            .subscribe(self.channel())
+            .await
+            .wrap_err("failed to subscribe to revocations")?;
+        // Anything revoked before now may have been missed
+        cache.clear();
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(message) = messages.next().await {
+            match parse_revocation(&message) {
+                Ok(revocation) => cache.apply(&revocation),
+                Err(e) => tracing::warn!(error = ?e, "ignoring invalid revocation message"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_revocation(message: &redis::Msg) -> Result<Revocation> {
+    let payload: String = message.get_payload()?;
+    serde_json::from_str(&payload).wrap_err("failed to parse revocation")
+}
+
+#[async_trait::async_trait]
+impl RevocationBroadcaster for RedisRevocationBroadcaster {
+    #[tracing::instrument(name = "Publishing revocation to Redis", skip_all)]
+    async fn publish(&self, revocation: &Revocation) -> Result<()> {
+        let payload =
+            serde_json::to_string(revocation).wrap_err("failed to serialize revocation")?;
+
+        let _: () = self
+            .conn
+            .clone()
+            .publish(self.channel(), payload)
+            .await
+            .wrap_err("failed to publish revocation")?;
+
+        Ok(())
+    }
+
+    async fn listen(self: Arc<Self>, cache: Arc<RevocationCache>) {
+        loop {
+            match self.receive(&cache).await {
+                Ok(()) => tracing::warn!("lost the Redis revocation subscription"),
+                Err(e) => tracing::warn!(error = ?e, "failed to receive revocations from Redis"),
+            }
+
+            cache.clear();
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use hashlink::LruCache;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::BannedTokenStoreType,
+    domain::{
+        data_stores::{BannedTokenStore, BannedTokenStoreError, TokenEpoch},
+        Email,
+    },
+    utils::clock::{ClockType, SystemClock},
+};
+
+pub const REVOCATION_CACHE_HITS_COUNTER: &str = "revocation_cache_hits_total";
+pub const REVOCATION_CACHE_MISSES_COUNTER: &str = "revocation_cache_misses_total";
+
+pub type RevocationBroadcasterType = Arc<dyn RevocationBroadcaster + Send + Sync>;
+
+/// A change to the banned tokens, as broadcast to the other instances.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Revocation {
+    Token {
+        token: String,
+    },
+    TokenEpoch {
+        email: String,
+        epoch: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        exempt_jti: Option<String>,
+    },
+}
+
+/// Tells every instance about revocations, so that they can update their caches.
+#[async_trait::async_trait]
+pub trait RevocationBroadcaster {
+    async fn publish(&self, revocation: &Revocation) -> Result<()>;
+    /// Applies the revocations every instance publishes to `cache` until the
+    /// task is dropped. The cache is cleared whenever some may have been missed.
+    async fn listen(self: Arc<Self>, cache: Arc<RevocationCache>);
+}
+
+/// A bounded cache of what the banned token store said about recent tokens
+/// and users. Entries are dropped after `ttl`, which bounds how long an
+/// instance that missed a broadcast keeps accepting a revoked token.
+pub struct RevocationCache {
+    entries: Mutex<Entries>,
+    ttl: Duration,
+    clock: ClockType,
+}
+
+struct Entries {
+    /// Whether each token is banned, and when to ask the store again.
+    tokens: LruCache<String, (bool, Instant)>,
+    /// Each user's epoch, if any, and when to ask the store again.
+    epochs: LruCache<String, (Option<TokenEpoch>, Instant)>,
+    /// Bumped by every change, so that a lookup which raced with one does not
+    /// cache what it read before it.
+    generation: u64,
+}
+
+impl RevocationCache {
+    /// Keeps up to `capacity` tokens and as many users.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self::with_clock(capacity, ttl, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(capacity: usize, ttl: Duration, clock: ClockType) -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                tokens: LruCache::new(capacity),
+                epochs: LruCache::new(capacity),
+                generation: 0,
+            }),
+            ttl,
+            clock,
+        }
+    }
+
+    /// Taken before asking the store, to pass to the matching insert.
+    pub fn generation(&self) -> u64 {
+        self.entries.lock().unwrap().generation
+    }
+
+    pub fn get_token(&self, token: &str) -> Option<bool> {
+        let now = self.clock.now();
+        let hit = lookup(&mut self.entries.lock().unwrap().tokens, token, now);
+        record_lookup("token", hit.is_some());
+        hit
+    }
+
+    pub fn get_token_epoch(&self, email: &str) -> Option<Option<TokenEpoch>> {
+        let now = self.clock.now();
+        let hit = lookup(&mut self.entries.lock().unwrap().epochs, email, now);
+        record_lookup("token_epoch", hit.is_some());
+        hit
+    }
+
+    /// Caches what the store said, unless anything changed since `generation`.
+    pub fn insert_token(&self, token: String, banned: bool, generation: u64) {
+        let expires_at = self.clock.now() + self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation {
+            entries.tokens.insert(token, (banned, expires_at));
+        }
+    }
+
+    /// Caches what the store said, unless anything changed since `generation`.
+    pub fn insert_token_epoch(&self, email: String, epoch: Option<TokenEpoch>, generation: u64) {
+        let expires_at = self.clock.now() + self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation {
+            entries.epochs.insert(email, (epoch, expires_at));
+        }
+    }
+
+    pub fn apply(&self, revocation: &Revocation) {
+        let expires_at = self.clock.now() + self.ttl;
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+
+        match revocation {
+            Revocation::Token { token } => {
+                entries.tokens.insert(token.clone(), (true, expires_at));
+            }
+            Revocation::TokenEpoch {
+                email,
+                epoch,
+                exempt_jti,
+            } => {
+                // Epochs only move forward, whatever order broadcasts arrive in
+                let epoch = match entries.epochs.get(email.as_str()) {
+                    Some((Some(cached), _)) if cached.epoch > *epoch => cached.clone(),
+                    _ => TokenEpoch {
+                        epoch: *epoch,
+                        exempt_jti: exempt_jti.clone(),
+                    },
+                };
+                entries
+                    .epochs
+                    .insert(email.clone(), (Some(epoch), expires_at));
+            }
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.generation += 1;
+        entries.tokens.clear();
+        entries.epochs.clear();
+    }
+}
+
+fn lookup<V: Clone>(
+    cache: &mut LruCache<String, (V, Instant)>,
+    key: &str,
+    now: Instant,
+) -> Option<V> {
+    match cache.get(key).cloned() {
+        Some((value, expires_at)) if expires_at > now => Some(value),
+        Some(_) => {
+            cache.remove(key);
+            None
+        }
+        None => None,
+    }
+}
+
+fn record_lookup(kind: &'static str, hit: bool) {
+    let counter = if hit {
+        REVOCATION_CACHE_HITS_COUNTER
+    } else {
+        REVOCATION_CACHE_MISSES_COUNTER
+    };
+    metrics::counter!(counter, "kind" => kind).increment(1);
+}
+
+/// Answers from the cache where it can, and tells every instance about the
+/// revocations made through it.
+pub struct CachedBannedTokenStore {
+    store: BannedTokenStoreType,
+    cache: Arc<RevocationCache>,
+    /// Unset when there are no other instances to tell.
+    broadcaster: Option<RevocationBroadcasterType>,
+}
+
+impl CachedBannedTokenStore {
+    pub fn new(
+        store: BannedTokenStoreType,
+        cache: Arc<RevocationCache>,
+        broadcaster: Option<RevocationBroadcasterType>,
+    ) -> Self {
+        Self {
+            store,
+            cache,
+            broadcaster,
+        }
+    }
+
+    async fn revoke(&self, revocation: Revocation) {
+        self.cache.apply(&revocation);
+
+        if let Some(broadcaster) = &self.broadcaster {
+            // The revocation is stored already, so other instances still pick
+            // it up once their cached answers expire
+            if let Err(e) = broadcaster.publish(&revocation).await {
+                tracing::warn!(error = ?e, "failed to broadcast revocation");
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for CachedBannedTokenStore {
+    async fn add_token(&self, token: String) -> Result<(), BannedTokenStoreError> {
+        self.store.add_token(token.clone()).await?;
+        self.revoke(Revocation::Token { token }).await;
+        Ok(())
+    }
+
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        if let Some(banned) = self.cache.get_token(token) {
+            return Ok(banned);
+        }
+
+        let generation = self.cache.generation();
+        let banned = self.store.contains_token(token).await?;
+        self.cache
+            .insert_token(token.to_owned(), banned, generation);
+
+        Ok(banned)
+    }
+
+    async fn set_token_epoch(
+        &self,
+        email: &Email,
+        epoch: TokenEpoch,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.store.set_token_epoch(email, epoch.clone()).await?;
+        self.revoke(Revocation::TokenEpoch {
+            email: email.as_ref().to_owned(),
+            epoch: epoch.epoch,
+            exempt_jti: epoch.exempt_jti,
+        })
+        .await;
+        Ok(())
+    }
+
+    async fn get_token_epoch(
+        &self,
+        email: &Email,
+    ) -> Result<Option<TokenEpoch>, BannedTokenStoreError> {
+        if let Some(epoch) = self.cache.get_token_epoch(email.as_ref()) {
+            return Ok(epoch);
+        }
+
+        let generation = self.cache.generation();
+        let epoch = self.store.get_token_epoch(email).await?;
+        self.cache
+            .insert_token_epoch(email.as_ref().to_owned(), epoch.clone(), generation);
+
+        Ok(epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{services::data_stores::HashsetBannedTokenStore, utils::clock::ManualClock};
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(30);
+
+    fn token_epoch(epoch: i64) -> TokenEpoch {
+        TokenEpoch {
+            epoch,
+            exempt_jti: None,
+        }
+    }
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    fn cached_store(clock: &ManualClock) -> (CachedBannedTokenStore, BannedTokenStoreType) {
+        let store: BannedTokenStoreType =
+            Arc::new(HashsetBannedTokenStore::new(Duration::from_secs(600)));
+        let cache = Arc::new(RevocationCache::with_clock(
+            100,
+            TTL,
+            Arc::new(clock.clone()),
+        ));
+        (
+            CachedBannedTokenStore::new(store.clone(), cache, None),
+            store,
+        )
+    }
+
+    #[tokio::test]
+    async fn answers_are_cached_until_they_expire() {
+        let clock = ManualClock::default();
+        let (cached_store, store) = cached_store(&clock);
+
+        assert!(!cached_store.contains_token("token").await.unwrap());
+        assert_eq!(cached_store.get_token_epoch(&email()).await.unwrap(), None);
+
+        // Changes made elsewhere are only seen once the cached answers expire
+        store.add_token("token".to_owned()).await.unwrap();
+        store
+            .set_token_epoch(&email(), token_epoch(100))
+            .await
+            .unwrap();
+        assert!(!cached_store.contains_token("token").await.unwrap());
+        assert_eq!(cached_store.get_token_epoch(&email()).await.unwrap(), None);
+
+        clock.advance(TTL);
+        assert!(cached_store.contains_token("token").await.unwrap());
+        assert_eq!(
+            cached_store.get_token_epoch(&email()).await.unwrap(),
+            Some(token_epoch(100))
+        );
+    }
+
+    #[tokio::test]
+    async fn revocations_through_the_store_are_seen_at_once() {
+        let clock = ManualClock::default();
+        let (cached_store, store) = cached_store(&clock);
+
+        assert!(!cached_store.contains_token("token").await.unwrap());
+        assert_eq!(cached_store.get_token_epoch(&email()).await.unwrap(), None);
+
+        cached_store.add_token("token".to_owned()).await.unwrap();
+        cached_store
+            .set_token_epoch(&email(), token_epoch(100))
+            .await
+            .unwrap();
+
+        assert!(cached_store.contains_token("token").await.unwrap());
+        assert_eq!(
+            cached_store.get_token_epoch(&email()).await.unwrap(),
+            Some(token_epoch(100))
+        );
+        assert!(store.contains_token("token").await.unwrap());
+        assert_eq!(
+            store.get_token_epoch(&email()).await.unwrap(),
+            Some(token_epoch(100))
+        );
+    }
+
+    #[test]
+    fn broadcast_revocations_replace_cached_answers() {
+        let cache = RevocationCache::new(100, TTL);
+        cache.insert_token("token".to_owned(), false, cache.generation());
+        cache.insert_token_epoch(
+            "test@example.com".to_owned(),
+            Some(token_epoch(200)),
+            cache.generation(),
+        );
+
+        cache.apply(&Revocation::Token {
+            token: "token".to_owned(),
+        });
+        cache.apply(&Revocation::TokenEpoch {
+            email: "test@example.com".to_owned(),
+            epoch: 100,
+            exempt_jti: None,
+        });
+
+        assert_eq!(cache.get_token("token"), Some(true));
+        assert_eq!(
+            cache.get_token_epoch("test@example.com"),
+            Some(Some(token_epoch(200)))
+        );
+    }
+
+    #[test]
+    fn lookups_that_raced_with_a_change_are_not_cached() {
+        let cache = RevocationCache::new(100, TTL);
+
+        let generation = cache.generation();
+        cache.clear();
+        cache.insert_token("token".to_owned(), false, generation);
+
+        assert_eq!(cache.get_token("token"), None);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted() {
+        let cache = RevocationCache::new(2, TTL);
+        for token in ["a", "b"] {
+            cache.insert_token(token.to_owned(), false, cache.generation());
+        }
+        cache.get_token("a");
+        cache.insert_token("c".to_owned(), false, cache.generation());
+
+        assert_eq!(cache.get_token("a"), Some(false));
+        assert_eq!(cache.get_token("b"), None);
+        assert_eq!(cache.get_token("c"), Some(false));
+    }
+
+    #[test]
+    fn revocations_are_tagged_in_json() {
+        let revocation = Revocation::TokenEpoch {
+            email: "test@example.com".to_owned(),
+            epoch: 100,
+            exempt_jti: None,
+        };
+        let json = serde_json::to_value(&revocation).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "token_epoch",
+                "email": "test@example.com",
+                "epoch": 100
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Revocation>(json).unwrap(),
+            revocation
+        );
+    }
+}
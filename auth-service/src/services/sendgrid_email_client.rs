@@ -0,0 +1,207 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+/// Sends emails through the SendGrid v3 Mail Send API.
+pub struct SendGridEmailClient {
+    http_client: Client,
+    base_url: String,
+    sender: Email,
+    api_key: Secret<String>,
+}
+
+impl SendGridEmailClient {
+    pub fn new(
+        base_url: String,
+        sender: Email,
+        api_key: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            sender,
+            api_key,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SendGridEmailClient {
+    #[tracing::instrument(name = "Sending email through SendGrid", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/v3/mail/send")?;
+
+        // See https://www.twilio.com/docs/sendgrid/api-reference/mail-send/mail-send
+        let request_body = SendEmailRequest {
+            personalizations: [Personalization {
+                to: [Address {
+                    email: message.recipient.as_ref(),
+                }],
+            }],
+            from: Address {
+                email: self.sender.as_ref(),
+            },
+            subject: &message.subject,
+            // SendGrid requires text/plain to come before text/html
+            content: [
+                Content {
+                    content_type: "text/plain",
+                    value: &message.text_body,
+                },
+                Content {
+                    content_type: "text/html",
+                    value: &message.html_body,
+                },
+            ],
+        };
+
+        self.http_client
+            .post(url)
+            .bearer_auth(self.api_key.expose_secret())
+            .json(&request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, Debug)]
+struct SendEmailRequest<'a> {
+    personalizations: [Personalization<'a>; 1],
+    from: Address<'a>,
+    subject: &'a str,
+    content: [Content<'a>; 2],
+}
+
+#[derive(serde::Serialize, Debug)]
+struct Personalization<'a> {
+    to: [Address<'a>; 1],
+}
+
+#[derive(serde::Serialize, Debug)]
+struct Address<'a> {
+    email: &'a str,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct Content<'a> {
+    #[serde(rename = "type")]
+    content_type: &'a str,
+    value: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use wiremock::matchers::{any, header, header_regex, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use super::*;
+    use crate::utils::constants::test;
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> Email {
+        Email::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: email(),
+            subject: subject(),
+            html_body: content(),
+            text_body: content(),
+        }
+    }
+
+    fn email_client(base_url: String) -> SendGridEmailClient {
+        let http_client = Client::builder()
+            .timeout(test::email_client::TIMEOUT)
+            .build()
+            .unwrap();
+        SendGridEmailClient::new(base_url, email(), Secret::new(Faker.fake()), http_client)
+    }
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body["personalizations"][0]["to"][0]["email"].is_string()
+                    && body["from"]["email"].is_string()
+                    && body["subject"].is_string()
+                    && body["content"][0]["type"] == "text/plain"
+                    && body["content"][1]["type"] == "text/html"
+            } else {
+                false
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_regex("Authorization", "^Bearer .+$"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/v3/mail/send"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(202).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+}
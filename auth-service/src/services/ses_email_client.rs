@@ -0,0 +1,421 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Client, Url,
+};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+const SERVICE: &str = "ses";
+const SIGNING_ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const CONTENT_TYPE_JSON: &str = "application/json";
+const AMZ_DATE_HEADER: &str = "x-amz-date";
+const AMZ_SECURITY_TOKEN_HEADER: &str = "x-amz-security-token";
+
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: Secret<String>,
+    /// Only set for temporary credentials, e.g. from an assumed role.
+    pub session_token: Option<Secret<String>>,
+}
+
+/// Sends emails through the Amazon SES v2 API, signing requests with SigV4.
+pub struct SesEmailClient {
+    http_client: Client,
+    base_url: String,
+    region: String,
+    sender: Email,
+    credentials: AwsCredentials,
+}
+
+impl SesEmailClient {
+    pub fn new(
+        base_url: String,
+        region: String,
+        sender: Email,
+        credentials: AwsCredentials,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            region,
+            sender,
+            credentials,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SesEmailClient {
+    #[tracing::instrument(name = "Sending email through SES", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join("/v2/email/outbound-emails")?;
+
+        // See https://docs.aws.amazon.com/ses/latest/APIReference-V2/API_SendEmail.html
+        let request_body = serde_json::to_vec(&SendEmailRequest {
+            from_email_address: self.sender.as_ref(),
+            destination: Destination {
+                to_addresses: [message.recipient.as_ref()],
+            },
+            content: Content {
+                simple: SimpleMessage {
+                    subject: Utf8Content::new(&message.subject),
+                    body: Body {
+                        text: Utf8Content::new(&message.text_body),
+                        html: Utf8Content::new(&message.html_body),
+                    },
+                },
+            },
+        })?;
+
+        let signed = sign_request(
+            "POST",
+            &url,
+            &request_body,
+            &self.credentials,
+            &self.region,
+            Utc::now(),
+        )?;
+
+        let mut request = self
+            .http_client
+            .post(url)
+            .header(CONTENT_TYPE, CONTENT_TYPE_JSON)
+            .header(AMZ_DATE_HEADER, signed.amz_date)
+            .header(AUTHORIZATION, signed.authorization);
+
+        if let Some(token) = &self.credentials.session_token {
+            request = request.header(AMZ_SECURITY_TOKEN_HEADER, token.expose_secret());
+        }
+
+        request
+            .body(request_body)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+struct SignedHeaders {
+    amz_date: String,
+    authorization: String,
+}
+
+/// Signs a JSON request for SES with AWS Signature Version 4.
+/// See https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html
+fn sign_request(
+    method: &str,
+    url: &Url,
+    payload: &[u8],
+    credentials: &AwsCredentials,
+    region: &str,
+    now: DateTime<Utc>,
+) -> Result<SignedHeaders> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => return Err(eyre!("SES URL has no host: {}", url)),
+    };
+
+    // Headers must be lowercase and sorted by name
+    let mut headers = vec![
+        ("content-type", CONTENT_TYPE_JSON.to_owned()),
+        ("host", host),
+        (AMZ_DATE_HEADER, amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push((AMZ_SECURITY_TOKEN_HEADER, token.expose_secret().to_owned()));
+    }
+
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        url.path(),
+        url.query().unwrap_or_default(),
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(payload))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, SERVICE);
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        SIGNING_ALGORITHM,
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let secret = format!("AWS4{}", credentials.secret_access_key.expose_secret());
+    let signing_key = [date.as_str(), region, SERVICE, "aws4_request"]
+        .iter()
+        .fold(secret.into_bytes(), |key, part| {
+            hmac_sha256(&key, part.as_bytes())
+        });
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    Ok(SignedHeaders {
+        authorization: format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            SIGNING_ALGORITHM, credentials.access_key_id, scope, signed_headers, signature
+        ),
+        amz_date,
+    })
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC can take a key of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from_email_address: &'a str,
+    destination: Destination<'a>,
+    content: Content<'a>,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Destination<'a> {
+    to_addresses: [&'a str; 1],
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Content<'a> {
+    simple: SimpleMessage<'a>,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SimpleMessage<'a> {
+    subject: Utf8Content<'a>,
+    body: Body<'a>,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Body<'a> {
+    text: Utf8Content<'a>,
+    html: Utf8Content<'a>,
+}
+
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Utf8Content<'a> {
+    data: &'a str,
+    charset: &'a str,
+}
+
+impl<'a> Utf8Content<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            data,
+            charset: "UTF-8",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::Fake;
+    use wiremock::matchers::{any, header, header_exists, header_regex, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use super::*;
+    use crate::utils::constants::test;
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> Email {
+        Email::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: email(),
+            subject: subject(),
+            html_body: content(),
+            text_body: content(),
+        }
+    }
+
+    fn credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_owned(),
+            secret_access_key: Secret::new("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_owned()),
+            session_token: None,
+        }
+    }
+
+    fn email_client(base_url: String, credentials: AwsCredentials) -> SesEmailClient {
+        let http_client = Client::builder()
+            .timeout(test::email_client::TIMEOUT)
+            .build()
+            .unwrap();
+        SesEmailClient::new(
+            base_url,
+            "us-east-1".to_owned(),
+            email(),
+            credentials,
+            http_client,
+        )
+    }
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                let simple = &body["Content"]["Simple"];
+                body["FromEmailAddress"].is_string()
+                    && body["Destination"]["ToAddresses"][0].is_string()
+                    && simple["Subject"]["Data"].is_string()
+                    && simple["Body"]["Text"]["Data"].is_string()
+                    && simple["Body"]["Html"]["Data"].is_string()
+            } else {
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn sign_request_matches_aws_reference_signature() {
+        // Reference signature computed with botocore's SigV4Auth for the same request
+        let url =
+            Url::parse("https://email.us-east-1.amazonaws.com/v2/email/outbound-emails").unwrap();
+        let now = "2026-10-19T09:46:06Z".parse().unwrap();
+
+        let signed = sign_request(
+            "POST",
+            &url,
+            br#"{"a":1}"#,
+            &credentials(),
+            "us-east-1",
+            now,
+        )
+        .unwrap();
+
+        assert_eq!(signed.amz_date, "20261019T094606Z");
+        assert_eq!(
+            signed.authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20261019/us-east-1/ses/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=1c61e2d98c69f388674fb3a751ce9227ccc05558ed360bb0bb0e5eaf7b452719"
+        );
+    }
+
+    #[test]
+    fn sign_request_includes_session_token() {
+        let url =
+            Url::parse("https://email.us-east-1.amazonaws.com/v2/email/outbound-emails").unwrap();
+        let credentials = AwsCredentials {
+            session_token: Some(Secret::new("token".to_owned())),
+            ..credentials()
+        };
+
+        let signed =
+            sign_request("POST", &url, b"{}", &credentials, "us-east-1", Utc::now()).unwrap();
+
+        assert!(signed
+            .authorization
+            .contains("SignedHeaders=content-type;host;x-amz-date;x-amz-security-token"));
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(
+            mock_server.uri(),
+            AwsCredentials {
+                session_token: Some(Secret::new("token".to_owned())),
+                ..credentials()
+            },
+        );
+
+        Mock::given(header_regex(
+            "Authorization",
+            "^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/\\d{8}/us-east-1/ses/aws4_request, ",
+        ))
+        .and(header_exists(AMZ_DATE_HEADER))
+        .and(header(AMZ_SECURITY_TOKEN_HEADER, "token"))
+        .and(header("Content-Type", "application/json"))
+        .and(path("/v2/email/outbound-emails"))
+        .and(method("POST"))
+        .and(SendEmailBodyMatcher)
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), credentials());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), credentials());
+
+        let response = ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert!(outcome.is_err());
+    }
+}
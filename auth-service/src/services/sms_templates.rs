@@ -0,0 +1,54 @@
+use crate::domain::Locale;
+
+/// The text messages we send. SMS bodies are kept to a single short segment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SmsTemplate {
+    TwoFACode { code: String },
+    PhoneVerification { code: String },
+}
+
+impl SmsTemplate {
+    pub fn render(&self, locale: Locale) -> String {
+        match (self, locale) {
+            (Self::TwoFACode { code }, Locale::En) => {
+                format!("Your sign-in code is {}. It expires in 10 minutes.", code)
+            }
+            (Self::TwoFACode { code }, Locale::Es) => {
+                format!(
+                    "Tu código de inicio de sesión es {}. Caduca en 10 minutos.",
+                    code
+                )
+            }
+            (Self::PhoneVerification { code }, Locale::En) => {
+                format!("Your phone verification code is {}.", code)
+            }
+            (Self::PhoneVerification { code }, Locale::Es) => {
+                format!("Tu código de verificación de teléfono es {}.", code)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_include_the_code_in_every_locale() {
+        for locale in [Locale::En, Locale::Es] {
+            for template in [
+                SmsTemplate::TwoFACode {
+                    code: "123456".to_owned(),
+                },
+                SmsTemplate::PhoneVerification {
+                    code: "123456".to_owned(),
+                },
+            ] {
+                let body = template.render(locale);
+                assert!(body.contains("123456"));
+                // Fits in a single GSM-7/UCS-2 segment
+                assert!(body.chars().count() <= 70);
+            }
+        }
+    }
+}
@@ -0,0 +1,445 @@
+use std::{str::FromStr, time::Duration};
+
+use color_eyre::eyre::{eyre, Report, Result};
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::{
+        authentication::{Credentials, Mechanism},
+        client::{Tls, TlsParameters},
+        PoolConfig,
+    },
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{Email, EmailClient, EmailMessage};
+
+/// How the connection to the relay is secured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTlsMode {
+    /// Plain text. Only meant for relays on a trusted network.
+    None,
+    /// Upgrade a plain connection with `STARTTLS`, failing if the relay does not offer it.
+    StartTls,
+    /// TLS from the first byte, usually on port 465.
+    Implicit,
+}
+
+impl SmtpTlsMode {
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Self::None => 25,
+            Self::StartTls => 587,
+            Self::Implicit => 465,
+        }
+    }
+}
+
+impl FromStr for SmtpTlsMode {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "implicit" | "tls" => Ok(Self::Implicit),
+            _ => Err(eyre!("Unsupported SMTP TLS mode: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpCredentials {
+    pub username: String,
+    pub password: Secret<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpEmailClientConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTlsMode,
+    /// Authenticates with `AUTH PLAIN`, or `AUTH LOGIN` if that is all the relay offers.
+    pub credentials: Option<SmtpCredentials>,
+    /// Applies to connecting and to sending each email as a whole.
+    pub timeout: Duration,
+    /// Maximum number of pooled connections kept open to the relay.
+    pub max_connections: u32,
+    /// How long an unused pooled connection is kept open.
+    pub idle_timeout: Duration,
+}
+
+/// Sends emails through our own mail relay over SMTP.
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: Mailbox,
+    timeout: Duration,
+}
+
+impl SmtpEmailClient {
+    pub fn new(config: SmtpEmailClientConfig, sender: Email) -> Result<Self> {
+        let tls = match config.tls {
+            SmtpTlsMode::None => Tls::None,
+            SmtpTlsMode::StartTls => Tls::Required(TlsParameters::new(config.host.clone())?),
+            SmtpTlsMode::Implicit => Tls::Wrapper(TlsParameters::new(config.host.clone())?),
+        };
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            .port(config.port)
+            .tls(tls)
+            .timeout(Some(config.timeout))
+            .pool_config(
+                PoolConfig::new()
+                    .max_size(config.max_connections)
+                    .idle_timeout(config.idle_timeout),
+            );
+
+        if let Some(credentials) = config.credentials {
+            builder = builder
+                .credentials(Credentials::new(
+                    credentials.username,
+                    credentials.password.expose_secret().to_owned(),
+                ))
+                .authentication(vec![Mechanism::Plain, Mechanism::Login]);
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender: sender.as_ref().parse()?,
+            timeout: config.timeout,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for SmtpEmailClient {
+    #[tracing::instrument(name = "Sending email over SMTP", skip_all)]
+    async fn send_email(&self, message: &EmailMessage) -> Result<()> {
+        let email = build_mime_message(&self.sender, message)?;
+
+        // The transport only times out connecting, so bound the whole exchange
+        // to avoid hanging on a relay that accepts connections but never replies
+        tokio::time::timeout(self.timeout, self.transport.send(email))
+            .await
+            .map_err(|_| eyre!("Timed out sending email over SMTP"))??;
+
+        Ok(())
+    }
+}
+
+/// Builds a `multipart/alternative` message carrying both the text and HTML bodies.
+pub(crate) fn build_mime_message(sender: &Mailbox, message: &EmailMessage) -> Result<Message> {
+    Ok(Message::builder()
+        .from(sender.clone())
+        .to(message.recipient.as_ref().parse()?)
+        .subject(&message.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            message.html_body.clone(),
+        ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use base64::Engine;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct ReceivedEmail {
+        auth: Option<String>,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        data: String,
+    }
+
+    #[derive(Default)]
+    struct FakeSmtpState {
+        connections: usize,
+        received: Vec<ReceivedEmail>,
+    }
+
+    /// A minimal in-process SMTP relay that records what it is sent.
+    struct FakeSmtpServer {
+        port: u16,
+        state: Arc<Mutex<FakeSmtpState>>,
+    }
+
+    #[derive(Clone, Copy)]
+    struct FakeSmtpBehaviour {
+        auth_mechanisms: &'static str,
+        reject_recipients: bool,
+        unresponsive: bool,
+    }
+
+    impl Default for FakeSmtpBehaviour {
+        fn default() -> Self {
+            Self {
+                auth_mechanisms: "PLAIN LOGIN",
+                reject_recipients: false,
+                unresponsive: false,
+            }
+        }
+    }
+
+    impl FakeSmtpServer {
+        async fn start(behaviour: FakeSmtpBehaviour) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let state = Arc::new(Mutex::new(FakeSmtpState::default()));
+
+            let server_state = state.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    server_state.lock().unwrap().connections += 1;
+                    tokio::spawn(handle_connection(stream, behaviour, server_state.clone()));
+                }
+            });
+
+            Self { port, state }
+        }
+
+        fn connections(&self) -> usize {
+            self.state.lock().unwrap().connections
+        }
+
+        fn received(&self) -> Vec<ReceivedEmail> {
+            self.state.lock().unwrap().received.clone()
+        }
+    }
+
+    async fn handle_connection(
+        stream: TcpStream,
+        behaviour: FakeSmtpBehaviour,
+        state: Arc<Mutex<FakeSmtpState>>,
+    ) -> std::io::Result<()> {
+        if behaviour.unresponsive {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            return Ok(());
+        }
+
+        let decode = |value: &str| {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(value.trim())
+                .unwrap_or_default();
+            String::from_utf8_lossy(&bytes).into_owned()
+        };
+
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut auth = None;
+        let mut email = ReceivedEmail::default();
+
+        writer.write_all(b"220 fake.smtp ESMTP\r\n").await?;
+
+        while let Some(line) = lines.next_line().await? {
+            let command = line.to_ascii_uppercase();
+            let reply = if command.starts_with("EHLO") {
+                format!(
+                    "250-fake.smtp\r\n250-AUTH {}\r\n250 8BITMIME\r\n",
+                    behaviour.auth_mechanisms
+                )
+            } else if let Some(credentials) = line.strip_prefix("AUTH PLAIN ") {
+                auth = Some(format!("PLAIN {}", decode(credentials).replace('\0', " ")));
+                "235 Authenticated\r\n".to_owned()
+            } else if command == "AUTH LOGIN" {
+                writer.write_all(b"334 VXNlcm5hbWU6\r\n").await?;
+                let username = lines.next_line().await?.unwrap_or_default();
+                writer.write_all(b"334 UGFzc3dvcmQ6\r\n").await?;
+                let password = lines.next_line().await?.unwrap_or_default();
+                auth = Some(format!("LOGIN {} {}", decode(&username), decode(&password)));
+                "235 Authenticated\r\n".to_owned()
+            } else if command.starts_with("MAIL FROM:") {
+                email = ReceivedEmail {
+                    auth: auth.clone(),
+                    mail_from: line[10..].to_owned(),
+                    ..Default::default()
+                };
+                "250 OK\r\n".to_owned()
+            } else if command.starts_with("RCPT TO:") {
+                match behaviour.reject_recipients {
+                    true => "550 Mailbox unavailable\r\n".to_owned(),
+                    false => {
+                        email.rcpt_to.push(line[8..].to_owned());
+                        "250 OK\r\n".to_owned()
+                    }
+                }
+            } else if command == "DATA" {
+                writer
+                    .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                    .await?;
+                while let Some(data_line) = lines.next_line().await? {
+                    if data_line == "." {
+                        break;
+                    }
+                    email.data.push_str(&data_line);
+                    email.data.push('\n');
+                }
+                state.lock().unwrap().received.push(email.clone());
+                "250 Queued\r\n".to_owned()
+            } else if command == "QUIT" {
+                writer.write_all(b"221 Bye\r\n").await?;
+                return Ok(());
+            } else {
+                // RSET, NOOP and anything else the pool uses to check the connection
+                "250 OK\r\n".to_owned()
+            };
+
+            writer.write_all(reply.as_bytes()).await?;
+        }
+
+        Ok(())
+    }
+
+    fn config(port: u16, credentials: Option<SmtpCredentials>) -> SmtpEmailClientConfig {
+        SmtpEmailClientConfig {
+            host: "127.0.0.1".to_owned(),
+            port,
+            tls: SmtpTlsMode::None,
+            credentials,
+            timeout: Duration::from_millis(500),
+            max_connections: 2,
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+
+    fn credentials() -> SmtpCredentials {
+        SmtpCredentials {
+            username: "relay-user".to_owned(),
+            password: Secret::new("relay-password".to_owned()),
+        }
+    }
+
+    fn sender() -> Email {
+        Email::parse("sender@example.com".to_owned()).unwrap()
+    }
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            recipient: Email::parse("recipient@example.com".to_owned()).unwrap(),
+            subject: "Your sign-in code".to_owned(),
+            html_body: "<p>123456</p>".to_owned(),
+            text_body: "Code: 123456".to_owned(),
+        }
+    }
+
+    #[test]
+    fn tls_mode_parses_case_insensitively() {
+        assert_eq!(
+            "STARTTLS".parse::<SmtpTlsMode>().unwrap(),
+            SmtpTlsMode::StartTls
+        );
+        assert_eq!("tls".parse::<SmtpTlsMode>().unwrap(), SmtpTlsMode::Implicit);
+        assert_eq!("none".parse::<SmtpTlsMode>().unwrap(), SmtpTlsMode::None);
+        assert!("ssl3".parse::<SmtpTlsMode>().is_err());
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_multipart_message_with_auth_plain() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour::default()).await;
+        let client =
+            SmtpEmailClient::new(config(server.port, Some(credentials())), sender()).unwrap();
+
+        client.send_email(&message()).await.unwrap();
+
+        let received = server.received();
+        assert_eq!(received.len(), 1);
+        let email = &received[0];
+        assert_eq!(
+            email.auth.as_deref(),
+            Some("PLAIN  relay-user relay-password")
+        );
+        assert_eq!(email.mail_from, "<sender@example.com>");
+        assert_eq!(email.rcpt_to, vec!["<recipient@example.com>"]);
+        assert!(email.data.contains("Subject: Your sign-in code"));
+        assert!(email.data.contains("multipart/alternative"));
+        assert!(email.data.contains("Code: 123456"));
+        assert!(email.data.contains("<p>123456</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_falls_back_to_auth_login() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour {
+            auth_mechanisms: "LOGIN",
+            ..Default::default()
+        })
+        .await;
+        let client =
+            SmtpEmailClient::new(config(server.port, Some(credentials())), sender()).unwrap();
+
+        client.send_email(&message()).await.unwrap();
+
+        assert_eq!(
+            server.received()[0].auth.as_deref(),
+            Some("LOGIN relay-user relay-password")
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_reuses_pooled_connection() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour::default()).await;
+        let client = SmtpEmailClient::new(config(server.port, None), sender()).unwrap();
+
+        client.send_email(&message()).await.unwrap();
+        // Connections are returned to the pool in the background
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.send_email(&message()).await.unwrap();
+
+        assert_eq!(server.received().len(), 2);
+        assert_eq!(server.connections(), 1);
+        assert_eq!(server.received()[0].auth, None);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_recipient_is_rejected() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour {
+            reject_recipients: true,
+            ..Default::default()
+        })
+        .await;
+        let client = SmtpEmailClient::new(config(server.port, None), sender()).unwrap();
+
+        assert!(client.send_email(&message()).await.is_err());
+        assert!(server.received().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_relay_does_not_respond() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour {
+            unresponsive: true,
+            ..Default::default()
+        })
+        .await;
+        let client = SmtpEmailClient::new(config(server.port, None), sender()).unwrap();
+
+        let outcome =
+            tokio::time::timeout(Duration::from_secs(5), client.send_email(&message())).await;
+
+        assert!(outcome
+            .expect("send_email should time out on its own")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn starttls_is_required_when_configured() {
+        let server = FakeSmtpServer::start(FakeSmtpBehaviour::default()).await;
+        let client = SmtpEmailClient::new(
+            SmtpEmailClientConfig {
+                host: "localhost".to_owned(),
+                tls: SmtpTlsMode::StartTls,
+                ..config(server.port, Some(credentials()))
+            },
+            sender(),
+        )
+        .unwrap();
+
+        assert!(client.send_email(&message()).await.is_err());
+        assert!(server.received().is_empty());
+    }
+}
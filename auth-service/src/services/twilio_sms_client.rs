@@ -0,0 +1,144 @@
+use color_eyre::eyre::Result;
+use reqwest::{Client, Url};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+/// Sends text messages through the Twilio Messages API, or any gateway that
+/// implements the same API.
+pub struct TwilioSmsClient {
+    http_client: Client,
+    base_url: String,
+    account_sid: String,
+    auth_token: Secret<String>,
+    sender: PhoneNumber,
+}
+
+impl TwilioSmsClient {
+    pub fn new(
+        base_url: String,
+        account_sid: String,
+        auth_token: Secret<String>,
+        sender: PhoneNumber,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            account_sid,
+            auth_token,
+            sender,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for TwilioSmsClient {
+    #[tracing::instrument(name = "Sending SMS through Twilio", skip_all)]
+    async fn send_sms(&self, recipient: &PhoneNumber, body: &str) -> Result<()> {
+        let url = Url::parse(&self.base_url)?.join(&format!(
+            "/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        ))?;
+
+        // See https://www.twilio.com/docs/messaging/api/message-resource#create-a-message-resource
+        let form = [
+            ("To", recipient.as_ref()),
+            ("From", self.sender.as_ref()),
+            ("Body", body),
+        ];
+
+        self.http_client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(self.auth_token.expose_secret()))
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{any, body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::utils::constants::test;
+
+    fn sms_client(base_url: String) -> TwilioSmsClient {
+        let http_client = Client::builder()
+            .timeout(test::sms_client::TIMEOUT)
+            .build()
+            .unwrap();
+        TwilioSmsClient::new(
+            base_url,
+            "AC123".to_owned(),
+            Secret::new("token".to_owned()),
+            PhoneNumber::parse(test::sms_client::SENDER.to_owned()).unwrap(),
+            http_client,
+        )
+    }
+
+    fn recipient() -> PhoneNumber {
+        PhoneNumber::parse("+14155550123".to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_sms_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        // base64("AC123:token")
+        Mock::given(header("Authorization", "Basic QUMxMjM6dG9rZW4="))
+            .and(header("Content-Type", "application/x-www-form-urlencoded"))
+            .and(path("/2010-04-01/Accounts/AC123/Messages.json"))
+            .and(method("POST"))
+            .and(body_string_contains("To=%2B14155550123"))
+            .and(body_string_contains("From=%2B"))
+            .and(body_string_contains("Body=Your+code"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&recipient(), "Your code").await;
+
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_sms_fails_if_the_server_returns_400() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&recipient(), "Your code").await;
+
+        assert!(outcome.is_err());
+    }
+
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(201).set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = sms_client.send_sms(&recipient(), "Your code").await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+use crate::{
+    app_state::WebhookStoreType,
+    domain::{WebhookDelivery, WebhookDeliveryAttempt},
+    utils::backoff::Backoff,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const DELIVERY_ID_HEADER: &str = "X-Webhook-Id";
+pub const EVENT_TYPE_HEADER: &str = "X-Webhook-Event";
+
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcherConfig {
+    /// How often the dispatcher looks for due deliveries.
+    pub poll_interval: Duration,
+    /// Maximum number of deliveries claimed per poll.
+    pub batch_size: u32,
+    /// How long a claimed delivery stays hidden from other dispatchers. Must
+    /// cover `batch_size` request timeouts.
+    pub lease: Duration,
+    pub backoff: Backoff,
+}
+
+/// Delivers queued webhook events to subscribers, retrying failures with backoff.
+pub struct WebhookDispatcher {
+    store: WebhookStoreType,
+    http_client: Client,
+    config: WebhookDispatcherConfig,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        store: WebhookStoreType,
+        http_client: Client,
+        config: WebhookDispatcherConfig,
+    ) -> Self {
+        Self {
+            store,
+            http_client,
+            config,
+        }
+    }
+
+    /// Polls for due deliveries until the task is dropped.
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(self.config.poll_interval);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.dispatch_due().await {
+                tracing::error!(error = ?e, "failed to dispatch webhooks");
+            }
+        }
+    }
+
+    /// Attempts every delivery that is currently due and returns how many were attempted.
+    /// The batch is sent concurrently, so that every attempt is recorded before the
+    /// lease runs out and another dispatcher claims the same deliveries.
+    #[tracing::instrument(name = "Dispatching due webhooks", skip_all)]
+    pub async fn dispatch_due(&self) -> Result<usize> {
+        let lease_until = Utc::now() + self.config.lease;
+        let deliveries = self
+            .store
+            .claim_due_deliveries(self.config.batch_size, lease_until)
+            .await?;
+
+        let count = deliveries.len();
+        let attempts = join_all(deliveries.iter().map(|delivery| self.attempt(delivery))).await;
+
+        for (delivery, attempt) in deliveries.into_iter().zip(attempts) {
+            let retry_at = match attempt.succeeded() {
+                true => None,
+                false => self
+                    .config
+                    .backoff
+                    .next_delay(delivery.attempts + 1)
+                    .map(|delay| attempt.attempted_at + delay),
+            };
+
+            if let Some(error) = &attempt.error {
+                tracing::warn!(
+                    delivery_id = %delivery.id,
+                    url = %delivery.url,
+                    will_retry = retry_at.is_some(),
+                    "webhook delivery failed: {}",
+                    error
+                );
+            }
+
+            self.store.record_attempt(attempt, retry_at).await?;
+        }
+
+        Ok(count)
+    }
+
+    async fn attempt(&self, delivery: &WebhookDelivery) -> WebhookDeliveryAttempt {
+        let attempted_at = Utc::now();
+        let timestamp = attempted_at.timestamp().to_string();
+        let signature = sign_payload(&delivery.secret, &timestamp, &delivery.payload);
+
+        let response = self
+            .http_client
+            .post(&delivery.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, format!("sha256={}", signature))
+            .header(TIMESTAMP_HEADER, timestamp)
+            .header(DELIVERY_ID_HEADER, delivery.id.to_string())
+            .header(EVENT_TYPE_HEADER, delivery.event_type.as_str())
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        let (response_status, error) = match response {
+            Ok(response) if response.status().is_success() => (Some(response.status()), None),
+            Ok(response) => (
+                Some(response.status()),
+                Some(format!("Subscriber responded with {}", response.status())),
+            ),
+            Err(e) => (e.status(), Some(e.to_string())),
+        };
+
+        WebhookDeliveryAttempt {
+            delivery_id: delivery.id,
+            attempted_at,
+            response_status: response_status.map(|status| status.as_u16()),
+            error,
+        }
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of `"{timestamp}.{payload}"`. Subscribers recompute
+/// this with their secret to check that a request came from us and was not replayed.
+pub fn sign_payload(secret: &Secret<String>, timestamp: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use wiremock::matchers::{header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    use super::*;
+    use crate::{
+        domain::{
+            data_stores::WebhookStore, Email, WebhookDeliveryStatus, WebhookEvent,
+            WebhookEventType, WebhookSubscription,
+        },
+        services::data_stores::HashmapWebhookStore,
+    };
+
+    fn config(max_attempts: u32) -> WebhookDispatcherConfig {
+        WebhookDispatcherConfig {
+            poll_interval: Duration::from_millis(10),
+            batch_size: 10,
+            lease: Duration::from_secs(30),
+            backoff: Backoff::new(Duration::ZERO, Duration::ZERO, max_attempts),
+        }
+    }
+
+    async fn dispatcher_with_subscription(
+        url: String,
+        max_attempts: u32,
+    ) -> (WebhookDispatcher, WebhookStoreType, WebhookSubscription) {
+        let subscription = WebhookSubscription::new(url, vec![WebhookEventType::UserSignedUp]);
+        let store = HashmapWebhookStore::default();
+        store.add_subscription(subscription.clone()).await.unwrap();
+        store
+            .enqueue_event(&WebhookEvent::new(
+                WebhookEventType::UserSignedUp,
+                Email::parse("test@example.com".to_owned()).unwrap(),
+            ))
+            .await
+            .unwrap();
+
+        let store: WebhookStoreType = Arc::new(store);
+        let http_client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let dispatcher = WebhookDispatcher::new(store.clone(), http_client, config(max_attempts));
+
+        (dispatcher, store, subscription)
+    }
+
+    struct ValidSignatureMatcher(Secret<String>);
+
+    impl wiremock::Match for ValidSignatureMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let header = |name: &str| request.headers.get(name)?.to_str().ok();
+            let (Some(signature), Some(timestamp)) =
+                (header(SIGNATURE_HEADER), header(TIMESTAMP_HEADER))
+            else {
+                return false;
+            };
+            let payload = String::from_utf8_lossy(&request.body);
+
+            signature == format!("sha256={}", sign_payload(&self.0, timestamp, &payload))
+        }
+    }
+
+    #[test]
+    fn signature_depends_on_secret_timestamp_and_payload() {
+        let secret = Secret::new("secret".to_owned());
+        let signature = sign_payload(&secret, "1", "{}");
+
+        assert_eq!(signature.len(), 64);
+        assert_ne!(
+            signature,
+            sign_payload(&Secret::new("other".to_owned()), "1", "{}")
+        );
+        assert_ne!(signature, sign_payload(&secret, "2", "{}"));
+        assert_ne!(signature, sign_payload(&secret, "1", "[]"));
+    }
+
+    #[tokio::test]
+    async fn dispatch_due_sends_signed_request() {
+        let mock_server = MockServer::start().await;
+        let (dispatcher, store, subscription) =
+            dispatcher_with_subscription(format!("{}/hooks", mock_server.uri()), 3).await;
+
+        Mock::given(method("POST"))
+            .and(path("/hooks"))
+            .and(header("Content-Type", "application/json"))
+            .and(header(EVENT_TYPE_HEADER, "user.signed_up"))
+            .and(header_exists(DELIVERY_ID_HEADER))
+            .and(ValidSignatureMatcher(subscription.secret.clone()))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+
+        let log = store.get_delivery_log(&subscription.id).await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status, WebhookDeliveryStatus::Delivered);
+        assert_eq!(log[0].attempt.response_status, Some(200));
+    }
+
+    #[tokio::test]
+    async fn dispatch_due_retries_until_attempts_are_exhausted() {
+        let mock_server = MockServer::start().await;
+        let (dispatcher, store, subscription) =
+            dispatcher_with_subscription(mock_server.uri(), 2).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+
+        let log = store.get_delivery_log(&subscription.id).await.unwrap();
+        assert_eq!(log.len(), 2);
+        assert!(log
+            .iter()
+            .all(|entry| entry.status == WebhookDeliveryStatus::Failed
+                && entry.attempt.response_status == Some(500)));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, Email},
+};
+
+use super::request_context::RequestContext;
+
+/// Appends an event to the audit log. Failures are logged rather than returned
+/// so that an audit log outage does not lock users out.
+#[tracing::instrument(name = "Recording audit event", skip_all, fields(event_type = %event_type))]
+pub async fn record_audit_event(
+    state: &AppState,
+    context: &RequestContext,
+    event_type: AuditEventType,
+    email: Option<&Email>,
+) {
+    let event = AuditEvent {
+        ip_address: context.ip_address,
+        user_agent: context.user_agent.clone(),
+        request_id: context.request_id.clone(),
+        ..AuditEvent::new(event_type, email.cloned())
+    };
+
+    if let Err(e) = state.audit_log_store.append_event(event).await {
+        tracing::error!(error = ?e, "failed to record audit event");
+    }
+}
@@ -1,17 +1,25 @@
-use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum_extra::extract::{
+    cookie::{Cookie, SameSite},
+    CookieJar,
+};
 use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, Result};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::BannedTokenStoreType,
+    domain::{email::Email, AuthAPIError, TokenEpoch},
+    settings::AuthSettings,
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::constants::JWT_COOKIE_NAME;
 
 #[tracing::instrument(skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(email: &Email, settings: &AuthSettings) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(email, settings)?;
     Ok(create_auth_cookie(token))
 }
 
@@ -26,16 +34,25 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
     cookie
 }
 
-pub const TOKEN_TTL_SECONDS: i64 = 600;
-
 #[tracing::instrument(skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+fn generate_auth_token(email: &Email, settings: &AuthSettings) -> Result<String> {
+    create_token(&new_claims(email, settings)?, settings)
+}
 
-    let exp = Utc::now()
+fn new_claims(email: &Email, settings: &AuthSettings) -> Result<Claims> {
+    let delta = chrono::Duration::from_std(settings.token_ttl())
+        .wrap_err("failed to create token ttl time delta")?;
+
+    let now = Utc::now();
+    let iat_ms = now.timestamp_millis();
+    let iat: usize = now.timestamp().try_into().wrap_err(format!(
+        "failed to cast iat time to usize. iat time: {}",
+        now.timestamp()
+    ))?;
+
+    let exp = now
         .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
+        .ok_or(eyre!("failed to add token ttl to current time"))?
         .timestamp();
 
     let exp: usize = exp.try_into().wrap_err(format!(
@@ -45,40 +62,92 @@ fn generate_auth_token(email: &Email) -> Result<String> {
 
     let sub = email.as_ref().to_owned();
 
-    let claims = Claims { sub, exp };
-
-    create_token(&claims)
+    Ok(Claims {
+        sub,
+        exp,
+        iat,
+        iat_ms: Some(iat_ms),
+        jti: Uuid::new_v4().to_string(),
+    })
 }
 
+/// Checks the signature and expiry, then that the token was neither banned on
+/// its own at logout nor issued before the user's token epoch.
 #[tracing::instrument(skip_all)]
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
+    settings: &AuthSettings,
 ) -> Result<Claims> {
-    match banned_token_store.read().await.contains_token(token).await {
-        Ok(value) => {
-            if value {
-                return Err(eyre!("token is banned"));
-            }
-        }
-        Err(e) => return Err(e.into()),
-    }
-
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &DecodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
         &Validation::default(),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .wrap_err("failed to decode token")?;
+
+    let email = Email::parse(claims.sub.clone()).map_err(|e| eyre!(e))?;
+    if banned_token_store.contains_token(&claims.jti).await? {
+        return Err(eyre!("token is banned"));
+    }
+
+    if let Some(epoch) = banned_token_store.get_token_epoch(&email).await? {
+        if epoch.bans(claims.issued_at_ms(), &claims.jti) {
+            return Err(eyre!("token was issued before the user's token epoch"));
+        }
+    }
+
+    Ok(claims)
 }
 
+/// Bans every token issued to the user so far, e.g. after a password change,
+/// and returns the auth cookie for a fresh one. The epoch is the time of the ban
+/// in milliseconds, so a login right after it is not caught, and the fresh
+/// token is also exempted by its `jti` in case the clock steps back.
 #[tracing::instrument(skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+pub async fn ban_all_tokens(
+    email: &Email,
+    banned_token_store: BannedTokenStoreType,
+    settings: &AuthSettings,
+) -> Result<Cookie<'static>> {
+    let banned_at = Utc::now().timestamp_millis();
+    let claims = new_claims(email, settings)?;
+
+    let epoch = TokenEpoch {
+        epoch: banned_at,
+        exempt_jti: Some(claims.jti.clone()),
+    };
+    banned_token_store.set_token_epoch(email, epoch).await?;
+
+    Ok(create_auth_cookie(create_token(&claims, settings)?))
+}
+
+/// Identifies the signed-in user from the auth cookie, for routes that require one.
+#[tracing::instrument(skip_all)]
+pub async fn authenticate(
+    jar: &CookieJar,
+    banned_token_store: BannedTokenStoreType,
+    settings: &AuthSettings,
+) -> Result<Email, AuthAPIError> {
+    let token = jar
+        .get(JWT_COOKIE_NAME)
+        .ok_or(AuthAPIError::MissingToken)?
+        .value();
+
+    let claims = validate_token(token, banned_token_store, settings)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    Email::parse(claims.sub).map_err(|_| AuthAPIError::InvalidToken)
+}
+
+#[tracing::instrument(skip_all)]
+fn create_token(claims: &Claims, settings: &AuthSettings) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &EncodingKey::from_secret(settings.jwt_secret.expose_secret().as_bytes()),
     )
     .wrap_err("failed to create token")
 }
@@ -87,21 +156,42 @@ fn create_token(claims: &Claims) -> Result<String> {
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    pub iat: usize,
+    /// When the token was issued in Unix milliseconds, as `iat` is too coarse to
+    /// tell a token from the second of a password change apart. Tokens issued
+    /// before this claim existed lack it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_ms: Option<i64>,
+    /// Identifies the token, so that logging out bans just this one.
+    pub jti: String,
+}
+
+impl Claims {
+    pub fn issued_at_ms(&self) -> i64 {
+        self.iat_ms.unwrap_or(self.iat as i64 * 1000)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use secrecy::Secret;
     use std::sync::Arc;
-    use tokio::sync::RwLock;
 
     use crate::{domain::BannedTokenStore, services::data_stores::HashsetBannedTokenStore};
 
     use super::*;
 
+    fn settings() -> AuthSettings {
+        AuthSettings {
+            jwt_secret: Secret::new("secret".to_owned()),
+            token_ttl_secs: 600,
+        }
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(&email, &settings()).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -123,16 +213,18 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(&email, &settings()).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let token = generate_auth_token(&email, &settings()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::new(settings().token_ttl()));
+        let result = validate_token(&token, banned_token_store, &settings())
+            .await
+            .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -146,19 +238,97 @@ mod tests {
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
-        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::new(settings().token_ttl()));
+        let result = validate_token(&token, banned_token_store, &settings()).await;
         assert!(result.is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_banned_token() {
         let email = Email::parse("test@example.com".to_owned()).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let mut hs = HashsetBannedTokenStore::default();
-        hs.add_token(token.clone()).await.unwrap();
-        let banned_token_store = Arc::new(RwLock::new(hs));
-        let result = validate_token(&token, banned_token_store).await;
+        let token = generate_auth_token(&email, &settings()).unwrap();
+        let other_token = generate_auth_token(&email, &settings()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::new(settings().token_ttl()));
+        let claims = validate_token(&token, banned_token_store.clone(), &settings())
+            .await
+            .unwrap();
+
+        banned_token_store.add_token(claims.jti).await.unwrap();
+
+        let result = validate_token(&token, banned_token_store.clone(), &settings()).await;
         assert!(result.is_err());
+        let result = validate_token(&other_token, banned_token_store, &settings()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_issued_before_epoch() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let now = Utc::now().timestamp_millis();
+        let claims = |iat_ms: i64| Claims {
+            sub: email.as_ref().to_owned(),
+            exp: (now / 1000) as usize + 600,
+            iat: (iat_ms / 1000) as usize,
+            iat_ms: Some(iat_ms),
+            jti: Uuid::new_v4().to_string(),
+        };
+        let old_token = create_token(&claims(now - 1), &settings()).unwrap();
+        let new_token = create_token(&claims(now), &settings()).unwrap();
+        let other_user_token = generate_auth_token(
+            &Email::parse("other@example.com".to_owned()).unwrap(),
+            &settings(),
+        )
+        .unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::new(settings().token_ttl()));
+
+        banned_token_store
+            .set_token_epoch(
+                &email,
+                TokenEpoch {
+                    epoch: now,
+                    exempt_jti: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let result = validate_token(&old_token, banned_token_store.clone(), &settings()).await;
+        assert!(result.is_err());
+        let result = validate_token(&new_token, banned_token_store.clone(), &settings()).await;
+        assert!(result.is_ok());
+        let result = validate_token(&other_user_token, banned_token_store, &settings()).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ban_all_tokens_bans_earlier_tokens_but_not_later_ones() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email, &settings()).unwrap();
+        let banned_token_store = Arc::new(HashsetBannedTokenStore::new(settings().token_ttl()));
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+
+        let cookie = ban_all_tokens(&email, banned_token_store.clone(), &settings())
+            .await
+            .unwrap();
+        let later_token = generate_auth_token(&email, &settings()).unwrap();
+
+        let result = validate_token(&token, banned_token_store.clone(), &settings()).await;
+        assert!(result.is_err());
+        let result = validate_token(cookie.value(), banned_token_store.clone(), &settings()).await;
+        assert!(result.is_ok());
+        let result = validate_token(&later_token, banned_token_store, &settings()).await;
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_issued_at_ms_falls_back_to_iat() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            exp: 1_600,
+            iat: 1_000,
+            iat_ms: None,
+            jti: Uuid::new_v4().to_string(),
+        };
+        assert_eq!(claims.issued_at_ms(), 1_000_000);
     }
 }
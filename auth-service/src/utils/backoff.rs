@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// Exponential backoff schedule for retrying background deliveries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub max_attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(base_delay: Duration, max_delay: Duration, max_attempts: u32) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            max_attempts,
+        }
+    }
+
+    /// Delay before the next try after `attempts` failed attempts, or `None`
+    /// once the attempts are exhausted.
+    pub fn next_delay(&self, attempts: u32) -> Option<Duration> {
+        if attempts >= self.max_attempts {
+            return None;
+        }
+
+        let factor = 2u32
+            .checked_pow(attempts.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        Some(
+            self.base_delay
+                .checked_mul(factor)
+                .unwrap_or(self.max_delay)
+                .min(self.max_delay),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff() -> Backoff {
+        Backoff::new(Duration::from_secs(1), Duration::from_secs(10), 6)
+    }
+
+    #[test]
+    fn delay_doubles_after_each_attempt() {
+        let backoff = backoff();
+
+        assert_eq!(backoff.next_delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next_delay(2), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.next_delay(3), Some(Duration::from_secs(4)));
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        assert_eq!(backoff().next_delay(5), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        assert_eq!(backoff().next_delay(6), None);
+    }
+}
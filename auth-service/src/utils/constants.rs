@@ -1,69 +1,85 @@
-use dotenvy::dotenv;
-use lazy_static::lazy_static;
-use secrecy::Secret;
-use std::env as std_env;
-
-lazy_static! {
-    pub static ref JWT_SECRET: Secret<String> = set_token();
-    pub static ref DATABASE_URL: String = set_db_url();
-    pub static ref REDIS_HOST_NAME: String = set_redis_host();
-    pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
-}
-
-fn set_token() -> Secret<String> {
-    dotenv().ok();
-    let secret = std_env::var(env::JWT_SECRET_ENV_VAR).expect("JWT_SECRET must be set.");
-    if secret.is_empty() {
-        panic!("JWT_SECRET must not be empty.");
-    }
-    Secret::new(secret)
-}
-
-fn set_db_url() -> String {
-    dotenv().ok();
-    std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set.")
-}
-
-fn set_redis_host() -> String {
-    dotenv().ok();
-    std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
-}
-
-fn set_postmark_auth_token() -> Secret<String> {
-    dotenv().ok();
-    Secret::new(
-        std_env::var(env::POSTMARK_AUTH_TOKEN_ENV_VAR).expect("POSTMARK_AUTH_TOKEN must be set."),
-    )
-}
-
 pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const PASSWORD_PEPPER_ENV_VAR: &str = "PASSWORD_PEPPER";
+    pub const REDIS_MODE_ENV_VAR: &str = "REDIS_MODE";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const REDIS_NODES_ENV_VAR: &str = "REDIS_NODES";
+    pub const REDIS_USERNAME_ENV_VAR: &str = "REDIS_USERNAME";
+    pub const REDIS_PASSWORD_ENV_VAR: &str = "REDIS_PASSWORD";
+    pub const REDIS_KEY_NAMESPACE_ENV_VAR: &str = "REDIS_KEY_NAMESPACE";
+    pub const STORE_BACKEND_ENV_VAR: &str = "STORE_BACKEND";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const TRUSTED_PROXIES_ENV_VAR: &str = "TRUSTED_PROXIES";
+    pub const ADMIN_API_TOKEN_ENV_VAR: &str = "ADMIN_API_TOKEN";
+    pub const EMAIL_PROVIDER_ENV_VAR: &str = "EMAIL_PROVIDER";
+    pub const SMTP_HOST_ENV_VAR: &str = "SMTP_HOST";
+    pub const SMTP_PORT_ENV_VAR: &str = "SMTP_PORT";
+    pub const SMTP_TLS_ENV_VAR: &str = "SMTP_TLS";
+    pub const SMTP_USERNAME_ENV_VAR: &str = "SMTP_USERNAME";
+    pub const SMTP_PASSWORD_ENV_VAR: &str = "SMTP_PASSWORD";
+    pub const APP_ENVIRONMENT_ENV_VAR: &str = "APP_ENVIRONMENT";
+    pub const MAILBOX_DIR_ENV_VAR: &str = "MAILBOX_DIR";
+    pub const EMAIL_FALLBACK_PROVIDER_ENV_VAR: &str = "EMAIL_FALLBACK_PROVIDER";
+    pub const SENDGRID_API_KEY_ENV_VAR: &str = "SENDGRID_API_KEY";
+    pub const MAILGUN_API_KEY_ENV_VAR: &str = "MAILGUN_API_KEY";
+    pub const MAILGUN_DOMAIN_ENV_VAR: &str = "MAILGUN_DOMAIN";
+    pub const MAILGUN_BASE_URL_ENV_VAR: &str = "MAILGUN_BASE_URL";
+    pub const AWS_REGION_ENV_VAR: &str = "AWS_REGION";
+    pub const AWS_ACCESS_KEY_ID_ENV_VAR: &str = "AWS_ACCESS_KEY_ID";
+    pub const AWS_SECRET_ACCESS_KEY_ENV_VAR: &str = "AWS_SECRET_ACCESS_KEY";
+    pub const AWS_SESSION_TOKEN_ENV_VAR: &str = "AWS_SESSION_TOKEN";
+    pub const SMS_PROVIDER_ENV_VAR: &str = "SMS_PROVIDER";
+    pub const TWILIO_ACCOUNT_SID_ENV_VAR: &str = "TWILIO_ACCOUNT_SID";
+    pub const TWILIO_AUTH_TOKEN_ENV_VAR: &str = "TWILIO_AUTH_TOKEN";
+    pub const TWILIO_FROM_NUMBER_ENV_VAR: &str = "TWILIO_FROM_NUMBER";
+    pub const TWILIO_BASE_URL_ENV_VAR: &str = "TWILIO_BASE_URL";
+    pub const ALLOWED_ORIGINS_ENV_VAR: &str = "ALLOWED_ORIGINS";
+    pub const APP_CONFIG_DIR_ENV_VAR: &str = "APP_CONFIG_DIR";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
-pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
-
-pub mod prod {
-    pub const APP_ADDRESS: &str = "0.0.0.0:3000";
-    pub mod email_client {
-        use std::time::Duration;
-
-        pub const BASE_URL: &str = "https://api.postmarkapp.com/email";
-        // If you created your own Postmark account, make sure to use your email address!
-        pub const SENDER: &str = "bogdan@codeiron.io";
-        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
-    }
-}
+pub const PRODUCTION_ENVIRONMENT: &str = "production";
+pub const DEFAULT_CONFIG_DIR: &str = "configuration";
 
 pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
+    pub const ADMIN_API_TOKEN: &str = "test-admin-token";
     pub mod email_client {
         use std::time::Duration;
 
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const SENDER: &str = "+15005550006";
+        pub const TIMEOUT: Duration = Duration::from_millis(200);
+    }
+    pub mod rate_limit {
+        pub const IP_CAPACITY: u32 = 1000;
+        pub const IP_REFILL_PER_SECOND: f64 = 1000.0;
+    }
+    pub mod email_outbox {
+        use std::time::Duration;
+
+        pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+        pub const BATCH_SIZE: u32 = 10;
+        pub const LEASE: Duration = Duration::from_secs(1);
+        pub const MAX_ATTEMPTS: u32 = 3;
+        pub const BASE_DELAY: Duration = Duration::ZERO;
+        pub const MAX_DELAY: Duration = Duration::ZERO;
+    }
+    pub mod webhooks {
+        use std::time::Duration;
+
+        pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+        pub const BATCH_SIZE: u32 = 10;
+        pub const TIMEOUT: Duration = Duration::from_millis(200);
+        pub const LEASE: Duration = Duration::from_secs(5);
+        pub const MAX_ATTEMPTS: u32 = 3;
+        pub const BASE_DELAY: Duration = Duration::ZERO;
+        pub const MAX_DELAY: Duration = Duration::ZERO;
+    }
 }
@@ -0,0 +1,207 @@
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use color_eyre::eyre::{eyre, Result};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+const WILDCARD: &str = "*";
+
+/// The CORS rules that apply to one route, after any override for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorsPolicy {
+    /// Exact origins, `https://*.example.com` subdomain patterns, or `*` for any origin.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Header names, or `*` for any header.
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: Option<Duration>,
+}
+
+impl CorsPolicy {
+    /// Builds the layer, rejecting rules the browser would refuse, such as
+    /// allowing credentials for every origin.
+    pub fn layer(&self) -> Result<CorsLayer> {
+        let any_origin = self.allowed_origins.iter().any(|origin| origin == WILDCARD);
+        let any_header = self.allowed_headers.iter().any(|header| header == WILDCARD);
+
+        if self.allow_credentials && (any_origin || any_header) {
+            return Err(eyre!(
+                "credentials cannot be allowed together with a `*` origin or header"
+            ));
+        }
+
+        let allow_origin = if any_origin {
+            AllowOrigin::any()
+        } else {
+            let patterns = self
+                .allowed_origins
+                .iter()
+                .map(|origin| OriginPattern::parse(origin))
+                .collect::<Result<Vec<_>>>()?;
+            AllowOrigin::predicate(move |origin, _| {
+                origin
+                    .to_str()
+                    .map(|origin| patterns.iter().any(|pattern| pattern.matches(origin)))
+                    .unwrap_or(false)
+            })
+        };
+
+        let allow_headers = if any_header {
+            AllowHeaders::any()
+        } else {
+            let headers = self
+                .allowed_headers
+                .iter()
+                .map(|header| {
+                    header
+                        .parse::<HeaderName>()
+                        .map_err(|_| eyre!("invalid header name: {}", header))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            AllowHeaders::list(headers)
+        };
+
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| eyre!("invalid method: {}", method))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut layer = CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods(methods)
+            .allow_headers(allow_headers)
+            .allow_credentials(self.allow_credentials);
+
+        if let Some(max_age) = self.max_age {
+            layer = layer.max_age(max_age);
+        }
+
+        Ok(layer)
+    }
+}
+
+/// An allowed origin, where `*` may stand in for the subdomains of a host.
+#[derive(Debug, Clone, PartialEq)]
+enum OriginPattern {
+    Exact(String),
+    Subdomains { scheme: String, suffix: String },
+}
+
+impl OriginPattern {
+    fn parse(origin: &str) -> Result<Self> {
+        if HeaderValue::from_str(origin).is_err() {
+            return Err(eyre!("invalid origin: {}", origin));
+        }
+
+        let (scheme, host) = origin
+            .split_once("://")
+            .ok_or_else(|| eyre!("origin must include a scheme: {}", origin))?;
+
+        match host.strip_prefix("*.") {
+            Some(suffix) if !suffix.is_empty() && !suffix.contains('*') => Ok(Self::Subdomains {
+                scheme: format!("{}://", scheme),
+                suffix: format!(".{}", suffix),
+            }),
+            None if !host.contains('*') => Ok(Self::Exact(origin.to_owned())),
+            _ => Err(eyre!(
+                "`*` is only allowed as the first label of the host: {}",
+                origin
+            )),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Exact(expected) => expected == origin,
+            Self::Subdomains { scheme, suffix } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|host| host.strip_suffix(suffix.as_str()))
+                .is_some_and(|subdomain| {
+                    !subdomain.is_empty()
+                        && subdomain
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> CorsPolicy {
+        CorsPolicy {
+            allowed_origins: vec!["http://localhost:8000".to_owned()],
+            allowed_methods: vec!["GET".to_owned(), "POST".to_owned()],
+            allowed_headers: vec!["content-type".to_owned()],
+            allow_credentials: true,
+            max_age: Some(Duration::from_secs(600)),
+        }
+    }
+
+    #[test]
+    fn exact_origins_match_only_themselves() {
+        let pattern = OriginPattern::parse("http://localhost:8000").unwrap();
+
+        assert!(pattern.matches("http://localhost:8000"));
+        assert!(!pattern.matches("http://localhost:8001"));
+        assert!(!pattern.matches("https://localhost:8000"));
+    }
+
+    #[test]
+    fn wildcard_origins_match_subdomains_only() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://eu.app.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("http://app.example.com"));
+        assert!(!pattern.matches("https://app.example.com.evil.com"));
+        assert!(!pattern.matches("https://evil.com/.example.com"));
+    }
+
+    #[test]
+    fn malformed_origins_are_rejected() {
+        for origin in [
+            "localhost:8000",
+            "https://app.*.example.com",
+            "https://*.",
+            "https://*.*.example.com",
+        ] {
+            assert!(OriginPattern::parse(origin).is_err(), "{}", origin);
+        }
+    }
+
+    #[test]
+    fn credentials_cannot_be_combined_with_wildcards() {
+        assert!(policy().layer().is_ok());
+
+        let mut any_origin = policy();
+        any_origin.allowed_origins = vec![WILDCARD.to_owned()];
+        assert!(any_origin.layer().is_err());
+
+        let mut any_header = policy();
+        any_header.allowed_headers = vec![WILDCARD.to_owned()];
+        assert!(any_header.layer().is_err());
+
+        any_origin.allow_credentials = false;
+        assert!(any_origin.layer().is_ok());
+    }
+
+    #[test]
+    fn invalid_methods_and_headers_are_rejected() {
+        let mut invalid_method = policy();
+        invalid_method.allowed_methods = vec!["GET POST".to_owned()];
+        assert!(invalid_method.layer().is_err());
+
+        let mut invalid_header = policy();
+        invalid_header.allowed_headers = vec!["content type".to_owned()];
+        assert!(invalid_header.layer().is_err());
+    }
+}
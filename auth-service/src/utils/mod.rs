@@ -1,3 +1,11 @@
+pub mod audit;
 pub mod auth;
+pub mod backoff;
+pub mod clock;
 pub mod constants;
+pub mod cors;
+pub mod password;
+pub mod rate_limit;
+pub mod request_context;
 pub mod tracing;
+pub mod webhooks;
@@ -0,0 +1,37 @@
+use secrecy::Secret;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, Email, PasswordPolicyViolation},
+};
+
+/// Checks a password chosen at signup or when changing passwords against the
+/// policy and, if configured, the breached password corpus.
+#[tracing::instrument(name = "Checking new password", skip_all)]
+pub async fn check_new_password(
+    state: &AppState,
+    password: &Secret<String>,
+    email: &Email,
+) -> Result<(), AuthAPIError> {
+    let mut violations = match state.settings.password_policy.check(password, email) {
+        Ok(()) => Vec::new(),
+        Err(violations) => violations,
+    };
+
+    if let Some(checker) = &state.breached_password_checker {
+        match checker.is_breached(password).await {
+            Ok(true) => violations.push(PasswordPolicyViolation::Breached),
+            Ok(false) => {}
+            Err(e) if state.settings.breached_passwords.fail_open => {
+                tracing::warn!(error = ?e, "failed to check for a breached password");
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(AuthAPIError::WeakPassword(violations))
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    convert::Infallible,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, MatchedPath, Request},
+    http::{
+        header::{CONTENT_TYPE, RETRY_AFTER},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use ipnet::IpNet;
+use tower::{Layer, Service};
+
+use crate::{
+    app_state::RateLimitStoreType,
+    domain::{Email, RateLimitDecision, RateLimitPolicy},
+    ErrorResponse,
+};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const MAX_INSPECTED_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    /// Bucket applied per route to every client IP.
+    pub ip_policy: RateLimitPolicy,
+    /// Bucket applied per route to the `email` field of JSON request bodies, if set.
+    pub email_policy: Option<RateLimitPolicy>,
+    /// Proxies whose `X-Forwarded-For` header is trusted to carry the client IP.
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStoreType,
+    config: RateLimitConfig,
+}
+
+impl RateLimiter {
+    pub fn new(store: RateLimitStoreType, config: RateLimitConfig) -> Self {
+        Self { store, config }
+    }
+
+    pub fn trusted_proxies(&self) -> &[IpNet] {
+        &self.config.trusted_proxies
+    }
+
+    pub fn layer(&self) -> RateLimitLayer {
+        RateLimitLayer {
+            limiter: self.clone(),
+        }
+    }
+
+    #[tracing::instrument(name = "Checking rate limit", skip_all)]
+    async fn check(&self, request: Request) -> Result<Request, Response> {
+        // The route template, so that `/admin/webhooks/:id` is one bucket
+        // rather than one per ID
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| path.as_str())
+            .unwrap_or_else(|| request.uri().path())
+            .to_owned();
+
+        let peer_ip = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        if let Some(peer_ip) = peer_ip {
+            let ip = client_ip(peer_ip, request.headers(), &self.config.trusted_proxies);
+            let key = format!("{}:ip:{}", route, ip);
+            self.consume(&key, &self.config.ip_policy).await?;
+        }
+
+        let Some(email_policy) = &self.config.email_policy else {
+            return Ok(request);
+        };
+
+        let (request, email) = extract_email(request).await?;
+        if let Some(email) = email {
+            let key = format!("{}:email:{}", route, email.to_lowercase());
+            self.consume(&key, email_policy).await?;
+        }
+
+        Ok(request)
+    }
+
+    /// Applies the email policy to a request that names its account in the auth
+    /// token rather than in its body.
+    pub async fn check_account(&self, route: &str, email: &Email) -> Result<(), Response> {
+        let Some(email_policy) = &self.config.email_policy else {
+            return Ok(());
+        };
+
+        let key = format!("{}:email:{}", route, email.as_ref().to_lowercase());
+        self.consume(&key, email_policy).await
+    }
+
+    async fn consume(&self, key: &str, policy: &RateLimitPolicy) -> Result<(), Response> {
+        let decision = self.store.consume_token(key, policy).await;
+
+        match decision {
+            Ok(RateLimitDecision::Allowed { .. }) => Ok(()),
+            Ok(RateLimitDecision::Limited { retry_after }) => {
+                Err(too_many_requests_response(retry_after))
+            }
+            // Fail open: an unavailable rate limit backend should not take the
+            // whole service down with it.
+            Err(e) => {
+                tracing::error!(error = ?e, "rate limit check failed, allowing request");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: RateLimiter,
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitService {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimitService<S> {
+    inner: S,
+    limiter: RateLimiter,
+}
+
+impl<S> Service<Request> for RateLimitService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // Take the service that was driven to readiness and leave a clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+
+        Box::pin(async move {
+            match limiter.check(request).await {
+                Ok(request) => inner.call(request).await,
+                Err(response) => Ok(response),
+            }
+        })
+    }
+}
+
+/// Resolves the originating client IP. `X-Forwarded-For` is only honoured when the
+/// peer is a trusted proxy, and is walked from the right, hop by hop, for as long
+/// as each hop is a trusted proxy. Anything a client wrote further left, parseable
+/// or not, is never reached.
+pub fn client_ip(peer_ip: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+
+    let forwarded: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+
+    let mut client = peer_ip;
+    for entry in forwarded.into_iter().rev() {
+        if !is_trusted(&client) {
+            break;
+        }
+        match entry.trim().parse() {
+            Ok(ip) => client = ip,
+            Err(_) => break,
+        }
+    }
+    client
+}
+
+async fn extract_email(request: Request) -> Result<(Request, Option<String>), Response> {
+    let is_json = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    if !is_json {
+        return Ok((request, None));
+    }
+
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_INSPECTED_BODY_BYTES)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+
+    let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|body| body.get("email")?.as_str().map(str::to_owned));
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+fn too_many_requests_response(retry_after: Duration) -> Response {
+    let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+
+    let body = Json(ErrorResponse {
+        error: "Too many requests".to_owned(),
+        violations: Vec::new(),
+    });
+
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(RETRY_AFTER, HeaderValue::from(retry_after_secs))],
+        body,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trusted_proxies() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap()]
+    }
+
+    fn headers(x_forwarded_for: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(X_FORWARDED_FOR, x_forwarded_for.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_client_ip_ignores_header_from_untrusted_peer() {
+        let peer: IpAddr = "203.0.113.7".parse().unwrap();
+
+        let ip = client_ip(peer, &headers("198.51.100.1"), &trusted_proxies());
+
+        assert_eq!(ip, peer);
+    }
+
+    #[test]
+    fn test_client_ip_uses_header_from_trusted_peer() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let ip = client_ip(peer, &headers("198.51.100.1, 10.0.0.1"), &trusted_proxies());
+
+        assert_eq!(ip, "198.51.100.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_spoofed_entries() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let ip = client_ip(
+            peer,
+            &headers("1.2.3.4, 198.51.100.1, 10.0.0.1"),
+            &trusted_proxies(),
+        );
+
+        assert_eq!(ip, "198.51.100.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_spoofed_entry_before_malformed_one() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let ip = client_ip(
+            peer,
+            &headers("1.2.3.4, not-an-ip, 198.51.100.1"),
+            &trusted_proxies(),
+        );
+
+        assert_eq!(ip, "198.51.100.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_stops_at_malformed_entry_behind_trusted_proxies() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let ip = client_ip(
+            peer,
+            &headers("1.2.3.4, not-an-ip, 10.0.0.1"),
+            &trusted_proxies(),
+        );
+
+        assert_eq!(ip, "10.0.0.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn test_client_ip_falls_back_to_peer_without_header() {
+        let peer: IpAddr = "10.0.0.2".parse().unwrap();
+
+        let ip = client_ip(peer, &HeaderMap::new(), &trusted_proxies());
+
+        assert_eq!(ip, peer);
+    }
+
+    #[test]
+    fn test_too_many_requests_response_rounds_retry_after_up() {
+        let response = too_many_requests_response(Duration::from_millis(1500));
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "2");
+    }
+}
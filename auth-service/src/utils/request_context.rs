@@ -0,0 +1,58 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts},
+};
+
+use crate::app_state::AppState;
+
+use super::rate_limit::client_ip;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Who made a request, as far as we can tell: the resolved client IP, the
+/// `User-Agent` header and the request id assigned by the request id layer.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestContext {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for RequestContext {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let ip_address =
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| {
+                    client_ip(
+                        addr.ip(),
+                        &parts.headers,
+                        state.rate_limiter.trusted_proxies(),
+                    )
+                });
+
+        let header = |name| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        Ok(Self {
+            ip_address,
+            user_agent: header(USER_AGENT.as_str()),
+            request_id: header(REQUEST_ID_HEADER),
+        })
+    }
+}
@@ -8,6 +8,8 @@ use std::time::Duration;
 use axum::{body::Body, extract::Request, response::Response};
 use tracing::{Level, Span};
 
+use super::request_context::REQUEST_ID_HEADER;
+
 pub fn init_tracing() -> Result<()> {
     let fmt_layer = fmt::layer().compact();
 
@@ -21,10 +23,15 @@ pub fn init_tracing() -> Result<()> {
 
     Ok(())
 }
-// Creates a new tracing span with a unique request ID for each incoming request.
+// Creates a new tracing span tagged with the request ID assigned by the request ID layer.
 // This helps in tracking and correlating logs for individual requests.
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     tracing::span!(
         Level::INFO,
         "[REQUEST]",
@@ -0,0 +1,15 @@
+use crate::{
+    app_state::AppState,
+    domain::{Email, WebhookEvent, WebhookEventType},
+};
+
+/// Queues an event for delivery to webhook subscribers. Failures are logged rather
+/// than returned so that a webhook outage does not fail the user's request.
+#[tracing::instrument(name = "Emitting webhook event", skip_all, fields(event_type = %event_type))]
+pub async fn emit_webhook_event(state: &AppState, event_type: WebhookEventType, email: &Email) {
+    let event = WebhookEvent::new(event_type, email.clone());
+
+    if let Err(e) = state.webhook_store.enqueue_event(&event).await {
+        tracing::error!(error = ?e, "failed to enqueue webhook event");
+    }
+}
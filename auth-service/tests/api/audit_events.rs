@@ -0,0 +1,270 @@
+use auth_service::{routes::AuditEventsResponse, utils::constants::test, ErrorResponse};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp, TEST_USER_AGENT};
+
+async fn get_events(app: &TestApp, email: &str) -> AuditEventsResponse {
+    let response = app
+        .get_audit_events(&[("email", email)], test::ADMIN_API_TOKEN)
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse")
+}
+
+fn event_types(response: &AuditEventsResponse) -> Vec<&str> {
+    response
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect()
+}
+
+#[tokio::test]
+async fn should_record_signup_login_and_logout_events() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong-password",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let login_request_id = response
+        .headers()
+        .get("x-request-id")
+        .expect("No request id header found")
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = get_events(&app, &random_email).await;
+
+    assert_eq!(
+        event_types(&events),
+        vec![
+            "logout",
+            "token_revoked",
+            "login_succeeded",
+            "login_failed",
+            "signup"
+        ]
+    );
+
+    for event in events.events.iter() {
+        assert_eq!(event.email.as_deref(), Some(random_email.as_str()));
+        assert_eq!(event.ip_address.as_deref(), Some("127.0.0.1"));
+        assert_eq!(event.user_agent.as_deref(), Some(TEST_USER_AGENT));
+        assert!(event.request_id.is_some());
+    }
+
+    assert_eq!(
+        events.events[2].request_id.as_deref(),
+        Some(login_request_id.as_str())
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_2fa_events() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
+    let request_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": uuid::Uuid::new_v4().to_string(),
+        "2FACode": "123456"
+    });
+
+    let response = app.post_verify_2fa(&request_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let events = get_events(&app, &random_email).await;
+
+    assert_eq!(
+        event_types(&events),
+        vec!["two_fa_failed", "two_fa_code_sent", "signup"]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_token_revoked_on_password_change() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let change_password_body = serde_json::json!({
+        "currentPassword": "password123",
+        "newPassword": "password456",
+    });
+
+    let response = app.post_change_password(&change_password_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = get_events(&app, &random_email).await;
+
+    assert_eq!(
+        event_types(&events),
+        vec![
+            "password_changed",
+            "token_revoked",
+            "login_succeeded",
+            "signup"
+        ]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_filter_events_by_time_range() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let now = chrono::Utc::now().to_rfc3339();
+
+    let response = app
+        .get_audit_events(
+            &[("email", &random_email), ("from", &now)],
+            test::ADMIN_API_TOKEN,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let events = response.json::<AuditEventsResponse>().await.unwrap();
+    assert!(events.events.is_empty());
+
+    let response = app
+        .get_audit_events(
+            &[("email", &random_email), ("to", &now)],
+            test::ADMIN_API_TOKEN,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let events = response.json::<AuditEventsResponse>().await.unwrap();
+    assert_eq!(event_types(&events), vec!["signup"]);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_admin_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_audit_events(&[], "not-the-admin-token").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_admin_token_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/admin/audit-events", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,143 @@
+use auth_service::{
+    domain::{password_sha1_hex, PasswordPolicyViolation},
+    services::hibp_offline_checker::HibpIndex,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const BREACHED_PASSWORD: &str = "password123";
+
+fn breached_hash() -> String {
+    password_sha1_hex(&Secret::new(BREACHED_PASSWORD.to_owned()))
+}
+
+/// Serves the range of the breached password, like the Pwned Passwords API.
+async fn start_range_api() -> MockServer {
+    let range_api = MockServer::start().await;
+    let hash = breached_hash();
+    let (prefix, suffix) = hash.split_at(5);
+
+    Mock::given(method("GET"))
+        .and(path(format!("/range/{}", prefix)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("{}:2254650\r\n", suffix)))
+        .mount(&range_api)
+        .await;
+
+    range_api
+}
+
+async fn signup(app: &TestApp, password: &str) -> reqwest::Response {
+    app.post_signup(&serde_json::json!({
+        "email": get_random_email(),
+        "password": password,
+        "requires2FA": false
+    }))
+    .await
+}
+
+async fn assert_rejected_as_breached(response: reqwest::Response) {
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .violations,
+        vec![PasswordPolicyViolation::Breached]
+    );
+}
+
+#[tokio::test]
+async fn should_reject_breached_password_found_by_range_api() {
+    let range_api = start_range_api().await;
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.breached_passwords.mode = "api".to_owned();
+        settings.breached_passwords.api_base_url = range_api.uri();
+    })
+    .await;
+
+    assert_rejected_as_breached(signup(&app, BREACHED_PASSWORD).await).await;
+    assert_eq!(
+        signup(&app, "a password nobody used")
+            .await
+            .status()
+            .as_u16(),
+        201
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_breached_password_found_in_offline_index() {
+    let dir = std::env::temp_dir().join(format!("auth-service-hibp-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("pwned-passwords-sha1.txt");
+    std::fs::write(&source, format!("{}:2254650\n", breached_hash())).unwrap();
+    let index_path = dir.join("hibp.idx");
+    HibpIndex::build(&source, &index_path).unwrap();
+
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.breached_passwords.mode = "offline".to_owned();
+        settings.breached_passwords.index_path = index_path;
+    })
+    .await;
+
+    assert_rejected_as_breached(signup(&app, BREACHED_PASSWORD).await).await;
+    assert_eq!(
+        signup(&app, "a password nobody used")
+            .await
+            .status()
+            .as_u16(),
+        201
+    );
+
+    app.clean_up().await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn should_accept_password_when_range_api_fails_open() {
+    let range_api = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&range_api)
+        .await;
+
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.breached_passwords.mode = "api".to_owned();
+        settings.breached_passwords.api_base_url = range_api.uri();
+    })
+    .await;
+
+    assert_eq!(signup(&app, BREACHED_PASSWORD).await.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_fail_when_range_api_fails_closed() {
+    let range_api = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&range_api)
+        .await;
+
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.breached_passwords.mode = "api".to_owned();
+        settings.breached_passwords.api_base_url = range_api.uri();
+        settings.breached_passwords.fail_open = false;
+    })
+    .await;
+
+    assert_eq!(signup(&app, BREACHED_PASSWORD).await.status().as_u16(), 500);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,316 @@
+use auth_service::{
+    domain::{PasswordPolicyViolation, RateLimitPolicy},
+    routes::{AuditEventsResponse, ChangePasswordResponse},
+    utils::constants::{test, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+
+use crate::helpers::{default_rate_limit_config, get_random_email, TestApp};
+
+/// Signs up a user without 2FA and logs them in.
+async fn sign_up_and_log_in(app: &TestApp, email: &str, password: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": password,
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": password
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_200_and_replace_password() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up_and_log_in(&app, &email, "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "a new password"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<ChangePasswordResponse>()
+            .await
+            .expect("Could not deserialize response body to ChangePasswordResponse"),
+        ChangePasswordResponse {
+            message: "Password changed".to_owned(),
+        }
+    );
+
+    let old_login = serde_json::json!({ "email": email, "password": "password123" });
+    assert_eq!(app.post_login(&old_login).await.status().as_u16(), 401);
+
+    let new_login = serde_json::json!({ "email": email, "password": "a new password" });
+    assert_eq!(app.post_login(&new_login).await.status().as_u16(), 200);
+
+    let events = app
+        .get_audit_events(&[("email", &email)], test::ADMIN_API_TOKEN)
+        .await
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse");
+    assert!(events
+        .events
+        .iter()
+        .any(|event| event.event_type == "password_changed"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_current_password_is_incorrect() {
+    let mut app = TestApp::new().await;
+    sign_up_and_log_in(&app, &get_random_email(), "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password124",
+            "newPassword": "a new password"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Incorrect credentials".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_check_current_password_before_new_password_policy() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.password_policy.require_digit = true;
+    })
+    .await;
+    sign_up_and_log_in(&app, &get_random_email(), "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password124",
+            "newPassword": "no digits here"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_account_guesses_too_often() {
+    let mut config = default_rate_limit_config();
+    config.email_policy = Some(RateLimitPolicy::new(2, 0.01));
+    let mut app = TestApp::new_with_rate_limit(config).await;
+    sign_up_and_log_in(&app, &get_random_email(), "password123").await;
+
+    let guess = serde_json::json!({
+        "currentPassword": "password124",
+        "newPassword": "a new password"
+    });
+    for _ in 0..2 {
+        let response = app.post_change_password(&guess).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_change_password(&guess).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().get("retry-after").is_some());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_with_violations_if_new_password_breaks_policy() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.password_policy.require_digit = true;
+        settings.password_policy.min_strength_score = 3;
+    })
+    .await;
+    sign_up_and_log_in(&app, "marguerite@example.com", "x7#Kq9!mZ2vP").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "x7#Kq9!mZ2vP",
+            "newPassword": "Marguerite!"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Password does not meet the policy");
+    assert!(body
+        .violations
+        .contains(&PasswordPolicyViolation::MissingDigit));
+    assert!(body
+        .violations
+        .contains(&PasswordPolicyViolation::ContainsEmail));
+    assert!(body
+        .violations
+        .iter()
+        .any(|violation| matches!(violation, PasswordPolicyViolation::TooWeak { .. })));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_new_password_was_used_recently() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.password_policy.history_size = 2;
+    })
+    .await;
+    sign_up_and_log_in(&app, &get_random_email(), "password123").await;
+
+    let change = |current: &str, new: &str| serde_json::json!({ "currentPassword": current, "newPassword": new });
+
+    // The current password is part of the history
+    let response = app
+        .post_change_password(&change("password123", "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .violations,
+        vec![PasswordPolicyViolation::Reused]
+    );
+
+    let response = app
+        .post_change_password(&change("password123", "password456"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&change("password456", "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Once pruned from the history, a password can be chosen again
+    let response = app
+        .post_change_password(&change("password456", "password789"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_change_password(&change("password789", "password123"))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let history_size: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM password_history")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(history_size, 2);
+
+    app.clean_up().await;
+}
+
+fn auth_token(response: &reqwest::Response) -> String {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn should_sign_out_other_sessions_but_keep_this_one() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up_and_log_in(&app, &email, "password123").await;
+
+    let login_body = serde_json::json!({ "email": email, "password": "password123" });
+    let other_session = auth_token(&app.post_login(&login_body).await);
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "a new password"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let this_session = auth_token(&response);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": this_session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_a_login_right_after_the_change() {
+    let mut app = TestApp::new().await;
+    let email = get_random_email();
+    sign_up_and_log_in(&app, &email, "password123").await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "a new password"
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({ "email": email, "password": "a new password" });
+    let new_session = auth_token(&app.post_login(&login_body).await);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "currentPassword": "password123",
+            "newPassword": "a new password"
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing auth token".to_owned()
+    );
+
+    app.clean_up().await;
+}
@@ -0,0 +1,99 @@
+use crate::helpers::TestApp;
+
+fn header<'a>(response: &'a reqwest::Response, name: &str) -> Option<&'a str> {
+    response
+        .headers()
+        .get(name)
+        .map(|value| value.to_str().unwrap())
+}
+
+#[tokio::test]
+async fn should_allow_preflight_from_configured_origin() {
+    let mut app = TestApp::new().await;
+
+    let response = app.preflight("/login", "http://localhost:8000").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("http://localhost:8000")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-credentials"),
+        Some("true")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-methods"),
+        Some("GET,POST")
+    );
+    assert_eq!(
+        header(&response, "access-control-allow-headers"),
+        Some("content-type")
+    );
+    assert_eq!(header(&response, "access-control-max-age"), Some("3600"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_preflight_from_wildcard_subdomain() {
+    let mut app = TestApp::new().await;
+
+    let response = app.preflight("/login", "https://app.example.com").await;
+
+    assert_eq!(
+        header(&response, "access-control-allow-origin"),
+        Some("https://app.example.com")
+    );
+
+    // The pattern only covers subdomains, not the domain itself
+    let response = app.preflight("/login", "https://example.com").await;
+
+    assert_eq!(header(&response, "access-control-allow-origin"), None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_allow_preflight_from_unknown_origin() {
+    let mut app = TestApp::new().await;
+
+    let response = app.preflight("/login", "https://evil.com").await;
+
+    assert_eq!(header(&response, "access-control-allow-origin"), None);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_apply_route_override() {
+    let mut app = TestApp::new().await;
+
+    let response = app.preflight("/verify-token", "https://evil.com").await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(header(&response, "access-control-allow-origin"), Some("*"));
+    assert_eq!(header(&response, "access-control-allow-credentials"), None);
+    assert_eq!(header(&response, "access-control-max-age"), Some("60"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_add_cors_headers_to_responses() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/verify-token", &app.address))
+        .header("Origin", "https://app.example.com")
+        .json(&serde_json::json!({ "token": "invalid" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(header(&response, "access-control-allow-origin"), Some("*"));
+
+    app.clean_up().await;
+}
@@ -0,0 +1,169 @@
+use auth_service::{
+    domain::{Email, LoginAttemptId, TokenEpoch, TwoFACode, TwoFACodePurpose, TwoFACodeStoreError},
+    routes::TwoFactorAuthResponse,
+    utils::constants::JWT_COOKIE_NAME,
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn spawn_app() -> TestApp {
+    TestApp::new_with_settings(|settings| {
+        settings.stores.backend = "database".to_owned();
+        // Entries are expired behind the stores' backs, which a cache would hide
+        settings.revocation_cache.enabled = false;
+    })
+    .await
+}
+
+#[tokio::test]
+async fn should_log_in_with_2fa_and_log_out_without_redis() {
+    let mut app = spawn_app().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let (_, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
+        .await
+        .expect("Failed to get 2FA code");
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref()
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_ignore_and_sweep_expired_entries() {
+    let mut app = spawn_app().await;
+    let email = Email::parse(get_random_email()).unwrap();
+
+    app.banned_token_store
+        .add_token("expired-token".to_owned())
+        .await
+        .unwrap();
+    app.banned_token_store
+        .add_token("banned-token".to_owned())
+        .await
+        .unwrap();
+    app.banned_token_store
+        .set_token_epoch(
+            &email,
+            TokenEpoch {
+                epoch: 0,
+                exempt_jti: None,
+            },
+        )
+        .await
+        .unwrap();
+    app.two_fa_code_store
+        .add_code(
+            email.clone(),
+            &TwoFACodePurpose::Login,
+            LoginAttemptId::default(),
+            TwoFACode::default(),
+        )
+        .await
+        .unwrap();
+
+    // A literal rather than a bound value, so that Postgres reads it as a timestamp
+    sqlx::query(
+        "UPDATE banned_tokens SET expires_at = '1970-01-01T00:00:00+00:00' \
+         WHERE token = 'expired-token'",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query("UPDATE two_fa_codes SET expires_at = '1970-01-01T00:00:00+00:00'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE token_epochs SET expires_at = '1970-01-01T00:00:00+00:00'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let banned_token_store = &app.banned_token_store;
+    assert!(!banned_token_store
+        .contains_token("expired-token")
+        .await
+        .unwrap());
+    assert!(banned_token_store
+        .contains_token("banned-token")
+        .await
+        .unwrap());
+    assert_eq!(
+        banned_token_store.get_token_epoch(&email).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        app.two_fa_code_store
+            .get_code(&email, &TwoFACodePurpose::Login)
+            .await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    let sweeper = &app.expired_entry_sweeper;
+    assert_eq!(sweeper.sweep().await, 3);
+    assert_eq!(sweeper.sweep().await, 0);
+
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM banned_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(remaining, 1);
+
+    app.clean_up().await;
+}
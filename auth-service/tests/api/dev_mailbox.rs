@@ -0,0 +1,78 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::domain::{Email, TwoFACodePurpose};
+
+#[tokio::test]
+async fn should_return_404_if_dev_mailbox_is_disabled() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_dev_mailbox().await;
+
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_show_emails_written_by_the_file_email_client() {
+    let mut app = TestApp::new_with_dev_mailbox().await;
+
+    let response = app.get_dev_mailbox().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("No emails yet."));
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
+    let (_, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
+        .await
+        .expect("Failed to get 2FA code");
+
+    let response = app.get_dev_mailbox().await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .is_some_and(|value| value.to_str().unwrap().starts_with("text/html")));
+
+    let page = response.text().await.unwrap();
+    assert!(page.contains("Your sign-in code"));
+    assert!(page.contains(&random_email));
+    assert!(page.contains(code.as_ref()));
+
+    let id = page
+        .split("href=\"/dev/mailbox/")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .expect("Mailbox page should link to the email");
+
+    let response = app.get_dev_mailbox_email(id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains(code.as_ref()));
+
+    let response = app.get_dev_mailbox_email("unknown").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
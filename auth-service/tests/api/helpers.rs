@@ -1,56 +1,188 @@
 use core::panic;
 use reqwest::{cookie::Jar, Client};
-use secrecy::Secret;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
-    Connection, Executor, PgConnection, PgPool,
+    AnyPool, Connection, Executor, PgConnection, PgPool, SqlitePool,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
-use std::sync::Arc;
-use tokio::sync::RwLock;
 use wiremock::MockServer;
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType}, domain::Email, get_postgres_pool, get_redis_client, services::{data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore}, postmark_email_client::PostmarkEmailClient}, utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME}, Application
+    app_state::{
+        build_stores, AppState, BannedTokenStoreType, Database, EmailClientType,
+        EmailOutboxStoreType, TwoFACodeStoreType, UserStoreType, WebhookStoreType,
+    },
+    domain::{Email, PhoneNumber, RateLimitPolicy},
+    get_postgres_pool, get_sqlite_pool,
+    services::{
+        data_stores::HashmapRateLimitStore,
+        email_outbox_worker::{EmailOutboxWorker, EmailOutboxWorkerConfig},
+        expired_entry_sweeper::ExpiredEntrySweeper,
+        file_email_client::{DevMailbox, FileEmailClient},
+        postmark_email_client::PostmarkEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_dispatcher::{WebhookDispatcher, WebhookDispatcherConfig},
+    },
+    settings::Settings,
+    utils::{
+        backoff::Backoff,
+        constants::test,
+        rate_limit::{RateLimitConfig, RateLimiter},
+    },
+    Application,
 };
 
 use std::str::FromStr;
 use uuid::Uuid;
 
+pub const TEST_USER_AGENT: &str = "auth-service-tests";
+
 pub struct TestApp {
     pub address: String,
+    pub settings: Arc<Settings>,
+    /// Connects to whichever database the app uses, for queries the API cannot make.
+    pub db_pool: AnyPool,
     pub cookie_jar: Arc<Jar>,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub webhook_dispatcher: WebhookDispatcher,
+    pub email_outbox_worker: EmailOutboxWorker,
+    /// Sweeps the stores as the app would, when `stores.backend` is `database` or `memory`.
+    pub expired_entry_sweeper: ExpiredEntrySweeper,
     pub http_client: reqwest::Client,
     pub email_server: MockServer,
-    pub db_name: String,
+    pub sms_server: MockServer,
+    pub mailbox_dir: Option<PathBuf>,
+    pub database: TestDatabase,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
-        let db_name = Uuid::new_v4().to_string();
-        let pg_pool = configure_postgresql(&db_name).await;
-        let redis_connection = Arc::new(RwLock::new(configure_redis()));
+        Self::new_with_rate_limit(default_rate_limit_config()).await
+    }
+
+    pub async fn new_with_rate_limit(rate_limit_config: RateLimitConfig) -> Self {
+        Self::build(
+            rate_limit_config,
+            None,
+            configure_settings(),
+            std::convert::identity,
+            std::convert::identity,
+        )
+        .await
+    }
+
+    /// Starts the app with the test settings as changed by `configure`.
+    pub async fn new_with_settings(configure: impl FnOnce(&mut Settings)) -> Self {
+        let mut settings = configure_settings();
+        configure(&mut settings);
+        Self::build(
+            default_rate_limit_config(),
+            None,
+            settings,
+            std::convert::identity,
+            std::convert::identity,
+        )
+        .await
+    }
+
+    /// Starts the app with its user and webhook stores wrapped by the given functions.
+    pub async fn new_with_stores(
+        wrap_user_store: impl FnOnce(UserStoreType) -> UserStoreType,
+        wrap_webhook_store: impl FnOnce(WebhookStoreType) -> WebhookStoreType,
+    ) -> Self {
+        Self::build(
+            default_rate_limit_config(),
+            None,
+            configure_settings(),
+            wrap_user_store,
+            wrap_webhook_store,
+        )
+        .await
+    }
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-            redis_connection.clone(),
-        )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    /// Writes emails to a temporary directory and serves them under `/dev/mailbox`.
+    pub async fn new_with_dev_mailbox() -> Self {
+        let mailbox_dir = std::env::temp_dir().join(format!("auth-service-{}", Uuid::new_v4()));
+        Self::build(
+            default_rate_limit_config(),
+            Some(mailbox_dir),
+            configure_settings(),
+            std::convert::identity,
+            std::convert::identity,
+        )
+        .await
+    }
+
+    async fn build(
+        rate_limit_config: RateLimitConfig,
+        mailbox_dir: Option<PathBuf>,
+        settings: Settings,
+        wrap_user_store: impl FnOnce(UserStoreType) -> UserStoreType,
+        wrap_webhook_store: impl FnOnce(WebhookStoreType) -> WebhookStoreType,
+    ) -> Self {
+        let settings = Arc::new(settings);
+        let database = TestDatabase::create(settings.database.url.expose_secret()).await;
+        let db_pool = database
+            .connect_any(settings.database.url.expose_secret())
+            .await;
+
+        let stores = build_stores(&settings, &database.as_database())
+            .await
+            .expect("Failed to build stores");
+        let user_store = wrap_user_store(stores.user_store);
+        let webhook_store = wrap_webhook_store(stores.webhook_store);
+        let webhook_dispatcher = configure_webhook_dispatcher(webhook_store.clone());
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
-        let email_client = Arc::new(configure_postmark_email_client(base_url));
+        let email_client: EmailClientType = match &mailbox_dir {
+            Some(mailbox_dir) => Arc::new(
+                FileEmailClient::new(
+                    mailbox_dir.clone(),
+                    Email::parse(test::email_client::SENDER.to_owned()).unwrap(),
+                )
+                .expect("Failed to create mailbox directory"),
+            ),
+            None => Arc::new(configure_postmark_email_client(base_url)),
+        };
+        let email_outbox_worker =
+            configure_email_outbox_worker(stores.email_outbox_store.clone(), email_client);
+
+        let sms_server = MockServer::start().await;
+        let sms_client = Arc::new(configure_twilio_sms_client(sms_server.uri()));
+
+        let breached_password_checker = settings
+            .breached_passwords
+            .checker()
+            .expect("Failed to build breached password checker");
+
+        // Always in memory, so that tests against one Redis don't share the
+        // buckets of their common client IP
+        let rate_limit_store = Arc::new(HashmapRateLimitStore::default());
+        let rate_limiter = RateLimiter::new(rate_limit_store, rate_limit_config);
 
         let app_state = AppState::new(
+            settings.clone(),
             user_store,
-            banned_token_store.clone(),
-            two_fa_code_store.clone(),
-            email_client,
+            stores.banned_token_store.clone(),
+            stores.two_fa_code_store.clone(),
+            stores.email_outbox_store,
+            sms_client,
+            breached_password_checker,
+            rate_limiter,
+            stores.audit_log_store,
+            webhook_store,
+            mailbox_dir.clone().map(DevMailbox::new),
         );
 
-        let app = Application::build(app_state, test::APP_ADDRESS)
+        let app = Application::build(app_state, &settings.application)
             .await
             .expect("Failed to build app");
 
@@ -62,24 +194,32 @@ impl TestApp {
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .user_agent(TEST_USER_AGENT)
             .build()
             .unwrap();
 
         Self {
             address,
+            settings,
+            db_pool,
             cookie_jar,
-            banned_token_store,
-            two_fa_code_store,
+            banned_token_store: stores.banned_token_store,
+            two_fa_code_store: stores.two_fa_code_store,
+            webhook_dispatcher,
+            email_outbox_worker,
+            expired_entry_sweeper: stores.expired_entry_sweeper,
             http_client,
             email_server,
-            db_name,
+            sms_server,
+            mailbox_dir,
+            database,
             clean_up_called: false,
         }
     }
 
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -90,7 +230,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -102,7 +242,18 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+    pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/change-password", &self.address))
             .json(body)
             .send()
             .await
@@ -141,12 +292,139 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    /// Sends a CORS preflight request, as a browser would before a cross-origin POST.
+    pub async fn preflight(&self, path: &str, origin: &str) -> reqwest::Response {
+        self.http_client
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("{}{}", &self.address, path),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "content-type")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/phone-number", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/verify-phone-number", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_two_fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa-channel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_events(&self, query: &[(&str, &str)], token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/audit-events", &self.address))
+            .bearer_auth(token)
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_webhook<Body>(&self, body: &Body, token: &str) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/admin/webhooks", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_webhooks(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/webhooks", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_webhook(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/webhooks/{}", &self.address, id))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_webhook_deliveries(&self, id: &str, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/admin/webhooks/{}/deliveries",
+                &self.address, id
+            ))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/mailbox", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox_email(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/dev/mailbox/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
         }
 
-        delete_database(&self.db_name).await;
+        self.db_pool.close().await;
+        self.database
+            .delete(self.settings.database.url.expose_secret())
+            .await;
+
+        if let Some(mailbox_dir) = &self.mailbox_dir {
+            let _ = std::fs::remove_dir_all(mailbox_dir);
+        }
 
         self.clean_up_called = true;
     }
@@ -160,14 +438,103 @@ impl Drop for TestApp {
     }
 }
 
+pub fn default_rate_limit_config() -> RateLimitConfig {
+    RateLimitConfig {
+        ip_policy: RateLimitPolicy::new(
+            test::rate_limit::IP_CAPACITY,
+            test::rate_limit::IP_REFILL_PER_SECOND,
+        ),
+        email_policy: None,
+        trusted_proxies: vec![],
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
 
-async fn configure_postgresql(db_name: &str) -> PgPool {
-    let postgresql_conn_url = DATABASE_URL.to_owned();
+/// Loads `configuration/test.toml`, with secrets such as `JWT_SECRET` from the environment.
+fn configure_settings() -> Settings {
+    dotenvy::dotenv().ok();
+    let vars: HashMap<String, String> = std::env::vars().collect();
+    let config_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("configuration");
 
-    configure_database(&postgresql_conn_url, db_name).await;
+    let mut settings =
+        Settings::from_sources(&config_dir, "test", &vars).expect("Failed to load settings");
+    settings.application.address = test::APP_ADDRESS.to_owned();
+    settings.admin.api_token = Some(Secret::new(test::ADMIN_API_TOKEN.to_owned()));
+
+    settings
+}
+
+/// A fresh database for each test: a Postgres database on the configured
+/// server, or a SQLite file in the temporary directory when `DATABASE_URL` is
+/// a `sqlite:` URL.
+pub enum TestDatabase {
+    Postgres { pool: PgPool, name: String },
+    Sqlite { pool: SqlitePool, path: PathBuf },
+}
+
+impl TestDatabase {
+    async fn create(url: &str) -> Self {
+        let name = Uuid::new_v4().to_string();
+
+        if url.starts_with("sqlite:") {
+            let path = std::env::temp_dir().join(format!("auth-service-{}.db", name));
+            let pool = get_sqlite_pool(&format!("sqlite://{}", path.display()))
+                .await
+                .expect("Failed to open SQLite database!");
+
+            sqlx::migrate!("./migrations_sqlite")
+                .run(&pool)
+                .await
+                .expect("Failed to migrate the database");
+
+            Self::Sqlite { pool, path }
+        } else {
+            let pool = configure_postgresql(url, &name).await;
+            Self::Postgres { pool, name }
+        }
+    }
+
+    fn as_database(&self) -> Database {
+        match self {
+            Self::Postgres { pool, .. } => Database::Postgres(pool.clone()),
+            Self::Sqlite { pool, .. } => Database::Sqlite(pool.clone()),
+        }
+    }
+
+    async fn connect_any(&self, url: &str) -> AnyPool {
+        sqlx::any::install_default_drivers();
+
+        let url = match self {
+            Self::Postgres { name, .. } => format!("{}/{}", url, name),
+            Self::Sqlite { path, .. } => format!("sqlite://{}", path.display()),
+        };
+
+        AnyPool::connect(&url)
+            .await
+            .expect("Failed to connect to the test database")
+    }
+
+    async fn delete(&self, url: &str) {
+        match self {
+            Self::Postgres { pool, name } => {
+                pool.close().await;
+                delete_database(url, name).await;
+            }
+            Self::Sqlite { pool, path } => {
+                pool.close().await;
+                for suffix in ["", "-wal", "-shm"] {
+                    let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+                }
+            }
+        }
+    }
+}
+
+async fn configure_postgresql(postgresql_conn_url: &str, db_name: &str) -> PgPool {
+    configure_database(postgresql_conn_url, db_name).await;
 
     let postgresql_conn_url_with_db = format!("{}/{}", postgresql_conn_url, db_name);
 
@@ -176,10 +543,8 @@ async fn configure_postgresql(db_name: &str) -> PgPool {
         .expect("Failed to create Postgres connection pool!")
 }
 
-async fn delete_database(db_name: &str) {
-    let postgresql_conn_url = DATABASE_URL.to_owned();
-
-    let connection_options = PgConnectOptions::from_str(&postgresql_conn_url)
+async fn delete_database(postgresql_conn_url: &str, db_name: &str) {
+    let connection_options = PgConnectOptions::from_str(postgresql_conn_url)
         .expect("Failed to parse PostgreSQL connection string");
 
     let mut connection = PgConnection::connect_with(&connection_options)
@@ -236,15 +601,6 @@ async fn configure_database(db_conn_string: &str, db_name: &str) {
         .expect("Failed to migrate the database");
 }
 
-fn configure_redis() -> redis::Connection {
-    let redis_hostname = DEFAULT_REDIS_HOSTNAME.to_owned();
-
-    get_redis_client(redis_hostname)
-        .expect("Failed to get Redis client")
-        .get_connection()
-        .expect("Failed to get Redis connection")
-}
-
 fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
     let postmark_auth_token = Secret::new("auth_token".to_owned());
 
@@ -257,3 +613,62 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
+
+fn configure_twilio_sms_client(base_url: String) -> TwilioSmsClient {
+    let sender = PhoneNumber::parse(test::sms_client::SENDER.to_owned()).unwrap();
+
+    let http_client = Client::builder()
+        .timeout(test::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        base_url,
+        "ACtest".to_owned(),
+        Secret::new("auth_token".to_owned()),
+        sender,
+        http_client,
+    )
+}
+
+fn configure_webhook_dispatcher(store: WebhookStoreType) -> WebhookDispatcher {
+    let http_client = Client::builder()
+        .timeout(test::webhooks::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookDispatcher::new(
+        store,
+        http_client,
+        WebhookDispatcherConfig {
+            poll_interval: test::webhooks::POLL_INTERVAL,
+            batch_size: test::webhooks::BATCH_SIZE,
+            lease: test::webhooks::LEASE,
+            backoff: Backoff::new(
+                test::webhooks::BASE_DELAY,
+                test::webhooks::MAX_DELAY,
+                test::webhooks::MAX_ATTEMPTS,
+            ),
+        },
+    )
+}
+
+fn configure_email_outbox_worker(
+    store: EmailOutboxStoreType,
+    email_client: EmailClientType,
+) -> EmailOutboxWorker {
+    EmailOutboxWorker::new(
+        store,
+        email_client,
+        EmailOutboxWorkerConfig {
+            poll_interval: test::email_outbox::POLL_INTERVAL,
+            batch_size: test::email_outbox::BATCH_SIZE,
+            lease: test::email_outbox::LEASE,
+            backoff: Backoff::new(
+                test::email_outbox::BASE_DELAY,
+                test::email_outbox::MAX_DELAY,
+                test::email_outbox::MAX_ATTEMPTS,
+            ),
+        },
+    )
+}
@@ -1,8 +1,27 @@
+use std::{sync::Arc, time::Duration};
+
 use crate::helpers::{get_random_email, TestApp};
+use argon2::Params;
 use auth_service::{
-    domain::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse,
+    app_state::{UserStoreType, WebhookStoreType},
+    domain::{
+        Email, Password, PhoneNumber, TwoFAChannel, TwoFACodePurpose, User, UserStore,
+        UserStoreError, WebhookDelivery, WebhookDeliveryAttempt, WebhookDeliveryLogEntry,
+        WebhookEvent, WebhookEventType, WebhookStore, WebhookStoreError, WebhookSubscription,
+    },
+    routes::TwoFactorAuthResponse,
+    services::argon2_password_hasher::Argon2PasswordHasher,
+    utils::constants::{test, JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use secrecy::{ExposeSecret, Secret};
+use tokio::sync::Barrier;
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, ResponseTemplate,
 };
-use wiremock::{matchers::{method, path}, Mock, ResponseTemplate};
 
 #[tokio::test]
 async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
@@ -39,6 +58,67 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_upgrade_weak_password_hash_on_login() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Simulate a hash stored before the cost parameters were raised
+    let weak_hasher = Argon2PasswordHasher::new(Params::new(1024, 1, 1, None).unwrap(), None, 1);
+    let weak_hash = weak_hasher
+        .hash(Secret::new("password123".to_owned()))
+        .await
+        .unwrap();
+
+    sqlx::query("UPDATE users SET password_hash = $1 WHERE email = $2")
+        .bind(weak_hash.expose_secret())
+        .bind(&random_email)
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let password_hash: String =
+        sqlx::query_scalar("SELECT password_hash FROM users WHERE email = $1")
+            .bind(&random_email)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+
+    let hashing = &app.settings.password_hashing;
+    let expected_prefix = format!(
+        "$argon2id$v=19$m={},t={},p={}$",
+        hashing.memory_cost_kib, hashing.time_cost, hashing.parallelism
+    );
+    assert!(password_hash.starts_with(&expected_prefix));
+
+    // The upgraded hash still verifies
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
     let mut app = TestApp::new().await;
@@ -71,6 +151,8 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     let json_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -78,16 +160,100 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     assert_eq!(json_body.message, "2FA required".to_owned());
 
-    let two_fa_code_store = app.two_fa_code_store.read().await;
+    let two_fa_code_store = &app.two_fa_code_store;
 
     let code_tuple = two_fa_code_store
-        .get_code(&Email::parse(random_email).unwrap())
+        .get_code(
+            &Email::parse(random_email).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
         .await
         .expect("Failed to get 2FA code");
-    
+
     assert_eq!(code_tuple.0.as_ref(), json_body.login_attempt_id);
 
-    drop(two_fa_code_store);
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_206_even_if_email_provider_is_down() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(u64::from(test::email_outbox::MAX_ATTEMPTS))
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    // The email is retried until it runs out of attempts, then dead-lettered
+    for _ in 0..test::email_outbox::MAX_ATTEMPTS {
+        assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+    }
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 0);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_in_users_locale() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true,
+        "locale": "es-MX"
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "To": random_email,
+            "Subject": "Tu código de inicio de sesión"
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     app.clean_up().await;
 }
 
@@ -109,7 +275,6 @@ async fn should_return_400_if_invalid_input() {
 
     let test_cases = vec![
         ("invalid_email", "password123"),
-        (random_email.as_str(), "invalid"),
         ("", "password123"),
         (random_email.as_str(), ""),
         ("", ""),
@@ -160,6 +325,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let test_cases = vec![
         (random_email.as_str(), "wrong-password"),
+        (random_email.as_str(), "short"),
         ("wrong@email.com", "password123"),
         ("wrong@email.com", "wrong-password"),
     ];
@@ -230,3 +396,188 @@ async fn should_return_422_if_malformed_credentials() {
 
     app.clean_up().await;
 }
+
+/// Holds every login, and the signup of one email, until all of them are
+/// inside the store at once.
+struct BarrierUserStore {
+    store: UserStoreType,
+    barrier: Arc<Barrier>,
+    slow_signup_email: Email,
+}
+
+#[async_trait::async_trait]
+impl UserStore for BarrierUserStore {
+    async fn add_user(&self, user: User) -> Result<(), UserStoreError> {
+        if user.email == self.slow_signup_email {
+            self.barrier.wait().await;
+        }
+        self.store.add_user(user).await
+    }
+
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        self.store.get_user(email).await
+    }
+
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        self.barrier.wait().await;
+        self.store.validate_user(email, password).await
+    }
+
+    async fn update_password(
+        &self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        self.store.update_password(email, password).await
+    }
+
+    async fn set_pending_phone_number(
+        &self,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        self.store
+            .set_pending_phone_number(email, phone_number)
+            .await
+    }
+
+    async fn verify_phone_number(
+        &self,
+        email: &Email,
+        phone_number: &PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        self.store.verify_phone_number(email, phone_number).await
+    }
+
+    async fn set_two_fa_channel(
+        &self,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        self.store.set_two_fa_channel(email, channel).await
+    }
+}
+
+/// Holds the webhook events of every login, and of the signup of one email,
+/// until all of them are inside the store at once.
+struct BarrierWebhookStore {
+    store: WebhookStoreType,
+    barrier: Arc<Barrier>,
+    slow_signup_email: Email,
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for BarrierWebhookStore {
+    async fn add_subscription(
+        &self,
+        subscription: WebhookSubscription,
+    ) -> Result<(), WebhookStoreError> {
+        self.store.add_subscription(subscription).await
+    }
+
+    async fn get_subscriptions(&self) -> Result<Vec<WebhookSubscription>, WebhookStoreError> {
+        self.store.get_subscriptions().await
+    }
+
+    async fn remove_subscription(&self, id: &uuid::Uuid) -> Result<(), WebhookStoreError> {
+        self.store.remove_subscription(id).await
+    }
+
+    async fn enqueue_event(&self, event: &WebhookEvent) -> Result<(), WebhookStoreError> {
+        if event.event_type == WebhookEventType::UserLoggedIn
+            || event.email == self.slow_signup_email
+        {
+            self.barrier.wait().await;
+        }
+        self.store.enqueue_event(event).await
+    }
+
+    async fn claim_due_deliveries(
+        &self,
+        limit: u32,
+        lease_until: DateTime<Utc>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        self.store.claim_due_deliveries(limit, lease_until).await
+    }
+
+    async fn record_attempt(
+        &self,
+        attempt: WebhookDeliveryAttempt,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<(), WebhookStoreError> {
+        self.store.record_attempt(attempt, retry_at).await
+    }
+
+    async fn get_delivery_log(
+        &self,
+        subscription_id: &uuid::Uuid,
+    ) -> Result<Vec<WebhookDeliveryLogEntry>, WebhookStoreError> {
+        self.store.get_delivery_log(subscription_id).await
+    }
+}
+
+#[tokio::test]
+async fn should_not_serialize_logins_behind_each_other_or_a_signup() {
+    const LOGINS: usize = 5;
+    // Every login and the slow signup have to be in each store together to get
+    // past its barrier, which a lock held across any one of them would prevent
+    let slow_signup_email = Email::parse(get_random_email()).unwrap();
+
+    let mut app = TestApp::new_with_stores(
+        |store| {
+            Arc::new(BarrierUserStore {
+                store,
+                barrier: Arc::new(Barrier::new(LOGINS + 1)),
+                slow_signup_email: slow_signup_email.clone(),
+            })
+        },
+        |store| {
+            Arc::new(BarrierWebhookStore {
+                store,
+                barrier: Arc::new(Barrier::new(LOGINS + 1)),
+                slow_signup_email: slow_signup_email.clone(),
+            })
+        },
+    )
+    .await;
+
+    let emails: Vec<_> = (0..LOGINS).map(|_| get_random_email()).collect();
+    for email in &emails {
+        let signup_body = serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        });
+        assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+    }
+
+    let slow_signup_body = serde_json::json!({
+        "email": slow_signup_email.as_ref(),
+        "password": "password123",
+        "requires2FA": false
+    });
+    let login_bodies: Vec<_> = emails
+        .iter()
+        .map(|email| serde_json::json!({ "email": email, "password": "password123" }))
+        .collect();
+
+    let (signup_response, login_responses) = tokio::time::timeout(Duration::from_secs(10), async {
+        tokio::join!(
+            app.post_signup(&slow_signup_body),
+            join_all(login_bodies.iter().map(|body| app.post_login(body)))
+        )
+    })
+    .await
+    .expect("Requests waited on each other");
+
+    assert_eq!(signup_response.status().as_u16(), 201);
+    for response in login_responses {
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
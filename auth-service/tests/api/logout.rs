@@ -35,7 +35,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(!auth_cookie.value().is_empty());
 
-    let token = auth_cookie.value();
+    let token = auth_cookie.value().to_owned();
 
     let response = app.post_logout().await;
 
@@ -48,15 +48,47 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     assert!(auth_cookie.value().is_empty());
 
-    let banned_token_store = app.banned_token_store.read().await;
-    let contains_token = banned_token_store
-        .contains_token(token)
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_other_sessions_signed_in() {
+    let mut app = TestApp::new().await;
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let other_session = app
+        .post_login(&login_body)
         .await
-        .expect("Failed to check if token is banned");
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
 
-    
-    assert!(contains_token);
-    drop(banned_token_store);
     app.clean_up().await;
 }
 
@@ -1,7 +1,18 @@
+mod audit_events;
+mod breached_passwords;
+mod change_password;
+mod cors;
+mod database_stores;
+mod dev_mailbox;
 mod helpers;
 mod login;
 mod logout;
+mod phone_number;
+mod rate_limit;
+mod revocation_cache;
 mod root;
 mod signup;
+mod store_conformance;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
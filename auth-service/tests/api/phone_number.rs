@@ -0,0 +1,460 @@
+use auth_service::{
+    domain::{Email, PhoneNumber, RateLimitPolicy, TwoFACodePurpose},
+    routes::{AddPhoneNumberResponse, TwoFactorAuthResponse},
+    ErrorResponse,
+};
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{default_rate_limit_config, get_random_email, TestApp};
+
+const PHONE_NUMBER: &str = "+1 (415) 555-0123";
+const OTHER_PHONE_NUMBER: &str = "+1 (415) 555-0199";
+const SMS_PATH: &str = "/2010-04-01/Accounts/ACtest/Messages.json";
+
+fn phone_verification(phone_number: &str) -> TwoFACodePurpose {
+    TwoFACodePurpose::PhoneVerification(PhoneNumber::parse(phone_number.to_owned()).unwrap())
+}
+
+/// Signs up a user with 2FA enabled and logs them in with the emailed code.
+async fn sign_up_and_log_in(app: &TestApp) -> String {
+    let email = get_random_email();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let (login_attempt_id, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
+        .await
+        .unwrap();
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code.as_ref()
+    });
+    assert_eq!(
+        app.post_verify_2fa(&verify_body).await.status().as_u16(),
+        200
+    );
+
+    email
+}
+
+async fn enroll_phone_number(app: &TestApp, email: &str) -> AddPhoneNumberResponse {
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<AddPhoneNumberResponse>()
+        .await
+        .expect("Could not deserialize response body to AddPhoneNumberResponse");
+
+    let (verification_id, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(email.to_owned()).unwrap(),
+            &phone_verification(PHONE_NUMBER),
+        )
+        .await
+        .unwrap();
+    assert_eq!(verification_id.as_ref(), body.verification_id);
+
+    let verify_body = serde_json::json!({
+        "verificationId": body.verification_id,
+        "code": code.as_ref()
+    });
+    assert_eq!(
+        app.post_verify_phone_number(&verify_body)
+            .await
+            .status()
+            .as_u16(),
+        200
+    );
+
+    body
+}
+
+#[tokio::test]
+async fn should_text_verification_code_to_new_phone_number() {
+    let mut app = TestApp::new().await;
+
+    sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .and(method("POST"))
+        .and(body_string_contains("To=%2B14155550123"))
+        .and(body_string_contains("verification+code"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<AddPhoneNumberResponse>()
+        .await
+        .expect("Could not deserialize response body to AddPhoneNumberResponse");
+    assert_eq!(body.message, "Verification code sent");
+    assert!(!body.verification_id.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_by_sms_once_phone_number_is_verified() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    // The enrollment code, then the sign-in code
+    Mock::given(path(SMS_PATH))
+        .and(method("POST"))
+        .and(body_string_contains("To=%2B14155550123"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(2)
+        .mount(&app.sms_server)
+        .await;
+
+    enroll_phone_number(&app, &email).await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert!(!body.login_attempt_id.is_empty());
+
+    // Only the code from the first sign-in went out by email
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 0);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_texting_verified_number_until_new_one_is_verified() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    // The enrollment code, then the sign-in code
+    Mock::given(path(SMS_PATH))
+        .and(method("POST"))
+        .and(body_string_contains("To=%2B14155550123"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(2)
+        .mount(&app.sms_server)
+        .await;
+    // Only the enrollment code
+    Mock::given(path(SMS_PATH))
+        .and(method("POST"))
+        .and(body_string_contains("To=%2B14155550199"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    enroll_phone_number(&app, &email).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": OTHER_PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_verification_code() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&app.sms_server)
+        .await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    let body = response.json::<AddPhoneNumberResponse>().await.unwrap();
+
+    let (_, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(email).unwrap(),
+            &phone_verification(PHONE_NUMBER),
+        )
+        .await
+        .unwrap();
+    let wrong_code = if code.as_ref() == "123456" {
+        "654321"
+    } else {
+        "123456"
+    };
+
+    let verify_body = serde_json::json!({
+        "verificationId": body.verification_id,
+        "code": wrong_code
+    });
+    let response = app.post_verify_phone_number(&verify_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_pending_login_code_when_enrolling() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&app.sms_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+    let (login_attempt_id, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
+        .await
+        .unwrap();
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let verify_body = serde_json::json!({
+        "email": email,
+        "loginAttemptId": login_attempt_id.as_ref(),
+        "2FACode": code.as_ref()
+    });
+    assert_eq!(
+        app.post_verify_2fa(&verify_body).await.status().as_u16(),
+        200
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_code_was_sent_to_another_number() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&app.sms_server)
+        .await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+    let body = response.json::<AddPhoneNumberResponse>().await.unwrap();
+    let (_, code) = app
+        .two_fa_code_store
+        .get_code(
+            &Email::parse(email).unwrap(),
+            &phone_verification(PHONE_NUMBER),
+        )
+        .await
+        .unwrap();
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": OTHER_PHONE_NUMBER }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let verify_body = serde_json::json!({
+        "verificationId": body.verification_id,
+        "code": code.as_ref()
+    });
+    let response = app.post_verify_phone_number(&verify_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_account_requests_too_many_codes() {
+    let mut config = default_rate_limit_config();
+    config.email_policy = Some(RateLimitPolicy::new(2, 0.01));
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(2)
+        .mount(&app.sms_server)
+        .await;
+
+    // Different numbers, so that only the account ties the requests together
+    for phone_number in [PHONE_NUMBER, OTHER_PHONE_NUMBER] {
+        let response = app
+            .post_phone_number(&serde_json::json!({ "phoneNumber": phone_number }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+1 (415) 555-0100" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_phone_number() {
+    let mut app = TestApp::new().await;
+
+    sign_up_and_log_in(&app).await;
+
+    for phone_number in ["", "4155550123", "+0123", "+1415555O123"] {
+        let response = app
+            .post_phone_number(&serde_json::json!({ "phoneNumber": phone_number }))
+            .await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            phone_number
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid phone number".to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": PHONE_NUMBER }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_switch_to_sms_without_verified_phone_number() {
+    let mut app = TestApp::new().await;
+
+    sign_up_and_log_in(&app).await;
+
+    let response = app
+        .post_two_fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Phone number not verified".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_switch_back_to_email() {
+    let mut app = TestApp::new().await;
+
+    let email = sign_up_and_log_in(&app).await;
+
+    Mock::given(path(SMS_PATH))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(1)
+        .mount(&app.sms_server)
+        .await;
+
+    enroll_phone_number(&app, &email).await;
+
+    let response = app
+        .post_two_fa_channel(&serde_json::json!({ "channel": "email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123"
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 206);
+
+    // Both sign-in codes went out by email
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 2);
+
+    app.clean_up().await;
+}
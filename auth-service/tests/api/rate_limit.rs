@@ -0,0 +1,162 @@
+use auth_service::{domain::RateLimitPolicy, ErrorResponse};
+use uuid::Uuid;
+
+use crate::helpers::{default_rate_limit_config, get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_return_429_with_retry_after_if_ip_limit_exceeded() {
+    let mut config = default_rate_limit_config();
+    config.ip_policy = RateLimitPolicy::new(2, 0.01);
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    for _ in 0..2 {
+        let response = app.post_logout().await;
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    let retry_after: u64 = response
+        .headers()
+        .get("retry-after")
+        .expect("No Retry-After header found")
+        .to_str()
+        .unwrap()
+        .parse()
+        .expect("Retry-After is not a number of seconds");
+
+    assert!(retry_after > 0);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+
+    // Buckets are kept per route
+    let response = app.post_login(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_share_bucket_across_route_parameters() {
+    let mut config = default_rate_limit_config();
+    config.ip_policy = RateLimitPolicy::new(2, 0.01);
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    for _ in 0..2 {
+        let response = app
+            .delete_webhook(&Uuid::new_v4().to_string(), "wrong-token")
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .delete_webhook(&Uuid::new_v4().to_string(), "wrong-token")
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_limit_verify_token() {
+    let mut config = default_rate_limit_config();
+    config.ip_policy = RateLimitPolicy::new(2, 0.01);
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    let verify_token_body = serde_json::json!({
+        "token": "invalid_token",
+    });
+
+    for _ in 0..5 {
+        let response = app.post_verify_token(&verify_token_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_email_limit_exceeded() {
+    let mut config = default_rate_limit_config();
+    config.email_policy = Some(RateLimitPolicy::new(1, 0.01));
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert!(response.headers().get("retry-after").is_some());
+
+    // Email matching is case insensitive
+    let login_body = serde_json::json!({
+        "email": random_email.to_uppercase(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Other emails from the same IP are unaffected
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_by_forwarded_ip_if_peer_is_trusted_proxy() {
+    let mut config = default_rate_limit_config();
+    config.ip_policy = RateLimitPolicy::new(1, 0.01);
+    config.trusted_proxies = vec!["127.0.0.1/32".parse().unwrap()];
+    let mut app = TestApp::new_with_rate_limit(config).await;
+
+    let post_logout_from = |ip: &'static str| {
+        app.http_client
+            .post(format!("{}/logout", &app.address))
+            .header("X-Forwarded-For", ip)
+            .send()
+    };
+
+    let response = post_logout_from("198.51.100.1").await.unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = post_logout_from("198.51.100.1").await.unwrap();
+    assert_eq!(response.status().as_u16(), 429);
+
+    let response = post_logout_from("198.51.100.2").await.unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
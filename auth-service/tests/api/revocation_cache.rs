@@ -0,0 +1,122 @@
+use std::{sync::Arc, time::Duration};
+
+use auth_service::{
+    app_state::BannedTokenStoreType,
+    domain::BannedTokenStore,
+    services::{
+        data_stores::PostgresBannedTokenStore,
+        postgres_revocation_broadcaster::PostgresRevocationBroadcaster,
+        revocation_cache::{CachedBannedTokenStore, RevocationBroadcaster, RevocationCache},
+    },
+    utils::constants::JWT_COOKIE_NAME,
+};
+
+use crate::helpers::{get_random_email, TestApp, TestDatabase};
+
+/// Long enough that only a broadcast can explain an instance seeing a revocation.
+const CACHE_TTL_SECS: u64 = 600;
+
+async fn spawn_app(backend: &str) -> TestApp {
+    TestApp::new_with_settings(|settings| {
+        settings.stores.backend = backend.to_owned();
+        settings.revocation_cache.ttl_secs = CACHE_TTL_SECS;
+    })
+    .await
+}
+
+/// Polls until `check` passes, as broadcasts arrive asynchronously.
+async fn eventually<F, Fut>(mut check: F) -> bool
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    for _ in 0..50 {
+        if check().await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+#[tokio::test]
+async fn should_sign_out_on_every_instance_sharing_redis() {
+    let mut app = spawn_app("redis").await;
+    let mut other_instance = spawn_app("redis").await;
+    let email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&signup_body).await.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({ "email": email, "password": "password123" });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+    let verify_token_body = serde_json::json!({ "token": token });
+
+    // Caches that the token is not banned
+    let response = other_instance.post_verify_token(&verify_token_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    assert!(
+        eventually(|| async {
+            other_instance
+                .post_verify_token(&verify_token_body)
+                .await
+                .status()
+                .as_u16()
+                == 401
+        })
+        .await
+    );
+
+    app.clean_up().await;
+    other_instance.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_broadcast_revocations_over_postgres() {
+    let mut app = spawn_app("database").await;
+    // SQLite is only used by a single instance, so it has nothing to broadcast to
+    let TestDatabase::Postgres { pool, .. } = &app.database else {
+        app.clean_up().await;
+        return;
+    };
+
+    let instance = || {
+        let store: BannedTokenStoreType = Arc::new(PostgresBannedTokenStore::new(
+            pool.clone(),
+            app.settings.auth.token_ttl(),
+        ));
+        let cache = Arc::new(RevocationCache::new(
+            100,
+            Duration::from_secs(CACHE_TTL_SECS),
+        ));
+        let broadcaster = Arc::new(PostgresRevocationBroadcaster::new(pool.clone()));
+        tokio::spawn(broadcaster.clone().listen(cache.clone()));
+        CachedBannedTokenStore::new(store, cache, Some(broadcaster))
+    };
+    let first = instance();
+    let second = instance();
+    // Give the listeners time to start listening
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    assert!(!second.contains_token("token").await.unwrap());
+
+    first.add_token("token".to_owned()).await.unwrap();
+
+    assert!(eventually(|| async { second.contains_token("token").await.unwrap() }).await);
+
+    app.clean_up().await;
+}
@@ -1,4 +1,5 @@
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use auth_service::{domain::PasswordPolicyViolation, routes::SignupResponse, ErrorResponse};
+use futures_util::future::join_all;
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -37,19 +38,12 @@ async fn should_return_201_if_valid_input() {
 async fn should_return_400_if_invalid_input() {
     let mut app = TestApp::new().await;
 
-    let random_email = get_random_email();
-
     let input = [
         serde_json::json!({
             "email": "",
             "password": "password123",
             "requires2FA": true
         }),
-        serde_json::json!({
-            "email": random_email,
-            "password": "",
-            "requires2FA": true
-        }),
         serde_json::json!({
             "email": "",
             "password": "",
@@ -60,11 +54,6 @@ async fn should_return_400_if_invalid_input() {
             "password": "password123",
             "requires2FA": true
         }),
-        serde_json::json!({
-            "email": random_email,
-            "password": "invalid",
-            "requires2FA": true
-        }),
     ];
 
     for i in input.iter() {
@@ -84,6 +73,92 @@ async fn should_return_400_if_invalid_input() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_400_with_violations_if_password_breaks_policy() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "invalid",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Password does not meet the policy");
+    assert_eq!(
+        body.violations,
+        vec![PasswordPolicyViolation::TooShort { min_length: 8 }]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_password_allowed_by_shorter_policy() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.password_policy.min_length = 4;
+    })
+    .await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "q7#Zx",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_password_is_too_weak() {
+    let mut app = TestApp::new_with_settings(|settings| {
+        settings.password_policy.min_strength_score = 3;
+    })
+    .await;
+
+    let weak_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&weak_body).await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .violations,
+        vec![PasswordPolicyViolation::TooWeak {
+            score: 1,
+            min_score: 3
+        }]
+    );
+
+    let strong_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "correct horse battery staple",
+        "requires2FA": true
+    });
+
+    assert_eq!(app.post_signup(&strong_body).await.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_409_if_email_already_exists() {
     let mut app = TestApp::new().await;
@@ -116,6 +191,28 @@ async fn should_return_409_if_email_already_exists() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_create_user_once_if_signed_up_concurrently() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let responses = join_all((0..10).map(|_| app.post_signup(&signup_body))).await;
+    let mut statuses: Vec<_> = responses
+        .iter()
+        .map(|response| response.status().as_u16())
+        .collect();
+    statuses.sort();
+
+    assert_eq!(statuses, [201, 409, 409, 409, 409, 409, 409, 409, 409, 409]);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_422_if_malformed_input() {
     let mut app = TestApp::new().await;
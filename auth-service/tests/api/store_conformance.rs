@@ -0,0 +1,586 @@
+//! Behaviour every implementation of the store traits must share, checked
+//! against each of them. Identifiers are random, so that stores backed by a
+//! shared server can be checked in parallel.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+
+use auth_service::{
+    app_state::BannedTokenStoreType,
+    domain::{
+        BannedTokenStore, Email, EmailMessage, EmailOutboxStore, EmailQueueDepth, LoginAttemptId,
+        OutboxEmail, Password, PhoneNumber, TokenEpoch, TwoFAChannel, TwoFACode, TwoFACodePurpose,
+        TwoFACodeStore, TwoFACodeStoreError, User, UserStore, UserStoreError,
+    },
+    get_redis_connection,
+    services::{
+        data_stores::{
+            HashmapEmailOutboxStore, HashmapTwoFACodeStore, HashmapUserStore,
+            HashsetBannedTokenStore, PostgresBannedTokenStore, PostgresEmailOutboxStore,
+            PostgresTwoFACodeStore, PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore,
+            SqliteBannedTokenStore, SqliteTwoFACodeStore, SqliteUserStore,
+        },
+        revocation_cache::{CachedBannedTokenStore, RevocationCache},
+    },
+};
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp, TestDatabase};
+
+const PASSWORD_HISTORY_SIZE: usize = 3;
+const TOKEN_TTL: Duration = Duration::from_secs(600);
+
+fn random_email() -> Email {
+    Email::parse(get_random_email()).unwrap()
+}
+
+fn password(password: &str) -> Password {
+    Password::parse(Secret::new(password.to_owned())).unwrap()
+}
+
+fn phone_number(phone_number: &str) -> PhoneNumber {
+    PhoneNumber::parse(phone_number.to_owned()).unwrap()
+}
+
+async fn check_user_store(store: impl UserStore) {
+    let email = random_email();
+    let unknown_email = random_email();
+    let user = User::new(email.clone(), password("password123"), true);
+
+    assert_eq!(store.add_user(user.clone()).await, Ok(()));
+    assert_eq!(
+        store.add_user(user).await,
+        Err(UserStoreError::UserAlreadyExists)
+    );
+
+    // The password may come back hashed, so only the other fields are compared
+    let stored = store.get_user(&email).await.unwrap();
+    assert_eq!(stored.email, email);
+    assert!(stored.requires_2fa);
+    assert_eq!(stored.phone_number, None);
+    assert!(!stored.phone_verified);
+    assert_eq!(stored.pending_phone_number, None);
+    assert_eq!(stored.two_fa_channel, TwoFAChannel::Email);
+    assert_eq!(
+        store.get_user(&unknown_email).await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    assert_eq!(
+        store.validate_user(&email, &password("password123")).await,
+        Ok(())
+    );
+    assert_eq!(
+        store
+            .validate_user(&email, &password("wrong-password"))
+            .await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+    assert_eq!(
+        store
+            .validate_user(&unknown_email, &password("password123"))
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    assert_eq!(
+        store.update_password(&email, password("password123")).await,
+        Err(UserStoreError::PasswordReused)
+    );
+    assert_eq!(
+        store.update_password(&email, password("password456")).await,
+        Ok(())
+    );
+    assert_eq!(
+        store.validate_user(&email, &password("password456")).await,
+        Ok(())
+    );
+    assert_eq!(
+        store.validate_user(&email, &password("password123")).await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+    assert_eq!(
+        store.update_password(&email, password("password123")).await,
+        Err(UserStoreError::PasswordReused)
+    );
+    assert_eq!(
+        store
+            .update_password(&unknown_email, password("password789"))
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+
+    let number = phone_number("+14155550100");
+    assert_eq!(
+        store.set_pending_phone_number(&email, number.clone()).await,
+        Ok(())
+    );
+    assert_eq!(
+        store
+            .verify_phone_number(&email, &phone_number("+14155550199"))
+            .await,
+        Err(UserStoreError::PhoneNumberMismatch)
+    );
+    assert_eq!(store.verify_phone_number(&email, &number).await, Ok(()));
+    assert_eq!(
+        store.set_two_fa_channel(&email, TwoFAChannel::Sms).await,
+        Ok(())
+    );
+    let stored = store.get_user(&email).await.unwrap();
+    assert_eq!(stored.phone_number, Some(number.clone()));
+    assert!(stored.phone_verified);
+    assert_eq!(stored.pending_phone_number, None);
+    assert_eq!(stored.two_fa_channel, TwoFAChannel::Sms);
+
+    // The verified number keeps receiving codes until a new one is verified
+    let new_number = phone_number("+14155550123");
+    assert_eq!(
+        store
+            .set_pending_phone_number(&email, new_number.clone())
+            .await,
+        Ok(())
+    );
+    let stored = store.get_user(&email).await.unwrap();
+    assert_eq!(stored.sms_phone_number(), Some(&number));
+    assert_eq!(stored.pending_phone_number, Some(new_number.clone()));
+    assert_eq!(
+        store.verify_phone_number(&email, &number).await,
+        Err(UserStoreError::PhoneNumberMismatch)
+    );
+    assert_eq!(store.verify_phone_number(&email, &new_number).await, Ok(()));
+    let stored = store.get_user(&email).await.unwrap();
+    assert_eq!(stored.sms_phone_number(), Some(&new_number));
+    assert_eq!(stored.pending_phone_number, None);
+
+    assert_eq!(
+        store
+            .set_pending_phone_number(&unknown_email, new_number.clone())
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store.verify_phone_number(&unknown_email, &new_number).await,
+        Err(UserStoreError::UserNotFound)
+    );
+    assert_eq!(
+        store
+            .set_two_fa_channel(&unknown_email, TwoFAChannel::Sms)
+            .await,
+        Err(UserStoreError::UserNotFound)
+    );
+}
+
+async fn check_banned_token_store(store: impl BannedTokenStore) {
+    let token = Uuid::new_v4().to_string();
+    let other_token = Uuid::new_v4().to_string();
+
+    assert!(!store.contains_token(&token).await.unwrap());
+    store.add_token(token.clone()).await.unwrap();
+    store.add_token(token.clone()).await.unwrap();
+    assert!(store.contains_token(&token).await.unwrap());
+    assert!(!store.contains_token(&other_token).await.unwrap());
+
+    let email = random_email();
+    let other_email = random_email();
+
+    let epoch = TokenEpoch {
+        epoch: 100,
+        exempt_jti: None,
+    };
+    let later_epoch = TokenEpoch {
+        epoch: 200,
+        exempt_jti: Some(token.clone()),
+    };
+
+    assert_eq!(store.get_token_epoch(&email).await.unwrap(), None);
+    store.set_token_epoch(&email, epoch.clone()).await.unwrap();
+    assert_eq!(store.get_token_epoch(&email).await.unwrap(), Some(epoch));
+    store
+        .set_token_epoch(&email, later_epoch.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_token_epoch(&email).await.unwrap(),
+        Some(later_epoch)
+    );
+    assert_eq!(store.get_token_epoch(&other_email).await.unwrap(), None);
+}
+
+async fn check_two_fa_code_store(store: impl TwoFACodeStore) {
+    let email = random_email();
+    let other_email = random_email();
+    let login = TwoFACodePurpose::Login;
+
+    assert_eq!(
+        store.get_code(&email, &login).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(
+            email.clone(),
+            &login,
+            login_attempt_id.clone(),
+            code.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_code(&email, &login).await,
+        Ok((login_attempt_id, code))
+    );
+
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(
+            email.clone(),
+            &login,
+            login_attempt_id.clone(),
+            code.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_code(&email, &login).await,
+        Ok((login_attempt_id.clone(), code.clone()))
+    );
+    assert_eq!(
+        store.get_code(&other_email, &login).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    // Codes for other purposes are kept apart from the login code
+    let phone_verification = TwoFACodePurpose::PhoneVerification(phone_number("+14155550123"));
+    let other_phone_verification =
+        TwoFACodePurpose::PhoneVerification(phone_number("+14155550199"));
+    let verification_id = LoginAttemptId::default();
+    let verification_code = TwoFACode::default();
+    store
+        .add_code(
+            email.clone(),
+            &phone_verification,
+            verification_id.clone(),
+            verification_code.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        store.get_code(&email, &login).await,
+        Ok((login_attempt_id, code))
+    );
+    assert_eq!(
+        store.get_code(&email, &other_phone_verification).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    assert_eq!(store.remove_code(&email, &login).await, Ok(()));
+    assert_eq!(
+        store.get_code(&email, &login).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+    assert_eq!(
+        store.get_code(&email, &phone_verification).await,
+        Ok((verification_id.clone(), verification_code.clone()))
+    );
+    assert_eq!(store.remove_code(&email, &login).await, Ok(()));
+    assert_eq!(store.remove_code(&other_email, &login).await, Ok(()));
+
+    // Only a matching code is taken, and only once
+    let wrong_code = TwoFACode::parse(Secret::new(
+        if verification_code.as_ref() == "123456" {
+            "654321"
+        } else {
+            "123456"
+        }
+        .to_owned(),
+    ))
+    .unwrap();
+    assert_eq!(
+        store
+            .take_code(&email, &phone_verification, &verification_id, &wrong_code)
+            .await,
+        Ok(false)
+    );
+    assert_eq!(
+        store
+            .take_code(&email, &login, &verification_id, &verification_code)
+            .await,
+        Ok(false)
+    );
+    assert_eq!(
+        store
+            .take_code(
+                &email,
+                &phone_verification,
+                &verification_id,
+                &verification_code
+            )
+            .await,
+        Ok(true)
+    );
+    assert_eq!(
+        store
+            .take_code(
+                &email,
+                &phone_verification,
+                &verification_id,
+                &verification_code
+            )
+            .await,
+        Ok(false)
+    );
+    assert_eq!(
+        store.get_code(&email, &phone_verification).await,
+        Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+    );
+
+    // Of two requests racing to take the same code, only one succeeds
+    let login_attempt_id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    store
+        .add_code(
+            email.clone(),
+            &login,
+            login_attempt_id.clone(),
+            code.clone(),
+        )
+        .await
+        .unwrap();
+    let (first, second) = tokio::join!(
+        store.take_code(&email, &login, &login_attempt_id, &code),
+        store.take_code(&email, &login, &login_attempt_id, &code),
+    );
+    assert_eq!(
+        [first.unwrap(), second.unwrap()]
+            .into_iter()
+            .filter(|taken| *taken)
+            .count(),
+        1
+    );
+}
+
+async fn check_email_outbox_store(store: impl EmailOutboxStore) {
+    let email = || {
+        OutboxEmail::new(EmailMessage {
+            recipient: random_email(),
+            subject: "Subject".to_owned(),
+            html_body: "<p>Content</p>".to_owned(),
+            text_body: "Content".to_owned(),
+        })
+    };
+    let (pending, sent, dead_lettered) = (email(), email(), email());
+    for email in [&pending, &sent, &dead_lettered] {
+        store.enqueue_email(email.clone()).await.unwrap();
+    }
+    store.mark_sent(&sent.id).await.unwrap();
+    store
+        .mark_failed(&dead_lettered.id, "HTTP 500", None)
+        .await
+        .unwrap();
+
+    let an_hour_ago = Utc::now() - Duration::from_secs(3600);
+    assert_eq!(store.delete_finished(an_hour_ago).await.unwrap(), 0);
+
+    let in_an_hour = Utc::now() + Duration::from_secs(3600);
+    assert_eq!(store.delete_finished(in_an_hour).await.unwrap(), 2);
+    assert_eq!(
+        store.queue_depth().await.unwrap(),
+        EmailQueueDepth {
+            pending: 1,
+            dead_lettered: 0,
+        }
+    );
+
+    let due = store.claim_due_emails(10, Utc::now()).await.unwrap();
+    assert_eq!(due, vec![pending]);
+}
+
+#[tokio::test]
+async fn hashmap_user_store_conforms() {
+    check_user_store(HashmapUserStore::new(PASSWORD_HISTORY_SIZE)).await;
+}
+
+#[tokio::test]
+async fn database_user_store_conforms() {
+    let mut app = TestApp::new().await;
+    let hasher = app
+        .settings
+        .password_hashing
+        .hasher()
+        .expect("Failed to build password hasher");
+
+    match &app.database {
+        TestDatabase::Postgres { pool, .. } => {
+            check_user_store(PostgresUserStore::new(
+                pool.clone(),
+                hasher,
+                PASSWORD_HISTORY_SIZE,
+            ))
+            .await
+        }
+        TestDatabase::Sqlite { pool, .. } => {
+            check_user_store(SqliteUserStore::new(
+                pool.clone(),
+                hasher,
+                PASSWORD_HISTORY_SIZE,
+            ))
+            .await
+        }
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn database_user_store_only_reports_mismatches_as_invalid_credentials() {
+    let mut app = TestApp::new().await;
+    let mut hashing = app.settings.password_hashing.clone();
+    hashing.pepper = Some(Secret::new("pepper".to_owned()));
+    let peppered = hashing.hasher().expect("Failed to build password hasher");
+    hashing.pepper = None;
+    let unpeppered = hashing.hasher().expect("Failed to build password hasher");
+
+    let (store, store_without_pepper): (Box<dyn UserStore>, Box<dyn UserStore>) =
+        match &app.database {
+            TestDatabase::Postgres { pool, .. } => (
+                Box::new(PostgresUserStore::new(
+                    pool.clone(),
+                    peppered,
+                    PASSWORD_HISTORY_SIZE,
+                )),
+                Box::new(PostgresUserStore::new(
+                    pool.clone(),
+                    unpeppered,
+                    PASSWORD_HISTORY_SIZE,
+                )),
+            ),
+            TestDatabase::Sqlite { pool, .. } => (
+                Box::new(SqliteUserStore::new(
+                    pool.clone(),
+                    peppered,
+                    PASSWORD_HISTORY_SIZE,
+                )),
+                Box::new(SqliteUserStore::new(
+                    pool.clone(),
+                    unpeppered,
+                    PASSWORD_HISTORY_SIZE,
+                )),
+            ),
+        };
+
+    let email = random_email();
+    let user = User::new(email.clone(), password("password123"), false);
+    store.add_user(user).await.unwrap();
+
+    assert_eq!(
+        store.validate_user(&email, &password("password124")).await,
+        Err(UserStoreError::InvalidCredentials)
+    );
+    // A hash that cannot be checked says nothing about the password
+    assert!(matches!(
+        store_without_pepper
+            .validate_user(&email, &password("password123"))
+            .await,
+        Err(UserStoreError::UnexpectedError(_))
+    ));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn hashset_banned_token_store_conforms() {
+    check_banned_token_store(HashsetBannedTokenStore::new(TOKEN_TTL)).await;
+}
+
+#[tokio::test]
+async fn redis_banned_token_store_conforms() {
+    let mut app = TestApp::new().await;
+    let conn = get_redis_connection(&app.settings.redis)
+        .await
+        .expect("Failed to connect to Redis");
+
+    check_banned_token_store(RedisBannedTokenStore::new(conn, TOKEN_TTL)).await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn database_banned_token_store_conforms() {
+    let mut app = TestApp::new().await;
+
+    match &app.database {
+        TestDatabase::Postgres { pool, .. } => {
+            check_banned_token_store(PostgresBannedTokenStore::new(pool.clone(), TOKEN_TTL)).await
+        }
+        TestDatabase::Sqlite { pool, .. } => {
+            check_banned_token_store(SqliteBannedTokenStore::new(pool.clone(), TOKEN_TTL)).await
+        }
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn cached_banned_token_store_conforms() {
+    let store: BannedTokenStoreType = Arc::new(HashsetBannedTokenStore::new(TOKEN_TTL));
+    let cache = Arc::new(RevocationCache::new(100, Duration::from_secs(30)));
+
+    check_banned_token_store(CachedBannedTokenStore::new(store, cache, None)).await;
+}
+
+#[tokio::test]
+async fn hashmap_two_fa_code_store_conforms() {
+    check_two_fa_code_store(HashmapTwoFACodeStore::default()).await;
+}
+
+#[tokio::test]
+async fn redis_two_fa_code_store_conforms() {
+    let mut app = TestApp::new().await;
+    let conn = get_redis_connection(&app.settings.redis)
+        .await
+        .expect("Failed to connect to Redis");
+
+    check_two_fa_code_store(RedisTwoFACodeStore::new(conn)).await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn database_two_fa_code_store_conforms() {
+    let mut app = TestApp::new().await;
+
+    match &app.database {
+        TestDatabase::Postgres { pool, .. } => {
+            check_two_fa_code_store(PostgresTwoFACodeStore::new(pool.clone())).await
+        }
+        TestDatabase::Sqlite { pool, .. } => {
+            check_two_fa_code_store(SqliteTwoFACodeStore::new(pool.clone())).await
+        }
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn hashmap_email_outbox_store_conforms() {
+    check_email_outbox_store(HashmapEmailOutboxStore::default()).await;
+}
+
+#[tokio::test]
+async fn postgres_email_outbox_store_conforms() {
+    let mut app = TestApp::new().await;
+    // SQLite deployments keep the outbox in memory
+    let TestDatabase::Postgres { pool, .. } = &app.database else {
+        app.clean_up().await;
+        return;
+    };
+
+    check_email_outbox_store(PostgresEmailOutboxStore::new(pool.clone())).await;
+
+    app.clean_up().await;
+}
@@ -1,5 +1,5 @@
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode},
+    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodePurpose},
     routes::TwoFactorAuthResponse,
     utils::constants::JWT_COOKIE_NAME,
     ErrorResponse,
@@ -40,6 +40,8 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -52,9 +54,10 @@ async fn should_return_200_if_correct_code() {
 
     let code_tuple = app
         .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
         .await
         .unwrap();
 
@@ -168,6 +171,8 @@ async fn should_return_401_if_incorrect_credentials() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -180,9 +185,10 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let code_tuple = app
         .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
         .await
         .unwrap();
 
@@ -274,6 +280,8 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -286,9 +294,10 @@ async fn should_return_401_if_old_code() {
 
     let code_tuple = app
         .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
         .await
         .unwrap();
 
@@ -300,6 +309,8 @@ async fn should_return_401_if_old_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     // 2FA attempt with old login_attempt_id and code
 
     let request_body = serde_json::json!({
@@ -347,6 +358,8 @@ async fn should_return_401_if_same_code_twice() {
 
     assert_eq!(response.status().as_u16(), 206);
 
+    assert_eq!(app.email_outbox_worker.deliver_due().await.unwrap(), 1);
+
     let response_body = response
         .json::<TwoFactorAuthResponse>()
         .await
@@ -359,9 +372,10 @@ async fn should_return_401_if_same_code_twice() {
 
     let code_tuple = app
         .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .get_code(
+            &Email::parse(random_email.clone()).unwrap(),
+            &TwoFACodePurpose::Login,
+        )
         .await
         .unwrap();
 
@@ -0,0 +1,267 @@
+use auth_service::{
+    routes::{CreateWebhookResponse, WebhookDeliveriesResponse, WebhooksResponse},
+    services::webhook_dispatcher::{sign_payload, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+    utils::constants::test,
+    ErrorResponse,
+};
+use secrecy::Secret;
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, MockServer, Request, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn create_webhook(app: &TestApp, url: String) -> CreateWebhookResponse {
+    let body = serde_json::json!({
+        "url": url,
+        "eventTypes": ["user.signed_up"],
+    });
+
+    let response = app.post_webhook(&body, test::ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateWebhookResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateWebhookResponse")
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn get_deliveries(app: &TestApp, id: &str) -> WebhookDeliveriesResponse {
+    let response = app.get_webhook_deliveries(id, test::ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<WebhookDeliveriesResponse>()
+        .await
+        .expect("Could not deserialize response body to WebhookDeliveriesResponse")
+}
+
+struct ValidSignatureMatcher(Secret<String>);
+
+impl wiremock::Match for ValidSignatureMatcher {
+    fn matches(&self, request: &Request) -> bool {
+        let header = |name: &str| request.headers.get(name)?.to_str().ok();
+        let (Some(signature), Some(timestamp)) =
+            (header(SIGNATURE_HEADER), header(TIMESTAMP_HEADER))
+        else {
+            return false;
+        };
+        let payload = String::from_utf8_lossy(&request.body);
+
+        signature == format!("sha256={}", sign_payload(&self.0, timestamp, &payload))
+    }
+}
+
+#[tokio::test]
+async fn should_deliver_signed_signup_event() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+
+    let webhook = create_webhook(&app, format!("{}/hooks", receiver.uri())).await;
+
+    Mock::given(method("POST"))
+        .and(path("/hooks"))
+        .and(header("X-Webhook-Event", "user.signed_up"))
+        .and(ValidSignatureMatcher(Secret::new(webhook.secret.clone())))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let dispatched = app.webhook_dispatcher.dispatch_due().await.unwrap();
+    assert_eq!(dispatched, 1);
+
+    let requests = receiver.received_requests().await.unwrap();
+    let payload: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(payload["type"], "user.signed_up");
+    assert_eq!(payload["data"]["email"], random_email);
+
+    let deliveries = get_deliveries(&app, &webhook.subscription.id.to_string()).await;
+    assert_eq!(deliveries.deliveries.len(), 1);
+    assert_eq!(deliveries.deliveries[0].status, "delivered");
+    assert_eq!(deliveries.deliveries[0].response_status, Some(204));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deliver_login_event() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+
+    let body = serde_json::json!({
+        "url": receiver.uri(),
+        "eventTypes": ["user.logged_in"],
+    });
+    let response = app.post_webhook(&body, test::ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    assert_eq!(app.post_login(&login_body).await.status().as_u16(), 200);
+
+    assert_eq!(app.webhook_dispatcher.dispatch_due().await.unwrap(), 1);
+
+    let requests = receiver.received_requests().await.unwrap();
+    let payload: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(payload["type"], "user.logged_in");
+    assert_eq!(payload["data"]["email"], random_email);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_retry_failed_deliveries_until_attempts_are_exhausted() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+
+    let webhook = create_webhook(&app, receiver.uri()).await;
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(u64::from(test::webhooks::MAX_ATTEMPTS))
+        .mount(&receiver)
+        .await;
+
+    signup(&app, &get_random_email()).await;
+
+    for _ in 0..test::webhooks::MAX_ATTEMPTS {
+        assert_eq!(app.webhook_dispatcher.dispatch_due().await.unwrap(), 1);
+    }
+    assert_eq!(app.webhook_dispatcher.dispatch_due().await.unwrap(), 0);
+
+    let deliveries = get_deliveries(&app, &webhook.subscription.id.to_string()).await;
+    assert_eq!(
+        deliveries.deliveries.len(),
+        test::webhooks::MAX_ATTEMPTS as usize
+    );
+    assert!(deliveries
+        .deliveries
+        .iter()
+        .all(|delivery| delivery.status == "failed" && delivery.response_status == Some(500)));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_deliver_to_deleted_webhooks() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+
+    let webhook = create_webhook(&app, receiver.uri()).await;
+
+    let response = app.get_webhooks(test::ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let webhooks = response.json::<WebhooksResponse>().await.unwrap();
+    assert_eq!(webhooks.webhooks.len(), 1);
+    assert_eq!(webhooks.webhooks[0].id, webhook.subscription.id);
+
+    let id = webhook.subscription.id.to_string();
+    let response = app.delete_webhook(&id, test::ADMIN_API_TOKEN).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&receiver)
+        .await;
+
+    signup(&app, &get_random_email()).await;
+    assert_eq!(app.webhook_dispatcher.dispatch_due().await.unwrap(), 0);
+
+    let response = app.get_webhooks(test::ADMIN_API_TOKEN).await;
+    let webhooks = response.json::<WebhooksResponse>().await.unwrap();
+    assert!(webhooks.webhooks.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({ "url": "not a url", "eventTypes": ["user.signed_up"] }),
+        serde_json::json!({ "url": "ftp://example.com", "eventTypes": ["user.signed_up"] }),
+        serde_json::json!({ "url": "https://example.com", "eventTypes": [] }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_webhook(test_case, test::ADMIN_API_TOKEN).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid credentials".to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_event_type_is_never_emitted() {
+    let mut app = TestApp::new().await;
+
+    for event_type in ["user.verified", "user.deleted", "user.email_changed"] {
+        let body = serde_json::json!({
+            "url": "https://example.com",
+            "eventTypes": ["user.signed_up", event_type],
+        });
+        let response = app.post_webhook(&body, test::ADMIN_API_TOKEN).await;
+
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for event type: {}",
+            event_type
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_admin_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_webhooks("wrong-token").await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}